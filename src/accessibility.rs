@@ -0,0 +1,64 @@
+/// Get `name` without the letter marking it, like "Bedroom" for "(B)edroom" or "Cabaña" for
+/// "Cabaña (C)".
+///
+/// This is the one place letter markers are taken out of names, so everything that reads names
+/// without their letters agrees on what a marker is.
+pub fn plain_name(name: &str) -> String {
+    name.split_whitespace()
+        .filter(|word| !(word.chars().count() == 3 && word.starts_with('(') && word.ends_with(')')))
+        .map(|word| word.replace(['(', ')'], ""))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Testing for screen reader mode.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::console::*;
+    use crate::game::game_state::*;
+    use crate::game::victim_user::*;
+    use crate::util::*;
+
+    /// Letters are taken out of names, wherever they are.
+    #[test]
+    fn plain_names() {
+        assert_eq!(plain_name("(B)edroom"), "Bedroom");
+        assert_eq!(plain_name("(L)ake Misty"), "Lake Misty");
+        assert_eq!(plain_name("Local (V)ersus"), "Local Versus");
+        assert_eq!(plain_name("Cabaña (C)"), "Cabaña");
+        assert_eq!(
+            plain_name("(H) Organizar una partida"),
+            "Organizar una partida"
+        );
+        assert_eq!(plain_name("Baño (Ñ)"), "Baño");
+    }
+
+    /// Screen readers get numbered choices, one line about each round and no ASCII art.
+    #[test]
+    fn screen_reader_mode() {
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.hide_part(1, 0);
+        state.play((0, 0), (2, 1));
+
+        let (mut console, output) = Console::scripted(&["2", "4"]);
+        console.set_accessible(true);
+        print_title_screen(&mut console);
+        assert_eq!(play_victim(&mut console, &state), (1, 3));
+        print_win(&mut console);
+        print_lose(&mut console);
+
+        let text = output.text();
+        assert!(text.contains("Welcome to Camp Misty."));
+        assert!(text.contains("Round 2. You found a car part. Car parts found: 1 of 2."));
+        assert!(text.contains("1. Cabin\n2. Lake Misty\n"));
+        assert!(!text.contains('('));
+        assert!(!text.contains('█'));
+
+        // Other consoles are left alone
+        let (mut console, output) = Console::scripted(&[]);
+        print_win(&mut console);
+        assert!(output.text().contains('█'));
+    }
+}
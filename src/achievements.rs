@@ -0,0 +1,284 @@
+use crate::console::*;
+use crate::game::difficulty::*;
+use crate::game::game_state::*;
+use crate::multiplayer::packets::*;
+use crate::profile::*;
+use crate::theme::*;
+use crate::tr;
+use crate::util::*;
+
+/// A long-term goal, worked out from the games in a profile.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    /// Escape as the victim without ever being chased.
+    Untouchable,
+
+    /// Win as the killer in under five rounds.
+    QuickKill,
+
+    /// Evade three chases in one game.
+    Slippery,
+
+    /// Find the car parts in every location, in the order they are on the map.
+    GrandTour,
+
+    /// Escape as the victim after being wounded.
+    Survivor,
+
+    /// Win ten games.
+    Veteran,
+
+    /// Beat the hard AI as both the victim and the killer.
+    Nightmare,
+}
+
+impl Achievement {
+    /// Every achievement, in the order they are listed.
+    pub const ALL: [Achievement; 7] = [
+        Achievement::Untouchable,
+        Achievement::QuickKill,
+        Achievement::Slippery,
+        Achievement::GrandTour,
+        Achievement::Survivor,
+        Achievement::Veteran,
+        Achievement::Nightmare,
+    ];
+
+    /// Identifier the achievement is saved under, which never changes.
+    pub fn id(self) -> &'static str {
+        match self {
+            Achievement::Untouchable => "untouchable",
+            Achievement::QuickKill => "quick_kill",
+            Achievement::Slippery => "slippery",
+            Achievement::GrandTour => "grand_tour",
+            Achievement::Survivor => "survivor",
+            Achievement::Veteran => "veteran",
+            Achievement::Nightmare => "nightmare",
+        }
+    }
+
+    /// Name of the achievement, in the current language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::Untouchable => tr!("achievement.untouchable"),
+            Achievement::QuickKill => tr!("achievement.quick_kill"),
+            Achievement::Slippery => tr!("achievement.slippery"),
+            Achievement::GrandTour => tr!("achievement.grand_tour"),
+            Achievement::Survivor => tr!("achievement.survivor"),
+            Achievement::Veteran => tr!("achievement.veteran"),
+            Achievement::Nightmare => tr!("achievement.nightmare"),
+        }
+    }
+
+    /// What has to be done to unlock the achievement, in the current language.
+    pub fn description(self) -> &'static str {
+        match self {
+            Achievement::Untouchable => tr!("achievement.untouchable.description"),
+            Achievement::QuickKill => tr!("achievement.quick_kill.description"),
+            Achievement::Slippery => tr!("achievement.slippery.description"),
+            Achievement::GrandTour => tr!("achievement.grand_tour.description"),
+            Achievement::Survivor => tr!("achievement.survivor.description"),
+            Achievement::Veteran => tr!("achievement.veteran.description"),
+            Achievement::Nightmare => tr!("achievement.nightmare.description"),
+        }
+    }
+
+    /// Get how far the games in `profile` are towards the achievement, and how far they need to
+    /// get to unlock it.
+    pub fn progress(self, profile: &Profile) -> (usize, usize) {
+        // Best progress in any single game, for goals that have to be reached in one game
+        let best = |score: &dyn Fn(&MatchRecord) -> usize| {
+            profile.matches.iter().map(score).max().unwrap_or(0)
+        };
+        let count = |m: &MatchRecord, matches: fn(&RoundResult) -> bool| {
+            m.results.iter().filter(|res| matches(&res.result)).count()
+        };
+
+        let (done, goal) = match self {
+            Achievement::Untouchable => (
+                best(&|m| {
+                    let chased = count(m, |res| matches!(res, RoundResult::ChaseBegins(_)));
+                    usize::from(m.role == PlayerType::Victim && m.won && chased == 0)
+                }),
+                1,
+            ),
+            Achievement::QuickKill => (
+                best(&|m| usize::from(m.role == PlayerType::Killer && m.won && m.rounds < 5)),
+                1,
+            ),
+            Achievement::Slippery => (
+                best(&|m| {
+                    if m.role == PlayerType::Victim {
+                        count(m, |res| *res == RoundResult::Evaded)
+                    } else {
+                        0
+                    }
+                }),
+                3,
+            ),
+            Achievement::GrandTour => (
+                best(&|m| {
+                    if m.role != PlayerType::Victim {
+                        return 0;
+                    }
+                    m.results
+                        .iter()
+                        .filter_map(|res| res.part_section_index)
+                        .enumerate()
+                        .take_while(|(i, section)| i == section)
+                        .count()
+                }),
+                MAP.len(),
+            ),
+            Achievement::Survivor => (
+                best(&|m| {
+                    let wounded = count(m, |res| *res == RoundResult::Wounded);
+                    usize::from(m.role == PlayerType::Victim && m.won && wounded > 0)
+                }),
+                1,
+            ),
+            Achievement::Veteran => (profile.record(|_| true).0, 10),
+            Achievement::Nightmare => (
+                [PlayerType::Victim, PlayerType::Killer]
+                    .iter()
+                    .filter(|role| {
+                        profile
+                            .record(|m| {
+                                m.role == **role && m.opponent == Opponent::Ai(Difficulty::Hard)
+                            })
+                            .0
+                            > 0
+                    })
+                    .count(),
+                2,
+            ),
+        };
+
+        (done.min(goal), goal)
+    }
+
+    /// Returns 'true' if `profile` has unlocked the achievement.
+    pub fn is_unlocked(self, profile: &Profile) -> bool {
+        profile.achievements.iter().any(|id| id == self.id())
+    }
+}
+
+/// Unlock every achievement the games in `profile` have earned, returning the ones that are new.
+pub fn unlock_achievements(profile: &mut Profile) -> Vec<Achievement> {
+    let unlocked: Vec<Achievement> = Achievement::ALL
+        .iter()
+        .copied()
+        .filter(|achievement| !achievement.is_unlocked(profile))
+        .filter(|achievement| {
+            let (done, goal) = achievement.progress(profile);
+            done >= goal
+        })
+        .collect();
+
+    profile.achievements.extend(
+        unlocked
+            .iter()
+            .map(|achievement| String::from(achievement.id())),
+    );
+    unlocked
+}
+
+/// Announce newly `unlocked` achievements.
+pub fn announce_achievements(console: &mut Console, unlocked: &[Achievement]) {
+    for achievement in unlocked {
+        let message = tr!(
+            "achievements.unlocked",
+            name = achievement.name(),
+            description = achievement.description()
+        );
+        writeln!(console, "{}", paint(console, Tone::Win, &message));
+    }
+}
+
+/// List every achievement along with how close the user is to unlocking it.
+pub fn achievements_menu(console: &mut Console) {
+    let profile = user_profile(console);
+    let unlocked = Achievement::ALL
+        .iter()
+        .filter(|achievement| achievement.is_unlocked(&profile))
+        .count();
+
+    writeln!(
+        console,
+        "{}",
+        tr!(
+            "achievements.title",
+            unlocked = unlocked,
+            total = Achievement::ALL.len()
+        )
+    );
+    for achievement in Achievement::ALL {
+        let status = if achievement.is_unlocked(&profile) {
+            paint(console, Tone::Part, tr!("achievements.done"))
+        } else {
+            let (done, goal) = achievement.progress(&profile);
+            format!("{}/{}", done, goal)
+        };
+        writeln!(
+            console,
+            "  [{}] {} - {}",
+            status,
+            achievement.name(),
+            achievement.description()
+        );
+    }
+
+    writeln!(console, "\n{}", tr!("menu.return_to_main"));
+    read_str(console);
+}
+
+/// Testing for achievements.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Achievements unlock once, as soon as a game earns them.
+    #[test]
+    fn unlocking() {
+        // Find the first three parts in order, evading a chase on the way
+        let mut state = GameState::new();
+        for section in 0..MAP.len() {
+            state.hide_part(section, 0);
+        }
+        state.play((0, 0), (4, 0));
+        state.play((1, 0), (1, 1));
+        state.play((1, 2), (1, 3));
+        state.play((2, 0), (4, 0));
+        state.play((4, 0), (0, 0));
+
+        let mut profile = Profile::default();
+        profile.matches.push(MatchRecord::new(
+            &state,
+            PlayerType::Victim,
+            Opponent::Player,
+            false,
+        ));
+        assert_eq!(Achievement::GrandTour.progress(&profile), (3, 5));
+        assert_eq!(Achievement::Slippery.progress(&profile), (1, 3));
+        assert!(unlock_achievements(&mut profile).is_empty());
+
+        // A quick win as the killer
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.hide_part(1, 0);
+        state.play((2, 0), (2, 0));
+        state.play((2, 1), (2, 1));
+        profile.matches.push(MatchRecord::new(
+            &state,
+            PlayerType::Killer,
+            Opponent::Ai(Difficulty::Hard),
+            true,
+        ));
+        assert_eq!(
+            unlock_achievements(&mut profile),
+            vec![Achievement::QuickKill]
+        );
+        assert!(unlock_achievements(&mut profile).is_empty());
+        assert_eq!(Achievement::Nightmare.progress(&profile), (1, 2));
+    }
+}
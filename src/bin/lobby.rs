@@ -0,0 +1,34 @@
+use camp_misty::multiplayer::lobby::*;
+
+/// Port the lobby listens on when none is given.
+const DEFAULT_PORT: u16 = 7777;
+
+fn main() {
+    // The only argument is an optional port
+    let port = match std::env::args().nth(1) {
+        Some(arg) => match arg.parse::<u16>() {
+            Ok(port) => port,
+            Err(_) => {
+                eprintln!("Usage: camp-misty-lobby [PORT]");
+                std::process::exit(1);
+            }
+        },
+        None => DEFAULT_PORT,
+    };
+
+    // Create the lobby
+    let server = match LobbyServer::new(port) {
+        Ok(server) => server,
+        Err(_) => {
+            eprintln!(
+                "There was a problem creating the lobby server on port {}.",
+                port
+            );
+            std::process::exit(1);
+        }
+    };
+
+    // Serve clients forever
+    println!("Camp Misty lobby server listening on port {}.", port);
+    server.run(|event| println!("{}", event));
+}
@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+/// Somewhere the lines the user types come from.
+pub trait Input {
+    /// Wait for the user to answer a prompt for up to `timeout`, or forever if it is `None`.
+    ///
+    /// Returns `RecvTimeoutError::Disconnected` once nothing more will ever be typed.
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String, RecvTimeoutError>;
+
+    /// Wait for up to `timeout` for a line typed while the game is busy with something else, like
+    /// waiting for the other player to move.
+    fn read_while_waiting(&mut self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        self.read_line(Some(timeout))
+    }
+
+    /// Returns 'true' if the user is typing at a terminal that nothing else is reading from.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// Somewhere text for the user goes.
+pub trait Output {
+    /// Show `text` to the user.
+    fn write_str(&mut self, text: &str);
+
+    /// Returns 'true' if the text is going to a terminal.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
+
+/// Where the game talks to the user, made up of an `Input` and an `Output`.
+///
+/// Text is written with `write!` and `writeln!`, which can't fail. Consoles can be handed to
+/// another thread, so a game can be played on one in the background.
+pub struct Console {
+    /// Where lines are read from.
+    input: Box<dyn Input + Send>,
+
+    /// Where text is written to.
+    output: Box<dyn Output + Send>,
+
+    /// File the user's profile is kept in, or `None` if finished games aren't remembered.
+    profile: Option<PathBuf>,
+
+    /// Whether output is made to suit screen readers.
+    accessible: bool,
+}
+
+impl Console {
+    /// Constructor.
+    pub fn new(
+        input: impl Input + Send + 'static,
+        output: impl Output + Send + 'static,
+    ) -> Console {
+        Console {
+            input: Box::new(input),
+            output: Box::new(output),
+            profile: None,
+            accessible: false,
+        }
+    }
+
+    /// Returns 'true' if screen reader mode is on.
+    ///
+    /// In screen reader mode there is no ASCII art or colour, choices are numbered, and
+    /// everything that happens in a round is announced in one line.
+    pub fn is_accessible(&self) -> bool {
+        self.accessible
+    }
+
+    /// Turn screen reader mode on or off.
+    pub fn set_accessible(&mut self, enabled: bool) {
+        self.accessible = enabled;
+    }
+
+    /// Keep the user's profile in the file at `path`.
+    pub fn with_profile(mut self, path: impl Into<PathBuf>) -> Console {
+        self.profile = Some(path.into());
+        self
+    }
+
+    /// Get the file the user's profile is kept in, if they have one.
+    pub fn profile_path(&self) -> Option<&Path> {
+        self.profile.as_deref()
+    }
+
+    /// Console that reads from stdin and writes to stdout.
+    pub fn stdio() -> Console {
+        Console::new(StdinInput, StdoutOutput)
+    }
+
+    /// Console that answers prompts with `answers` and keeps everything written in memory.
+    ///
+    /// The console has no profile, so games played on it aren't remembered. Returns the console along with a handle to what gets written to it.
+    pub fn scripted(answers: &[&str]) -> (Console, MemoryOutput) {
+        let output = MemoryOutput::new();
+        (
+            Console::new(ScriptedInput::new(answers), output.clone()),
+            output,
+        )
+    }
+
+    /// Get the input.
+    pub fn input(&mut self) -> &mut dyn Input {
+        self.input.as_mut()
+    }
+
+    /// Write formatted text, so `write!` and `writeln!` can be used with a console.
+    pub fn write_fmt(&mut self, args: fmt::Arguments) {
+        match args.as_str() {
+            Some(text) => self.output.write_str(text),
+            None => self.output.write_str(&args.to_string()),
+        }
+    }
+
+    /// Returns 'true' if the output is a terminal.
+    pub fn output_is_terminal(&self) -> bool {
+        self.output.is_terminal()
+    }
+
+    /// Returns 'true' if both the input and output are a terminal the user is sitting at.
+    pub fn is_terminal(&self) -> bool {
+        self.input.is_terminal() && self.output.is_terminal()
+    }
+}
+
+/// Reads the lines the user types on a background thread.
+///
+/// Stdin is read by a background thread so that input can be waited on with a timeout while
+/// other things (like network messages) are being checked. The thread only reads when a line is
+/// asked for, so stdin is left alone for anything else that wants it, like the terminal UI.
+struct StdinReader {
+    /// Asks the thread to read another line.
+    requests: Sender<()>,
+
+    /// Lines the thread has read.
+    lines: Receiver<String>,
+
+    /// Flag indicating the thread is waiting for the user to type a line.
+    reading: bool,
+}
+
+impl StdinReader {
+    /// Wait for the next line for up to `timeout`, or forever if it is `None`.
+    fn recv(&mut self, timeout: Option<Duration>) -> Result<String, RecvTimeoutError> {
+        if !self.reading {
+            // The thread is gone once stdin is closed, which shows up when receiving
+            let _ = self.requests.send(());
+            self.reading = true;
+        }
+
+        let res = match timeout {
+            Some(timeout) => self.lines.recv_timeout(timeout),
+            None => self
+                .lines
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        if res.is_ok() {
+            self.reading = false;
+        }
+        res
+    }
+}
+
+/// Get the reader for the lines the user has typed.
+fn stdin_lines() -> &'static Mutex<StdinReader> {
+    static LINES: OnceLock<Mutex<StdinReader>> = OnceLock::new();
+    LINES.get_or_init(|| {
+        let (request_sender, requests) = std::sync::mpsc::channel::<()>();
+        let (sender, lines) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for () in requests {
+                let mut input = String::new();
+                match std::io::stdin().read_line(&mut input) {
+                    Ok(0) | Err(_) => return,
+                    Ok(_) => {
+                        if sender.send(String::from(input.trim())).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        Mutex::new(StdinReader {
+            requests: request_sender,
+            lines,
+            reading: false,
+        })
+    })
+}
+
+/// Input typed into stdin.
+pub struct StdinInput;
+
+impl Input for StdinInput {
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String, RecvTimeoutError> {
+        stdin_lines().lock().unwrap().recv(timeout)
+    }
+
+    fn is_terminal(&self) -> bool {
+        // Anything else reading stdin while we wait on a line would fight over what the user types
+        std::io::stdin().is_terminal() && !stdin_lines().lock().unwrap().reading
+    }
+}
+
+/// Output printed to stdout.
+pub struct StdoutOutput;
+
+impl Output for StdoutOutput {
+    fn write_str(&mut self, text: &str) {
+        print!("{}", text);
+        std::io::Write::flush(&mut std::io::stdout()).expect("Flush failed!");
+    }
+
+    fn is_terminal(&self) -> bool {
+        std::io::stdout().is_terminal()
+    }
+}
+
+/// Input that answers prompts from a script, for testing.
+///
+/// Nothing is typed while the game is waiting on something else, so scripts can't be thrown off
+/// by how long the other player takes.
+pub struct ScriptedInput {
+    /// Answers to prompts, in order.
+    answers: VecDeque<String>,
+}
+
+impl ScriptedInput {
+    /// Constructor.
+    ///
+    /// Every prompt takes the next of the `answers`. Running out of answers lets the time on
+    /// prompts with a deadline run out, but panics otherwise, since the script didn't expect the
+    /// prompt.
+    pub fn new(answers: &[&str]) -> ScriptedInput {
+        ScriptedInput {
+            answers: answers.iter().map(|answer| String::from(*answer)).collect(),
+        }
+    }
+}
+
+impl Input for ScriptedInput {
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String, RecvTimeoutError> {
+        match (self.answers.pop_front(), timeout) {
+            (Some(answer), _) => Ok(answer),
+            (None, Some(timeout)) => {
+                std::thread::sleep(timeout);
+                Err(RecvTimeoutError::Timeout)
+            }
+            (None, None) => panic!("Ran out of scripted answers!"),
+        }
+    }
+
+    fn read_while_waiting(&mut self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        std::thread::sleep(timeout);
+        Err(RecvTimeoutError::Timeout)
+    }
+}
+
+/// Output kept in memory, for testing.
+///
+/// Cloning the output gives another handle to the same text, so it can be read while the game
+/// is still writing to it.
+#[derive(Clone, Default)]
+pub struct MemoryOutput {
+    text: Arc<Mutex<String>>,
+}
+
+impl MemoryOutput {
+    /// Constructor.
+    pub fn new() -> MemoryOutput {
+        MemoryOutput::default()
+    }
+
+    /// Everything written so far.
+    pub fn text(&self) -> String {
+        self.text.lock().unwrap().clone()
+    }
+}
+
+impl Output for MemoryOutput {
+    fn write_str(&mut self, text: &str) {
+        self.text.lock().unwrap().push_str(text);
+    }
+}
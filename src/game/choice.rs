@@ -0,0 +1,320 @@
+use std::time::Instant;
+
+use crate::accessibility::*;
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::theme::*;
+use crate::tr;
+use crate::util::*;
+
+/// How an answer matched a list of names.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NameMatch {
+    /// Exactly one name matched, at this index.
+    Found(usize),
+
+    /// Nothing matched, but the name at this index is close.
+    Suggestion(usize),
+
+    /// Nothing matched, or more than one name did.
+    NoMatch,
+}
+
+/// Get the words of `name` in lowercase, without accents or letter markers like "(C)".
+fn words(name: &str) -> Vec<String> {
+    plain_name(name)
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .flat_map(char::to_lowercase)
+                .map(|c| match c {
+                    'á' | 'à' | 'ä' => 'a',
+                    'é' | 'è' | 'ë' => 'e',
+                    'í' | 'ì' | 'ï' => 'i',
+                    'ó' | 'ò' | 'ö' => 'o',
+                    'ú' | 'ù' | 'ü' => 'u',
+                    'ñ' => 'n',
+                    c => c,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Get the number of single character edits needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != *b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Match `input` against `options`, given as names and their letters.
+///
+/// Accepts a letter, a number counting from 1, a full name, or an unambiguous prefix of the name
+/// or any word in it, ignoring case and accents. If nothing matches, a close enough name is
+/// suggested instead.
+pub fn match_name(input: &str, options: &[(&str, char)]) -> NameMatch {
+    let input = input.trim();
+
+    // Numbers
+    if let Ok(number) = input.parse::<usize>() {
+        return match number.checked_sub(1).filter(|i| *i < options.len()) {
+            Some(i) => NameMatch::Found(i),
+            None => NameMatch::NoMatch,
+        };
+    }
+
+    // Letters
+    let mut chars = input.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let upper = c.to_uppercase().next().unwrap_or(c);
+        return match options.iter().position(|(_, letter)| *letter == upper) {
+            Some(i) => NameMatch::Found(i),
+            None => NameMatch::NoMatch,
+        };
+    }
+
+    let input = words(input).join(" ");
+    if input.is_empty() {
+        return NameMatch::NoMatch;
+    }
+
+    // Every way of referring to each option, being the full name and each word in it
+    let names: Vec<Vec<String>> = options
+        .iter()
+        .map(|(name, _)| {
+            let words = words(name);
+            std::iter::once(words.join(" ")).chain(words).collect()
+        })
+        .collect();
+    let matching = |matches: &dyn Fn(&String) -> bool| -> Vec<usize> {
+        (0..names.len())
+            .filter(|i| names[*i].iter().any(matches))
+            .collect()
+    };
+
+    // Whole names win over prefixes, so a name can be picked even if it starts another
+    for found in [
+        matching(&|name| *name == input),
+        matching(&|name| name.starts_with(&input)),
+    ] {
+        match found[..] {
+            [i] => return NameMatch::Found(i),
+            [] => {}
+            _ => return NameMatch::NoMatch,
+        }
+    }
+
+    // Suggest the closest name, if it's only a typo or two away
+    if input.chars().count() < 3 {
+        return NameMatch::NoMatch;
+    }
+    let allowed = (input.chars().count() / 3).max(1);
+    names
+        .iter()
+        .enumerate()
+        .filter_map(|(i, names)| {
+            names
+                .iter()
+                .map(|name| edit_distance(&input, name))
+                .min()
+                .map(|distance| (distance, i))
+        })
+        .filter(|(distance, _)| *distance <= allowed)
+        .min()
+        .map_or(NameMatch::NoMatch, |(_, i)| NameMatch::Suggestion(i))
+}
+
+/// Print the `names` of every choice, numbered in screen reader mode.
+pub fn print_choices<'a>(console: &mut Console, names: impl IntoIterator<Item = &'a String>) {
+    for (i, name) in names.into_iter().enumerate() {
+        if console.is_accessible() {
+            writeln!(console, "{}. {}", i + 1, plain_name(name));
+        } else {
+            writeln!(
+                console,
+                "{}",
+                tr!("move.option", name = paint(console, Tone::Section, name))
+            );
+        }
+    }
+}
+
+/// Get the names and letters of every section in `state`.
+fn section_options(state: &GameState) -> Vec<(&str, char)> {
+    state
+        .sections
+        .iter()
+        .map(|section| (section.name.as_str(), section.letter))
+        .collect()
+}
+
+/// Get the names and letters of every sub-section of `section` in `state`.
+fn sub_section_options(state: &GameState, section: usize) -> Vec<(&str, char)> {
+    state.sections[section]
+        .sub_sections
+        .iter()
+        .map(|sub_section| (sub_section.name.as_str(), sub_section.letter))
+        .collect()
+}
+
+/// Work out which spot in `section` the words in `input` refer to.
+///
+/// The section itself may be named first, like "lake dock".
+fn match_sub_section(state: &GameState, section: usize, input: &[&str]) -> NameMatch {
+    let options = sub_section_options(state, section);
+    let whole = match_name(&input.join(" "), &options);
+    if let NameMatch::Found(_) = whole {
+        return whole;
+    }
+
+    for split in 1..input.len() {
+        if match_name(&input[..split].join(" "), &section_options(state))
+            == NameMatch::Found(section)
+        {
+            if let NameMatch::Found(i) = match_name(&input[split..].join(" "), &options) {
+                return NameMatch::Found(i);
+            }
+        }
+    }
+    whole
+}
+
+/// Print that the user's answer wasn't understood, with `err_msg`, or suggest `suggestion`.
+fn not_understood(console: &mut Console, err_msg: &str, suggestion: Option<&str>) {
+    match suggestion {
+        Some(name) => writeln!(console, "{}", tr!("error.did_you_mean", name = name)),
+        None => writeln!(console, "{}", err_msg),
+    }
+}
+
+/// Have the user pick a section in `state` before a `deadline`.
+///
+/// A spot may be given along with the section, like "A B" or "manor basement", in which case it
+/// is returned too. Returns `None` if the deadline passes first.
+pub fn pick_section_before(
+    console: &mut Console,
+    state: &GameState,
+    deadline: Option<Instant>,
+) -> Option<(usize, Option<usize>)> {
+    let options = section_options(state);
+    loop {
+        let input = read_str_before(console, deadline)?;
+        let input: Vec<&str> = input.split_whitespace().collect();
+
+        let whole = match_name(&input.join(" "), &options);
+        if let NameMatch::Found(i) = whole {
+            return Some((i, None));
+        }
+
+        // The section, followed by the spot
+        let mut suggestion = match whole {
+            NameMatch::Suggestion(i) => Some(state.sections[i].name.as_str()),
+            _ => None,
+        };
+        for split in 1..input.len() {
+            if let NameMatch::Found(i) = match_name(&input[..split].join(" "), &options) {
+                match match_sub_section(state, i, &input[split..]) {
+                    NameMatch::Found(j) => return Some((i, Some(j))),
+                    NameMatch::Suggestion(j) => {
+                        suggestion = suggestion.or(Some(&state.sections[i].sub_sections[j].name))
+                    }
+                    NameMatch::NoMatch => {}
+                }
+            }
+        }
+
+        not_understood(console, tr!("error.not_location"), suggestion);
+    }
+}
+
+/// Have the user pick a sub-section of `section` in `state` before a `deadline`.
+///
+/// Returns `None` if the deadline passes first.
+pub fn pick_sub_section_before(
+    console: &mut Console,
+    state: &GameState,
+    section: usize,
+    deadline: Option<Instant>,
+) -> Option<usize> {
+    loop {
+        let input = read_str_before(console, deadline)?;
+        let input: Vec<&str> = input.split_whitespace().collect();
+
+        match match_sub_section(state, section, &input) {
+            NameMatch::Found(i) => return Some(i),
+            NameMatch::Suggestion(i) => not_understood(
+                console,
+                tr!("error.not_spot"),
+                Some(&state.sections[section].sub_sections[i].name),
+            ),
+            NameMatch::NoMatch => not_understood(console, tr!("error.not_spot"), None),
+        }
+    }
+}
+
+/// Testing for matching what the user typed.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Letters, names and prefixes are all accepted, and typos get a suggestion.
+    #[test]
+    fn matching() {
+        let options = [
+            ("(B)edroom", 'B'),
+            ("(K)itchen", 'K'),
+            ("(L)ake Misty", 'L'),
+            ("Cabaña (C)", 'C'),
+        ];
+        assert_eq!(match_name("k", &options), NameMatch::Found(1));
+        assert_eq!(match_name("3", &options), NameMatch::Found(2));
+        assert_eq!(match_name("5", &options), NameMatch::NoMatch);
+        assert_eq!(match_name(" KITCHEN ", &options), NameMatch::Found(1));
+        assert_eq!(match_name("bed", &options), NameMatch::Found(0));
+        assert_eq!(match_name("misty", &options), NameMatch::Found(2));
+        assert_eq!(match_name("cabana", &options), NameMatch::Found(3));
+        assert_eq!(match_name("kitchne", &options), NameMatch::Suggestion(1));
+        assert_eq!(match_name("x", &options), NameMatch::NoMatch);
+        assert_eq!(match_name("garage", &options), NameMatch::NoMatch);
+
+        // Letters that aren't ASCII are still markers, not words
+        assert_eq!(words("Baño (Ñ)"), vec![String::from("bano")]);
+
+        // Both choices at once, or the section again during a chase
+        let state = GameState::new();
+        let (mut console, output) = Console::scripted(&["manor basment", "manor basement"]);
+        assert_eq!(
+            pick_section_before(&mut console, &state, None),
+            Some((2, Some(2)))
+        );
+        assert!(output.text().contains("Did you mean the (B)asement?"));
+
+        let (mut console, _) = Console::scripted(&["A B"]);
+        assert_eq!(
+            pick_section_before(&mut console, &state, None),
+            Some((2, Some(2)))
+        );
+
+        let (mut console, _) = Console::scripted(&["lake", "lake west"]);
+        assert_eq!(
+            pick_section_before(&mut console, &state, None),
+            Some((1, None))
+        );
+        assert_eq!(
+            pick_sub_section_before(&mut console, &state, 1, None),
+            Some(3)
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
+use crate::tui::map_text;
+
+/// Where a car part was hidden, and when it was found.
+#[derive(Debug, PartialEq)]
+pub struct HiddenPart {
+    /// Section and sub-section the part was hidden in.
+    pub spot: (usize, usize),
+
+    /// Round the victim found it in, if they did.
+    pub found_in: Option<usize>,
+}
+
+/// Get where every car part was hidden at the start of the game in `state`, in section order.
+pub fn hidden_parts(state: &GameState) -> Vec<HiddenPart> {
+    let mut parts: Vec<HiddenPart> = Vec::new();
+
+    // Parts that were found are gone from the map, so they come from the history
+    for (i, round) in state.history.iter().enumerate() {
+        if round.result.part_section_index.is_some() {
+            parts.push(HiddenPart {
+                spot: round.victim_move,
+                found_in: Some(i + 1),
+            });
+        }
+    }
+
+    for (i, section) in state.sections.iter().enumerate() {
+        for (j, sub_section) in section.sub_sections.iter().enumerate() {
+            if sub_section.part {
+                parts.push(HiddenPart {
+                    spot: (i, j),
+                    found_in: None,
+                });
+            }
+        }
+    }
+
+    parts.sort_by_key(|part| part.spot);
+    parts
+}
+
+/// Get the rounds where both players were in the same section, but in different spots.
+pub fn near_misses(state: &GameState) -> Vec<(usize, &PlayedRound)> {
+    state
+        .history
+        .iter()
+        .enumerate()
+        .filter(|(_, round)| {
+            round.victim_move.0 == round.killer_move.0 && round.victim_move.1 != round.killer_move.1
+        })
+        .map(|(i, round)| (i + 1, round))
+        .collect()
+}
+
+/// Pad `text` with spaces to be `width` characters wide.
+fn pad(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(len)))
+}
+
+/// Describe what happened in a round in a few words.
+fn describe(result: PlayResult) -> String {
+    let mut description = String::from(match result.result {
+        RoundResult::Nothing => tr!("debrief.result.nothing"),
+        RoundResult::Caught => tr!("debrief.result.caught"),
+        RoundResult::Wounded => tr!("debrief.result.wounded"),
+        RoundResult::AllPartsFound => tr!("debrief.result.escaped"),
+        RoundResult::Evaded => tr!("debrief.result.evaded"),
+        RoundResult::ChaseBegins(_) => tr!("debrief.result.chase"),
+        RoundResult::TrapTriggered => tr!("debrief.result.trap"),
+    });
+
+    // Finding the last part is already covered by the victim escaping
+    if result.part_section_index.is_some() {
+        match result.result {
+            RoundResult::Nothing => description = String::from(tr!("debrief.result.part")),
+            RoundResult::AllPartsFound => {}
+            _ => description = format!("{}, {}", tr!("debrief.result.part"), description),
+        }
+    }
+    description
+}
+
+/// Print everything that happened in the finished game in `state`.
+///
+/// Reveals where the car parts were, what both players did each round and how close the killer
+/// came. `player_type` is the type of player "we" are, or `None` if both players are watching.
+pub fn print_debrief(console: &mut Console, state: &GameState, player_type: Option<PlayerType>) {
+    let spot_name = |spot: (usize, usize)| {
+        format!(
+            "{} / {}",
+            state.sections[spot.0].name, state.sections[spot.0].sub_sections[spot.1].name
+        )
+    };
+
+    writeln!(
+        console,
+        "\n{}",
+        paint(console, Tone::Title, tr!("debrief.title"))
+    );
+
+    // Where the parts were, on the map and then section by section
+    let parts = hidden_parts(state);
+    writeln!(console, "\n{}", tr!("debrief.parts"));
+
+    // Screen readers get on better with the list alone
+    if !console.is_accessible() {
+        let map = map_text(state, |spot| {
+            let part = parts.iter().find(|part| part.spot == spot)?;
+            Some(if part.found_in.is_some() { '*' } else { '?' })
+        });
+        for line in map {
+            writeln!(console, "  {}", line);
+        }
+        writeln!(console, "  {}\n", tr!("debrief.map_key"));
+    }
+
+    for part in &parts {
+        let section = &state.sections[part.spot.0];
+        let line = match part.found_in {
+            Some(round) => tr!(
+                "debrief.part_found",
+                section = section.name,
+                spot = section.sub_sections[part.spot.1].name,
+                round = round
+            ),
+            None => tr!(
+                "debrief.part_missed",
+                section = section.name,
+                spot = section.sub_sections[part.spot.1].name
+            ),
+        };
+        let tone = if part.found_in.is_some() {
+            Tone::Part
+        } else {
+            Tone::Section
+        };
+        writeln!(console, "  {}", paint(console, tone, &line));
+    }
+
+    // Both players' moves side by side
+    let role_name = |role: PlayerType, name: &str| {
+        if Some(role) == player_type {
+            tr!("debrief.you", role = name)
+        } else {
+            String::from(name)
+        }
+    };
+    let header = [
+        String::from(tr!("debrief.round")),
+        role_name(PlayerType::Victim, tr!("debrief.victim")),
+        role_name(PlayerType::Killer, tr!("debrief.killer")),
+        String::from(tr!("debrief.happened")),
+    ];
+    let rows: Vec<[String; 4]> = state
+        .history
+        .iter()
+        .enumerate()
+        .map(|(i, round)| {
+            [
+                (i + 1).to_string(),
+                spot_name(round.victim_move),
+                spot_name(round.killer_move),
+                describe(round.result),
+            ]
+        })
+        .collect();
+    let widths: Vec<usize> = (0..3)
+        .map(|column| {
+            std::iter::once(&header)
+                .chain(&rows)
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    writeln!(console, "\n{}", tr!("debrief.timeline"));
+    for row in std::iter::once(&header).chain(&rows) {
+        writeln!(
+            console,
+            "  {}  {}  {}  {}",
+            pad(&row[0], widths[0]),
+            pad(&row[1], widths[1]),
+            pad(&row[2], widths[2]),
+            row[3]
+        );
+    }
+
+    // Times the killer was in the right place, but the wrong spot
+    let misses = near_misses(state);
+    writeln!(console, "\n{}", tr!("debrief.near_misses"));
+    if misses.is_empty() {
+        writeln!(console, "  {}", tr!("debrief.no_near_misses"));
+    }
+    for (round, played) in &misses {
+        let section = &state.sections[played.victim_move.0];
+        writeln!(
+            console,
+            "  {}",
+            tr!(
+                "debrief.near_miss",
+                round = round,
+                section = section.name,
+                victim_spot = section.sub_sections[played.victim_move.1].name,
+                killer_spot = section.sub_sections[played.killer_move.1].name
+            )
+        );
+    }
+
+    // Summary
+    let count = |matches: fn(&RoundResult) -> bool| {
+        state
+            .history
+            .iter()
+            .filter(|round| matches(&round.result.result))
+            .count()
+    };
+    let distinct = |spot: fn(&PlayedRound) -> (usize, usize)| {
+        let mut spots: Vec<(usize, usize)> = state.history.iter().map(spot).collect();
+        spots.sort();
+        spots.dedup();
+        spots.len()
+    };
+    let found = parts.iter().filter(|part| part.found_in.is_some()).count();
+
+    writeln!(console, "\n{}", tr!("debrief.stats"));
+    writeln!(
+        console,
+        "  {}",
+        tr!("debrief.stats.rounds", rounds = state.history.len())
+    );
+    writeln!(
+        console,
+        "  {}",
+        tr!("debrief.stats.parts", found = found, total = parts.len())
+    );
+    writeln!(
+        console,
+        "  {}",
+        tr!(
+            "debrief.stats.chases",
+            count = count(|result| matches!(result, RoundResult::ChaseBegins(_)))
+        )
+    );
+    writeln!(
+        console,
+        "  {}",
+        tr!("debrief.stats.near_misses", count = misses.len())
+    );
+    writeln!(
+        console,
+        "  {}",
+        tr!(
+            "debrief.stats.wounds",
+            count = count(|result| matches!(result, RoundResult::Wounded | RoundResult::Caught))
+        )
+    );
+    writeln!(
+        console,
+        "  {}",
+        tr!(
+            "debrief.stats.searched",
+            victim = distinct(|round| round.victim_move),
+            killer = distinct(|round| round.killer_move)
+        )
+    );
+    writeln!(console);
+}
+
+/// Testing for debriefs.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Found and missed parts are both revealed, along with every near miss.
+    #[test]
+    fn debrief() {
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.hide_part(1, 2);
+
+        state.play((0, 0), (2, 1));
+        state.play((3, 1), (3, 2));
+        state.play((3, 4), (3, 4));
+
+        assert_eq!(
+            hidden_parts(&state),
+            vec![
+                HiddenPart {
+                    spot: (0, 0),
+                    found_in: Some(1)
+                },
+                HiddenPart {
+                    spot: (1, 2),
+                    found_in: None
+                },
+            ]
+        );
+        assert_eq!(near_misses(&state).len(), 1);
+
+        let (mut console, output) = Console::scripted(&[]);
+        print_debrief(&mut console, &state, Some(PlayerType::Killer));
+        let text = output.text();
+        assert!(text.contains("(C)abin: the car part was in the (B)edroom, found in round 1"));
+        assert!(text.contains("(L)ake Misty: the car part was in the (E)ast shore, never found"));
+        assert!(text.contains("* (B)edroom"));
+        assert!(text.contains("? (E)ast shore"));
+        assert!(text.contains("Killer (you)"));
+        assert!(text.contains("Round 2: both in the (B)onfire"));
+        assert!(text.contains("Near misses: 1"));
+    }
+}
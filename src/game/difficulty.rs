@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// How well the AI plays.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Difficulty {
+    /// The AI often wanders around at random.
+    Easy,
+
+    /// The AI sometimes wanders around at random.
+    Normal,
+
+    /// The AI always plays as well as it can.
+    Hard,
+}
+
+impl Difficulty {
+    /// Chance of the AI making a random move instead of a good one.
+    pub fn mistake_chance(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Normal => 0.2,
+            Difficulty::Hard => 0.0,
+        }
+    }
+}
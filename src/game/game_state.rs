@@ -1,6 +1,72 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::game::sections::*;
+use crate::locale::*;
+
+/// Key of a name in the message catalogue, along with the letter used to pick it.
+///
+/// Letters are the same in every language, so only the names are translated.
+pub type MapEntry = (&'static str, char);
+
+/// Every section and its sub-sections.
+pub const MAP: [(&str, char, [MapEntry; 5]); 5] = [
+    (
+        "map.cabin",
+        'C',
+        [
+            ("map.cabin.bedroom", 'B'),
+            ("map.cabin.kitchen", 'K'),
+            ("map.cabin.toilet", 'T'),
+            ("map.cabin.closet", 'C'),
+            ("map.cabin.attic", 'A'),
+        ],
+    ),
+    (
+        "map.lake",
+        'L',
+        [
+            ("map.lake.dock", 'D'),
+            ("map.lake.boat", 'B'),
+            ("map.lake.east_shore", 'E'),
+            ("map.lake.west_shore", 'W'),
+            ("map.lake.south_shore", 'S'),
+        ],
+    ),
+    (
+        "map.manor",
+        'A',
+        [
+            ("map.manor.master_bedroom", 'M'),
+            ("map.manor.dining_hall", 'D'),
+            ("map.manor.basement", 'B'),
+            ("map.manor.kitchen", 'K'),
+            ("map.manor.fourier", 'F'),
+        ],
+    ),
+    (
+        "map.bonfire",
+        'B',
+        [
+            ("map.bonfire.shrubs", 'S'),
+            ("map.bonfire.couch", 'C'),
+            ("map.bonfire.logs", 'L'),
+            ("map.bonfire.trees", 'T'),
+            ("map.bonfire.blankets", 'B'),
+        ],
+    ),
+    (
+        "map.forest",
+        'O',
+        [
+            ("map.forest.pond", 'P'),
+            ("map.forest.cave", 'C'),
+            ("map.forest.shrine", 'S'),
+            ("map.forest.fairy_circle", 'F'),
+            ("map.forest.hollow_log", 'H'),
+        ],
+    ),
+];
 
 /// Structure describing the current state of the game.
 pub struct GameState {
@@ -15,10 +81,26 @@ pub struct GameState {
 
     /// Flag indicating the victim is wounded
     pub victim_is_wounded: bool,
+
+    /// Every round played so far, oldest first.
+    pub history: Vec<PlayedRound>,
 }
 
-/// The result of a previous round and an optional car part if one was found.
+/// A round that has been played, with the moves both players made.
 #[derive(Copy, Clone)]
+pub struct PlayedRound {
+    /// Section and sub-section the victim checked.
+    pub victim_move: (usize, usize),
+
+    /// Section and sub-section the killer checked.
+    pub killer_move: (usize, usize),
+
+    /// What happened.
+    pub result: PlayResult,
+}
+
+/// The result of a previous round and an optional car part if one was found.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct PlayResult {
     /// Result of the round.
     pub result: RoundResult,
@@ -28,7 +110,7 @@ pub struct PlayResult {
 }
 
 /// A result of a round in the game
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum RoundResult {
     /// Nothing happens.
     Nothing,
@@ -78,79 +160,43 @@ impl Default for GameState {
 impl GameState {
     /// Constructor.
     pub fn new() -> GameState {
-        // Create sections
-        let sections = vec![
-            Section::new(
-                String::from("(C)abin"),
-                'C',
-                vec![
-                    SubSection::new(String::from("(B)edroom"), 'B', false),
-                    SubSection::new(String::from("(K)itchen"), 'K', false),
-                    SubSection::new(String::from("(T)oilet"), 'T', false),
-                    SubSection::new(String::from("(C)loset"), 'C', false),
-                    SubSection::new(String::from("(A)ttic"), 'A', false),
-                ],
-            ),
-            Section::new(
-                String::from("(L)ake Misty"),
-                'L',
-                vec![
-                    SubSection::new(String::from("(D)ock"), 'D', false),
-                    SubSection::new(String::from("(B)oat"), 'B', false),
-                    SubSection::new(String::from("(E)ast shore"), 'E', false),
-                    SubSection::new(String::from("(W)est shore"), 'W', false),
-                    SubSection::new(String::from("(S)outh shore"), 'S', false),
-                ],
-            ),
-            Section::new(
-                String::from("(A)bandoned manor"),
-                'A',
-                vec![
-                    SubSection::new(String::from("(M)aster bedroom"), 'M', false),
-                    SubSection::new(String::from("(D)ining hall"), 'D', false),
-                    SubSection::new(String::from("(B)asement"), 'B', false),
-                    SubSection::new(String::from("(K)itchen"), 'K', false),
-                    SubSection::new(String::from("(F)ourier"), 'F', false),
-                ],
-            ),
-            Section::new(
-                String::from("(B)onfire"),
-                'B',
-                vec![
-                    SubSection::new(String::from("(S)hrubs"), 'S', false),
-                    SubSection::new(String::from("(C)ouch"), 'C', false),
-                    SubSection::new(String::from("(L)ogs"), 'L', false),
-                    SubSection::new(String::from("(T)rees"), 'T', false),
-                    SubSection::new(String::from("(B)lankets"), 'B', false),
-                ],
-            ),
-            Section::new(
-                String::from("(O)ld forest"),
-                'O',
-                vec![
-                    SubSection::new(String::from("(P)ond"), 'P', false),
-                    SubSection::new(String::from("(C)ave"), 'C', false),
-                    SubSection::new(String::from("(S)hrine"), 'S', false),
-                    SubSection::new(String::from("(F)airy circle"), 'F', false),
-                    SubSection::new(String::from("(H)ollow log"), 'H', false),
-                ],
-            ),
-        ];
+        // Create sections, named in the current language
+        let sections = MAP
+            .iter()
+            .map(|(name, letter, sub_sections)| {
+                Section::new(
+                    String::from(tr(name)),
+                    *letter,
+                    sub_sections
+                        .iter()
+                        .map(|(name, letter)| {
+                            SubSection::new(String::from(tr(name)), *letter, false)
+                        })
+                        .collect(),
+                )
+            })
+            .collect();
 
         GameState {
             sections,
             last_result: PlayResult::new(RoundResult::Nothing, None),
             part_count: 0,
             victim_is_wounded: false,
+            history: Vec::new(),
         }
     }
 
     /// Generate random game state.
     pub fn gen_state(&mut self) {
+        self.gen_state_with(&mut rand::thread_rng());
+    }
+
+    /// Generate random game state using `rng`, so a seeded generator always gives the same state.
+    pub fn gen_state_with<R: Rng>(&mut self, rng: &mut R) {
         // Distribute car parts
         for i in 0..self.sections.len() {
             // Randomly choose which sub section gets the part
-            let rand_ind = rand::thread_rng().gen_range(0, self.sections[i].sub_sections.len());
+            let rand_ind = rng.gen_range(0, self.sections[i].sub_sections.len());
 
             // Place the part in the sub section
             self.hide_part(i, rand_ind);
@@ -201,6 +247,39 @@ impl GameState {
         None
     }
 
+    /// Check if a section and sub-section tuple is a move that can be played this round.
+    ///
+    /// The move must be within bounds, and during a chase it must be in the section the chase is
+    /// taking place in.
+    pub fn is_valid_move(&self, tup: (usize, usize)) -> bool {
+        if tup.0 >= self.sections.len() || tup.1 >= self.sections[tup.0].sub_sections.len() {
+            return false;
+        }
+
+        match self.last_result.result {
+            RoundResult::ChaseBegins(section) => tup.0 == section,
+            _ => true,
+        }
+    }
+
+    /// Pick a random move that can be played this round.
+    pub fn random_move(&self) -> (usize, usize) {
+        self.random_move_with(&mut rand::thread_rng())
+    }
+
+    /// Pick a random move that can be played this round using `rng`.
+    pub fn random_move_with<R: Rng>(&self, rng: &mut R) -> (usize, usize) {
+        // During a chase the move has to be in the chase section
+        let section = match self.last_result.result {
+            RoundResult::ChaseBegins(section) => section,
+            _ => rng.gen_range(0, self.sections.len()),
+        };
+
+        let sub_section = rng.gen_range(0, self.sections[section].sub_sections.len());
+
+        (section, sub_section)
+    }
+
     /// Perform a round of the game.
     ///
     /// `victim` is a tuple containing the indices of the section and sub-section the victim is checking.
@@ -264,6 +343,11 @@ impl GameState {
         // Update last result
         let res = PlayResult::new(round_result, if car_part { Some(victim.0) } else { None });
         self.last_result = res;
+        self.history.push(PlayedRound {
+            victim_move: victim,
+            killer_move: killer,
+            result: res,
+        });
 
         res
     }
@@ -274,6 +358,28 @@ impl GameState {
 mod test {
     use crate::game::killer_ai::*;
     use crate::game::victim_ai::*;
+    use crate::locale::*;
+
+    /// Every name on the map shows its letter, in every language.
+    #[test]
+    fn map_names() {
+        for locale in &LOCALES {
+            for (name, letter, sub_sections) in &super::MAP {
+                for (name, letter) in
+                    std::iter::once((name, letter)).chain(sub_sections.iter().map(|(n, l)| (n, l)))
+                {
+                    let message = locale.message(name).unwrap();
+                    assert!(
+                        message.contains(&format!("({})", letter)),
+                        "\"{}\" doesn't show the letter {} in {}",
+                        message,
+                        letter,
+                        locale.code
+                    );
+                }
+            }
+        }
+    }
 
     /// Runs a simulation of the game with AI players.
     #[test]
@@ -298,7 +404,7 @@ mod test {
             // Play game until there is a winner
             loop {
                 // Have each AI make a move
-                let killer_move = killer.play(&mut state);
+                let killer_move = killer.play(&state);
                 let victim_move = victim.play(&mut state);
 
                 // Submit moves to the game state
@@ -1,20 +1,36 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+use crate::game::difficulty::*;
 use crate::game::game_state::*;
 
 /// An AI version of the killer to be used in testing/single player
 pub struct KillerAI {
     /// List of sections to check
     sections: Vec<usize>,
+
+    /// How well the killer plays.
+    difficulty: Difficulty,
+
+    /// Random number generator used to pick moves.
+    rng: StdRng,
 }
 
-impl<'a> KillerAI {
+impl KillerAI {
     /// Constructor.
     ///
-    /// Only argument is the game state the killer will be playing in.
+    /// Only argument is the game state the killer will be playing in. The killer plays as well
+    /// as it can.
     pub fn new(state: &GameState) -> KillerAI {
+        KillerAI::with_difficulty(state, Difficulty::Hard, StdRng::from_entropy())
+    }
+
+    /// Create a killer that plays at a `difficulty`, picking moves with `rng`.
+    pub fn with_difficulty(state: &GameState, difficulty: Difficulty, rng: StdRng) -> KillerAI {
         KillerAI {
             sections: (0..state.sections.len()).collect(),
+            difficulty,
+            rng,
         }
     }
 
@@ -31,16 +47,21 @@ impl<'a> KillerAI {
             }
         }
 
+        // Easier killers sometimes wander around aimlessly
+        if self.rng.gen_bool(self.difficulty.mistake_chance()) {
+            return state.random_move_with(&mut self.rng);
+        }
+
         // Determine move based off of last round result
         match last_result.result {
             // Normal round logic
             RoundResult::Nothing | RoundResult::Evaded | RoundResult::Wounded => {
                 // Choose a random section from our list of valid sections
-                let sec_ind = rand::thread_rng().gen_range(0, self.sections.len());
+                let sec_ind = self.rng.gen_range(0, self.sections.len());
                 let section = self.sections[sec_ind];
 
                 // Choose a random sub section within the section
-                let sub_section = rand::thread_rng().gen_range(0, state.sections.len());
+                let sub_section = self.rng.gen_range(0, state.sections.len());
 
                 // Play that move
                 (section, sub_section)
@@ -49,8 +70,9 @@ impl<'a> KillerAI {
             // Special logic for a chase
             RoundResult::ChaseBegins(section) => {
                 // Choose a random sub section within the section
-                let sub_sec_ind =
-                    rand::thread_rng().gen_range(0, state.sections[section].sub_sections.len());
+                let sub_sec_ind = self
+                    .rng
+                    .gen_range(0, state.sections[section].sub_sections.len());
 
                 // Play that move in the chase section
                 (section, sub_sec_ind)
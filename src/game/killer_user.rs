@@ -1,132 +1,185 @@
+use std::time::Instant;
+
+use crate::console::*;
+use crate::game::choice::*;
 use crate::game::game_state::*;
-use crate::util::*;
+use crate::game::knowledge::*;
+use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
 
 /// Play a round of the game as a killer by passing in the current game state.
-pub fn play_killer(state: &mut GameState) -> (usize, usize) {
-    // Convenience function for special print out
-    let found_part_msg = || {
-        if let Some(ind) = state.last_result.part_section_index {
-            println!(
-                "Oh no! The victim found a car part in the {}!",
-                state.sections[ind].name
-            );
-        }
-    };
+pub fn play_killer(console: &mut Console, state: &mut GameState) -> (usize, usize) {
+    play_killer_before(console, state, None).expect("Move without a deadline timed out!")
+}
 
-    // Print out a special message depending on what happened last round
-    match state.last_result.result {
-        RoundResult::ChaseBegins(section) => {
-            found_part_msg();
-            println!("Muahaha! You have the victim in your sights!");
-            println!(
-                "Where in the {} would you like to search for them?",
-                state.sections[section].name
-            );
-        }
+/// Play a round of the game as a killer before a `deadline`.
+///
+/// Works like `play_killer`, but returns `None` if the deadline passes before a move is chosen. A
+/// `deadline` of `None` means there is no time limit.
+pub fn play_killer_before(
+    console: &mut Console,
+    state: &mut GameState,
+    deadline: Option<Instant>,
+) -> Option<(usize, usize)> {
+    // Screen readers get everything that happened in one line
+    if console.is_accessible() {
+        print_announcement(console, state, PlayerType::Killer);
+    } else {
+        // Remind the user of what they know so far
+        print_knowledge(console, state, PlayerType::Killer);
+
+        // Convenience function for special print out
+        let found_part_msg = |console: &mut Console| {
+            if let Some(ind) = state.last_result.part_section_index {
+                writeln!(
+                    console,
+                    "{}",
+                    paint(
+                        console,
+                        Tone::Part,
+                        &tr!("killer.part_found", section = state.sections[ind].name)
+                    )
+                );
+            }
+        };
+
+        // Print out a special message depending on what happened last round
+        match state.last_result.result {
+            RoundResult::ChaseBegins(section) => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Chase, tr!("killer.chase_begins"))
+                );
+                writeln!(
+                    console,
+                    "{}",
+                    tr!(
+                        "killer.pick_chase_spot",
+                        section = paint(console, Tone::Section, &state.sections[section].name)
+                    )
+                );
+            }
 
-        RoundResult::Evaded => {
-            found_part_msg();
-            println!("No, no, no! The victim got away!");
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::Evaded => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Escape, tr!("killer.evaded"))
+                );
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        RoundResult::Nothing => {
-            found_part_msg();
-            println!("Paitently, you stalk the grounds of Camp Misty for your victim...");
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::Nothing => {
+                found_part_msg(console);
+                writeln!(console, "{}", tr!("killer.nothing"));
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        RoundResult::TrapTriggered => {
-            found_part_msg();
-            println!("Oh no! You stepped right into the victims trap!");
-            println!("You spent the round getting yourself out.");
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::TrapTriggered => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Escape, tr!("killer.trap_triggered"))
+                );
+                writeln!(console, "{}", tr!("killer.trap_hint"));
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        RoundResult::Wounded => {
-            found_part_msg();
-            println!("Muahaha! You found the victim and were able to get a good swing in.");
-            println!("They are wounded. If you find them again, you win...");
-            println!("Now, which location would you like to check?");
+            RoundResult::Wounded => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Wound, tr!("killer.wounded"))
+                );
+                writeln!(console, "{}", tr!("killer.wounded_hint"));
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
+            // Win conditions are ignored
+            _ => {}
         }
-        // Win conditions are ignored
-        _ => {}
     }
 
     // Determine the round type
     match state.last_result.result {
         // Chase round!
         RoundResult::ChaseBegins(section) => {
-            // Print all sub sections and construct a vec with all sub section characters
-            let mut sub_section_chars = Vec::<char>::new();
-            for sub_section in &state.sections[section].sub_sections {
-                println!("{}?", sub_section.name);
-                sub_section_chars.push(sub_section.letter);
-            }
-
-            // Ask user for character
-            let sub_section_char = pick_char(
-                &sub_section_chars,
-                "Sorry, that isn't a spot! Choose a spot.",
+            // Print all sub sections
+            print_choices(
+                console,
+                state.sections[section].sub_sections.iter().map(|s| &s.name),
             );
 
-            // Get the sub section by character
-            // NOTE: Again, no None checks are needed
-            let sub_section_ind = state
-                .get_sub_section_by_letter(section, sub_section_char)
-                .expect("Sub section not found!");
+            // Ask user for the sub section
+            let sub_section_ind = pick_sub_section_before(console, state, section, deadline)?;
 
             // Return the section and sub section tuple
-            (section, sub_section_ind)
+            Some((section, sub_section_ind))
         }
 
         // Normal round
         _ => {
-            // Print all sections and construct vec with all section characters
-            let mut section_chars = Vec::<char>::new();
-            for section in &state.sections {
-                println!("{}?", section.name);
-                section_chars.push(section.letter);
-            }
-
-            // Ask user for character
-            let section_char = pick_char(
-                &section_chars,
-                "Sorry, that isn't a location! Choose a location.",
-            );
-
-            // Get the section by character
-            // NOTE: We don't need to do a 'None' check here because the 'pick_char' function guarantees we choose
-            // a valid section
-            let section_ind = state
-                .get_section_by_letter(section_char)
-                .expect("Section not found!");
-            let section = &mut state.sections[section_ind];
-
-            // Flavor message
-            println!("Which spot in here would you like to check?");
-
-            // Print all sub sections and construct a vec with all sub section characters
-            let mut sub_section_chars = Vec::<char>::new();
-            for sub_section in &section.sub_sections {
-                println!("{}?", sub_section.name);
-                sub_section_chars.push(sub_section.letter);
-            }
-
-            // Ask user for character
-            let sub_section_char = pick_char(
-                &sub_section_chars,
-                "Sorry, that isn't a spot! Choose a spot.",
-            );
-
-            // Get the sub section by character
-            // NOTE: Again, no None checks are needed
-            let sub_section_ind = state
-                .get_sub_section_by_letter(section_ind, sub_section_char)
-                .expect("Sub section not found!");
+            // Print all sections
+            print_choices(console, state.sections.iter().map(|s| &s.name));
+
+            // Ask user for the section, which may come along with the sub section
+            let (section_ind, sub_section_ind) = pick_section_before(console, state, deadline)?;
+            let sub_section_ind = match sub_section_ind {
+                Some(sub_section_ind) => sub_section_ind,
+                None => {
+                    // Flavor message
+                    writeln!(console, "{}", tr!("move.pick_spot"));
+
+                    // Print all sub sections
+                    print_choices(
+                        console,
+                        state.sections[section_ind]
+                            .sub_sections
+                            .iter()
+                            .map(|s| &s.name),
+                    );
+
+                    // Ask user for the sub section
+                    pick_sub_section_before(console, state, section_ind, deadline)?
+                }
+            };
 
             // Return the section and sub section tuple
-            (section_ind, sub_section_ind)
+            Some((section_ind, sub_section_ind))
         }
     }
 }
+
+/// Testing for the killer's moves.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Locations and spots are picked by letter or name, and anything else is asked for again.
+    #[test]
+    fn killer_moves() {
+        let mut state = GameState::new();
+        let (mut console, output) = Console::scripted(&["X", "garage", "c", "K"]);
+        assert_eq!(play_killer(&mut console, &mut state), (0, 1));
+        assert_eq!(
+            output
+                .text()
+                .matches("Sorry, that isn't a location!")
+                .count(),
+            2
+        );
+
+        // Only the spot is asked for during a chase
+        state.last_result = PlayResult::new(RoundResult::ChaseBegins(2), None);
+        let (mut console, output) = Console::scripted(&["B"]);
+        assert_eq!(play_killer(&mut console, &mut state), (2, 2));
+        assert!(output
+            .text()
+            .contains("Where in the (A)bandoned manor would you like to search for them?"));
+    }
+}
@@ -0,0 +1,293 @@
+use crate::accessibility::*;
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
+
+/// Everything one player has learned about the game so far.
+///
+/// Only holds what that player was actually told, so it never gives away the other player's
+/// moves or where the remaining car parts are.
+pub struct Knowledge {
+    /// Player type of whoever this is for.
+    pub player_type: PlayerType,
+
+    /// Number of rounds played.
+    pub rounds: usize,
+
+    /// Spots this player searched, in the order they were first searched.
+    pub searched: Vec<(usize, usize)>,
+
+    /// Sections car parts were found in, which won't have any more.
+    pub cleared: Vec<usize>,
+
+    /// Number of car parts there were at the start of the game.
+    pub total_parts: usize,
+
+    /// Round number and section of every chase.
+    pub chases: Vec<(usize, usize)>,
+
+    /// Round number and spot of every time the victim was wounded.
+    pub wounds: Vec<(usize, (usize, usize))>,
+}
+
+impl Knowledge {
+    /// Work out what the `player_type` player knows from the rounds played in `state`.
+    pub fn new(state: &GameState, player_type: PlayerType) -> Knowledge {
+        let mut knowledge = Knowledge {
+            player_type,
+            rounds: state.history.len(),
+            searched: Vec::new(),
+            cleared: Vec::new(),
+            total_parts: state.part_count,
+            chases: Vec::new(),
+            wounds: Vec::new(),
+        };
+
+        for (i, round) in state.history.iter().enumerate() {
+            let our_move = match player_type {
+                PlayerType::Victim => round.victim_move,
+                PlayerType::Killer => round.killer_move,
+            };
+            if !knowledge.searched.contains(&our_move) {
+                knowledge.searched.push(our_move);
+            }
+
+            // Both players are told where parts are found
+            if let Some(section) = round.result.part_section_index {
+                knowledge.cleared.push(section);
+                knowledge.total_parts += 1;
+            }
+
+            // Both players were in the same spot for a wound, so we know where it happened
+            match round.result.result {
+                RoundResult::ChaseBegins(section) => knowledge.chases.push((i + 1, section)),
+                RoundResult::Wounded => knowledge.wounds.push((i + 1, our_move)),
+                _ => {}
+            }
+        }
+
+        knowledge
+    }
+
+    /// Print a summary of what's known, before a move is picked.
+    ///
+    /// Nothing is printed before the first round, since nothing is known yet.
+    pub fn print(&self, console: &mut Console, state: &GameState) {
+        if self.rounds == 0 {
+            return;
+        }
+
+        let section_name = |console: &Console, section: usize| {
+            paint(console, Tone::Section, &state.sections[section].name)
+        };
+
+        writeln!(
+            console,
+            "{}",
+            tr!("knowledge.title", round = self.rounds + 1)
+        );
+
+        let parts_key = match self.player_type {
+            PlayerType::Victim => "knowledge.parts.victim",
+            PlayerType::Killer => "knowledge.parts.killer",
+        };
+        writeln!(
+            console,
+            "  {}",
+            tr!(
+                parts_key,
+                found = self.cleared.len(),
+                total = self.total_parts
+            )
+        );
+
+        if !self.cleared.is_empty() {
+            let sections: Vec<String> = self
+                .cleared
+                .iter()
+                .map(|section| section_name(console, *section))
+                .collect();
+            writeln!(
+                console,
+                "  {}",
+                tr!("knowledge.cleared", sections = sections.join(", "))
+            );
+        }
+
+        // Spots are grouped by the section they are in
+        writeln!(console, "  {}", tr!("knowledge.searched"));
+        for section in 0..state.sections.len() {
+            let spots: Vec<String> = self
+                .searched
+                .iter()
+                .filter(|spot| spot.0 == section)
+                .map(|spot| {
+                    paint(
+                        console,
+                        Tone::Section,
+                        &state.sections[spot.0].sub_sections[spot.1].name,
+                    )
+                })
+                .collect();
+            if !spots.is_empty() {
+                writeln!(
+                    console,
+                    "    {}: {}",
+                    section_name(console, section),
+                    spots.join(", ")
+                );
+            }
+        }
+
+        for (round, section) in &self.chases {
+            writeln!(
+                console,
+                "  {}",
+                tr!(
+                    "knowledge.chase",
+                    round = round,
+                    section = section_name(console, *section)
+                )
+            );
+        }
+
+        for (round, spot) in &self.wounds {
+            writeln!(
+                console,
+                "  {}",
+                tr!(
+                    "knowledge.wound",
+                    round = round,
+                    spot = state.sections[spot.0].sub_sections[spot.1].name,
+                    section = section_name(console, spot.0)
+                )
+            );
+        }
+
+        writeln!(console);
+    }
+
+    /// Describe what happened last round, and what to do next, in one line for screen readers.
+    pub fn announce(&self, state: &GameState) -> String {
+        let result = state.last_result;
+        let section_name = |section: usize| plain_name(&state.sections[section].name);
+        let mut parts: Vec<String> = vec![tr!("announce.round", round = self.rounds + 1)];
+
+        // What happened, from this player's side
+        let victim = self.player_type == PlayerType::Victim;
+        if let Some(section) = result.part_section_index {
+            parts.push(if victim {
+                String::from(tr!("announce.victim.part_found"))
+            } else {
+                tr!(
+                    "announce.killer.part_found",
+                    section = section_name(section)
+                )
+            });
+        }
+        let event = match (result.result, victim) {
+            (RoundResult::ChaseBegins(section), true) => {
+                tr!("announce.victim.chase", section = section_name(section))
+            }
+            (RoundResult::ChaseBegins(section), false) => {
+                tr!("announce.killer.chase", section = section_name(section))
+            }
+            (RoundResult::Evaded, true) => String::from(tr!("announce.victim.evaded")),
+            (RoundResult::Evaded, false) => String::from(tr!("announce.killer.evaded")),
+            (RoundResult::Wounded, true) => String::from(tr!("announce.victim.wounded")),
+            (RoundResult::Wounded, false) => String::from(tr!("announce.killer.wounded")),
+            (RoundResult::TrapTriggered, true) => String::from(tr!("announce.victim.trap")),
+            (RoundResult::TrapTriggered, false) => String::from(tr!("announce.killer.trap")),
+            _ if self.rounds == 0 => String::from(tr!("announce.start")),
+            _ if result.part_section_index.is_none() => String::from(tr!("announce.nothing")),
+            _ => String::new(),
+        };
+        if !event.is_empty() {
+            parts.push(event);
+        }
+
+        // Where the game stands
+        parts.push(tr!(
+            "announce.parts",
+            found = self.cleared.len(),
+            total = self.total_parts
+        ));
+        if !self.wounds.is_empty() {
+            parts.push(String::from(if victim {
+                tr!("announce.victim.hurt")
+            } else {
+                tr!("announce.killer.hurt")
+            }));
+        }
+
+        // What to do next
+        parts.push(match result.result {
+            RoundResult::ChaseBegins(section) => {
+                tr!("announce.pick_spot", section = section_name(section))
+            }
+            _ => String::from(tr!("announce.pick_location")),
+        });
+
+        parts.join(" ")
+    }
+}
+
+/// Print what the `player_type` player knows about the game in `state`.
+pub fn print_knowledge(console: &mut Console, state: &GameState, player_type: PlayerType) {
+    Knowledge::new(state, player_type).print(console, state);
+}
+
+/// Announce what happened last round to the `player_type` player in one line, for screen readers.
+pub fn print_announcement(console: &mut Console, state: &GameState, player_type: PlayerType) {
+    writeln!(
+        console,
+        "{}",
+        Knowledge::new(state, player_type).announce(state)
+    );
+}
+
+/// Testing for what each player knows.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Each player only knows their own moves, along with what both of them were told.
+    #[test]
+    fn only_what_each_player_knows() {
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.hide_part(1, 0);
+
+        state.play((0, 0), (2, 1));
+        state.play((3, 1), (3, 2));
+        state.play((3, 4), (3, 4));
+
+        let victim = Knowledge::new(&state, PlayerType::Victim);
+        assert_eq!(victim.searched, vec![(0, 0), (3, 1), (3, 4)]);
+        assert_eq!(victim.cleared, vec![0]);
+        assert_eq!(victim.total_parts, 2);
+        assert_eq!(victim.chases, vec![(2, 3)]);
+        assert_eq!(victim.wounds, vec![(3, (3, 4))]);
+
+        let killer = Knowledge::new(&state, PlayerType::Killer);
+        assert_eq!(killer.searched, vec![(2, 1), (3, 2), (3, 4)]);
+        assert_eq!(killer.cleared, vec![0]);
+
+        // The killer never learns which spot the part was in
+        let (mut console, output) = Console::scripted(&[]);
+        killer.print(&mut console, &state);
+        let text = output.text();
+        assert!(text.contains("(C)abin"));
+        assert!(!text.contains("(B)edroom"));
+        assert!(text.contains("(D)ining hall"));
+
+        // Everything that happened last round fits in one line
+        assert_eq!(
+            victim.announce(&state),
+            "Round 4. The killer wounded you. Car parts found: 1 of 2. You are wounded. \
+             Pick a location."
+        );
+    }
+}
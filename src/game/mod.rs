@@ -1,6 +1,10 @@
+pub mod choice;
+pub mod debrief;
+pub mod difficulty;
 pub mod game_state;
 pub mod killer_ai;
 pub mod killer_user;
+pub mod knowledge;
 pub mod sections;
 pub mod victim_ai;
 pub mod victim_user;
@@ -27,8 +27,8 @@ impl Section {
     ///
     /// NOTE: `letter` should be an uppercase letter.
     pub fn new(name: String, letter: char, sub_sections: Vec<SubSection>) -> Section {
-        assert_eq!(letter.is_alphabetic(), true);
-        assert_eq!(letter.is_uppercase(), true);
+        assert!(letter.is_alphabetic());
+        assert!(letter.is_uppercase());
 
         Section {
             name,
@@ -43,8 +43,8 @@ impl SubSection {
     ///
     /// NOTE: `letter` should be an uppercase letter.
     pub fn new(name: String, letter: char, part: bool) -> SubSection {
-        assert_eq!(letter.is_alphabetic(), true);
-        assert_eq!(letter.is_uppercase(), true);
+        assert!(letter.is_alphabetic());
+        assert!(letter.is_uppercase());
 
         SubSection { name, letter, part }
     }
@@ -1,20 +1,36 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+use crate::game::difficulty::*;
 use crate::game::game_state::*;
 
 /// An AI version of a victim to be used for testing/single player.
 pub struct VictimAI {
     /// List of all unvisted sections and sub sections
     unvisited: Vec<(usize, usize)>,
+
+    /// How well the victim plays.
+    difficulty: Difficulty,
+
+    /// Random number generator used to pick moves.
+    rng: StdRng,
 }
 
 impl VictimAI {
     /// Constructor.
     ///
-    /// The only argument is a reference to the game state to play in.
+    /// The only argument is a reference to the game state to play in. The victim plays as well as
+    /// it can.
     pub fn new(state: &GameState) -> VictimAI {
+        VictimAI::with_difficulty(state, Difficulty::Hard, StdRng::from_entropy())
+    }
+
+    /// Create a victim that plays at a `difficulty`, picking moves with `rng`.
+    pub fn with_difficulty(state: &GameState, difficulty: Difficulty, rng: StdRng) -> VictimAI {
         let mut ai = VictimAI {
             unvisited: Vec::<(usize, usize)>::new(),
+            difficulty,
+            rng,
         };
 
         // Initialize unvisited tuples
@@ -32,15 +48,64 @@ impl VictimAI {
     ///
     /// Returns a tuple containing what move the AI decided to take.
     pub fn play(&mut self, state: &mut GameState) -> (usize, usize) {
+        // Easier victims sometimes wander around aimlessly
+        let tup = if self.rng.gen_bool(self.difficulty.mistake_chance()) {
+            let tup = state.random_move_with(&mut self.rng);
+            self.unvisited.retain(|&unvisited| unvisited != tup);
+            tup
+        }
         // Determine move based off of last round result
-        let tup = match state.last_result.result {
+        else {
+            self.choose_move(state)
+        };
+
+        // If the move we are going to make results in us finding a part, we can remove all
+        // section/sub-section tuples that are in the same section we are searching
+        {
+            if state.sections[tup.0].sub_sections[tup.1].part {
+                // To do this, we take advantage of the guarantee made during construction of the
+                // victim ai: the unvisited tuples are sorted by section. This means we can loop
+                // over the unvisted tuples, marking the first and last tuples we see that are in
+                // the correct section. Then, we can drain that range.
+
+                // Find index of first and last valid section
+                let mut section_range: (usize, usize) =
+                    (self.unvisited.len(), self.unvisited.len());
+                for (i, unvisited) in self.unvisited.iter().enumerate() {
+                    // Check if we care about this tuple
+                    if unvisited.0 == tup.0 {
+                        // Mark beginning and end if beginning is unmarked
+                        if section_range.0 == self.unvisited.len() {
+                            section_range.0 = i;
+                            section_range.1 = i;
+                        }
+                        // Mark end for the rest
+                        else {
+                            section_range.1 = i;
+                        }
+                    }
+                }
+
+                // Drain the elements if needed
+                if section_range.0 == self.unvisited.len() {
+                    self.unvisited.drain(section_range.0..section_range.1);
+                }
+            }
+        }
+
+        tup
+    }
+
+    /// Pick a good move based off of the last round result.
+    fn choose_move(&mut self, state: &GameState) -> (usize, usize) {
+        match state.last_result.result {
             // Normal round logic
             RoundResult::Nothing
             | RoundResult::TrapTriggered
             | RoundResult::Evaded
             | RoundResult::Wounded => {
                 // Choose a random section/sub-section tuple from our list of unvisited tuples
-                let tup_ind = rand::thread_rng().gen_range(0, self.unvisited.len());
+                let tup_ind = self.rng.gen_range(0, self.unvisited.len());
 
                 // Get the tuple, remove it from the unvisited list and return it
                 self.unvisited.remove(tup_ind)
@@ -62,12 +127,13 @@ impl VictimAI {
                 if valid_moves.is_empty() {
                     valid_moves.push((
                         section,
-                        rand::thread_rng().gen_range(0, state.sections[section].sub_sections.len()),
+                        self.rng
+                            .gen_range(0, state.sections[section].sub_sections.len()),
                     ));
                 }
 
                 // Choose a random move from that list
-                let tup = valid_moves[rand::thread_rng().gen_range(0, valid_moves.len())];
+                let tup = valid_moves[self.rng.gen_range(0, valid_moves.len())];
 
                 // Remove the move from the unvisted list
                 let mut tup_to_remove = self.unvisited.len();
@@ -88,42 +154,6 @@ impl VictimAI {
 
             // Invalid round type
             _ => panic!("Invalid round type detected by victim AI."),
-        };
-
-        // If the move we are going to make results in us finding a part, we can remove all
-        // section/sub-section tuples that are in the same section we are searching
-        {
-            if state.sections[tup.0].sub_sections[tup.1].part {
-                // To do this, we take advantage of the guarantee made during construction of the
-                // victim ai: the unvisited tuples are sorted by section. This means we can loop
-                // over the unvisted tuples, marking the first and last tuples we see that are in
-                // the correct section. Then, we can drain that range.
-
-                // Find index of first and last valid section
-                let mut section_range: (usize, usize) =
-                    (self.unvisited.len(), self.unvisited.len());
-                for (i, unvisited) in self.unvisited.iter().enumerate() {
-                    // Check if we care about this tuple
-                    if unvisited.0 == tup.0 {
-                        // Mark beginning and end if beginning is unmarked
-                        if section_range.0 == self.unvisited.len() {
-                            section_range.0 = i;
-                            section_range.1 = i;
-                        }
-                        // Mark end for the rest
-                        else {
-                            section_range.1 = i;
-                        }
-                    }
-                }
-
-                // Drain the elements if needed
-                if section_range.0 == self.unvisited.len() {
-                    self.unvisited.drain(section_range.0..section_range.1);
-                }
-            }
         }
-
-        tup
     }
 }
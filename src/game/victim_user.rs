@@ -1,57 +1,105 @@
+use std::time::Instant;
+
+use crate::console::*;
+use crate::game::choice::*;
 use crate::game::game_state::*;
-use crate::util::*;
+use crate::game::knowledge::*;
+use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
 
 /// Play a round of the game as a victim, passing in the current game state.
-pub fn play_victim(state: &GameState) -> (usize, usize) {
-    // Convenience function for special print out
-    let found_part_msg = || {
-        if state.last_result.part_section_index.is_some() {
-            println!("Nice! You found a car part!");
-        }
-    };
+pub fn play_victim(console: &mut Console, state: &GameState) -> (usize, usize) {
+    play_victim_before(console, state, None).expect("Move without a deadline timed out!")
+}
 
-    // Print out a special message depending on what happened last round
-    match state.last_result.result {
-        RoundResult::ChaseBegins(section) => {
-            found_part_msg();
-            println!(
-                "Oh no! The killer is in the {} with you! They're right behind you!",
-                state.sections[section].name
-            );
-            println!("Where would you like to hide?");
-        }
+/// Play a round of the game as a victim before a `deadline`.
+///
+/// Works like `play_victim`, but returns `None` if the deadline passes before a move is chosen. A
+/// `deadline` of `None` means there is no time limit.
+pub fn play_victim_before(
+    console: &mut Console,
+    state: &GameState,
+    deadline: Option<Instant>,
+) -> Option<(usize, usize)> {
+    // Screen readers get everything that happened in one line
+    if console.is_accessible() {
+        print_announcement(console, state, PlayerType::Victim);
+    } else {
+        // Remind the user of what they know so far
+        print_knowledge(console, state, PlayerType::Victim);
+
+        // Convenience function for special print out
+        let found_part_msg = |console: &mut Console| {
+            if state.last_result.part_section_index.is_some() {
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Part, tr!("victim.part_found"))
+                );
+            }
+        };
+
+        // Print out a special message depending on what happened last round
+        match state.last_result.result {
+            RoundResult::ChaseBegins(section) => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(
+                        console,
+                        Tone::Chase,
+                        &tr!(
+                            "victim.chase_begins",
+                            section = state.sections[section].name
+                        )
+                    )
+                );
+                writeln!(console, "{}", tr!("victim.pick_chase_spot"));
+            }
 
-        RoundResult::Evaded => {
-            found_part_msg();
-            println!("What a relief! You evaded the killer!");
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::Evaded => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Escape, tr!("victim.evaded"))
+                );
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        RoundResult::Nothing => {
-            found_part_msg();
-            println!(
-                "You carefully navigate the grounds of Camp Misty, searching for car parts..."
-            );
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::Nothing => {
+                found_part_msg(console);
+                writeln!(console, "{}", tr!("victim.nothing"));
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        RoundResult::TrapTriggered => {
-            found_part_msg();
-            println!("Ha, ha, ha! You hear the killer fall into your trap!");
-            println!("You were safe that round.");
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::TrapTriggered => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Escape, tr!("victim.trap_triggered"))
+                );
+                writeln!(console, "{}", tr!("victim.trap_hint"));
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        RoundResult::Wounded => {
-            found_part_msg();
-            println!("Oh no! You ran right into the killer and they cut you across");
-            println!("the back as you tried to get away!");
-            println!("You have a nasty wound. If they catch you again, you won't survive...");
-            println!("Now, which location would you like to check?");
-        }
+            RoundResult::Wounded => {
+                found_part_msg(console);
+                writeln!(
+                    console,
+                    "{}",
+                    paint(console, Tone::Wound, tr!("victim.wounded"))
+                );
+                writeln!(console, "{}", tr!("victim.wounded_hint"));
+                writeln!(console, "{}", tr!("move.pick_location"));
+            }
 
-        // Win conditions are ignored
-        _ => {}
+            // Win conditions are ignored
+            _ => {}
+        }
     }
 
     // Logic for chosing a location
@@ -60,76 +108,73 @@ pub fn play_victim(state: &GameState) -> (usize, usize) {
     match state.last_result.result {
         // Chase round!
         RoundResult::ChaseBegins(section) => {
-            // Print all sub sections and construct a vec with all sub section characters
-            let mut sub_section_chars = Vec::<char>::new();
-            for sub_section in &state.sections[section].sub_sections {
-                println!("{}?", sub_section.name);
-                sub_section_chars.push(sub_section.letter);
-            }
-
-            // Ask user for character
-            let sub_section_char = pick_char(
-                &sub_section_chars,
-                "Sorry, that isn't a spot! Choose a spot.",
+            // Print all sub sections
+            print_choices(
+                console,
+                state.sections[section].sub_sections.iter().map(|s| &s.name),
             );
 
-            // Get the sub section by character
-            // NOTE: Again, no None checks are needed
-            let sub_section_ind = state
-                .get_sub_section_by_letter(section, sub_section_char)
-                .expect("Sub section not found!");
+            // Ask user for the sub section
+            let sub_section_ind = pick_sub_section_before(console, state, section, deadline)?;
 
             // Return the section and sub section tuple
-            (section, sub_section_ind)
+            Some((section, sub_section_ind))
         }
 
         // A normal round
         _ => {
-            // Print all sections and construct vec with all section characters
-            let mut section_chars = Vec::<char>::new();
-            for section in &state.sections {
-                println!("{}?", section.name);
-                section_chars.push(section.letter);
-            }
-
-            // Ask user for character
-            let section_char = pick_char(
-                &section_chars,
-                "Sorry, that isn't a location! Choose a location.",
-            );
-
-            // Get the section by character
-            // NOTE: We don't need to do a 'None' check here because the 'pick_char' function guarantees we choose
-            // a valid section
-            let section_ind = state
-                .get_section_by_letter(section_char)
-                .expect("Section not found!");
-            let section = &state.sections[section_ind];
-
-            // Flavor message
-            println!("Which spot in here would you like to check?");
-
-            // Print all sub sections and construct a vec with all sub section characters
-            let mut sub_section_chars = Vec::<char>::new();
-            for sub_section in &section.sub_sections {
-                println!("{}?", sub_section.name);
-                sub_section_chars.push(sub_section.letter);
-            }
-
-            // Ask user for character
-            let sub_section_char = pick_char(
-                &sub_section_chars,
-                "Sorry, that isn't a spot! Choose a spot.",
-            );
-
-            // Get the sub section by character
-            // NOTE: Again, no None checks are needed
-            let sub_section_ind = state
-                .get_sub_section_by_letter(section_ind, sub_section_char)
-                .expect("Sub section not found!");
+            // Print all sections
+            print_choices(console, state.sections.iter().map(|s| &s.name));
+
+            // Ask user for the section, which may come along with the sub section
+            let (section_ind, sub_section_ind) = pick_section_before(console, state, deadline)?;
+            let sub_section_ind = match sub_section_ind {
+                Some(sub_section_ind) => sub_section_ind,
+                None => {
+                    // Flavor message
+                    writeln!(console, "{}", tr!("move.pick_spot"));
+
+                    // Print all sub sections
+                    print_choices(
+                        console,
+                        state.sections[section_ind]
+                            .sub_sections
+                            .iter()
+                            .map(|s| &s.name),
+                    );
+
+                    // Ask user for the sub section
+                    pick_sub_section_before(console, state, section_ind, deadline)?
+                }
+            };
 
             // Return the section and sub section tuple
-            (section_ind, sub_section_ind)
+            Some((section_ind, sub_section_ind))
         }
     }
 }
+
+/// Testing for the victim's moves.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Locations and spots are picked by letter, and anything else is asked for again.
+    #[test]
+    fn victim_moves() {
+        let mut state = GameState::new();
+        state.last_result = PlayResult::new(RoundResult::Nothing, Some(1));
+        let (mut console, output) = Console::scripted(&["o", "Z", "h"]);
+        assert_eq!(play_victim(&mut console, &state), (4, 4));
+        assert!(output.text().contains("Nice! You found a car part!"));
+        assert!(output.text().contains("Sorry, that isn't a spot!"));
+
+        // Only the spot is asked for during a chase
+        state.last_result = PlayResult::new(RoundResult::ChaseBegins(1), None);
+        let (mut console, output) = Console::scripted(&["C", "W"]);
+        assert_eq!(play_victim(&mut console, &state), (1, 3));
+        assert!(output
+            .text()
+            .contains("The killer is in the (L)ake Misty with you!"));
+    }
+}
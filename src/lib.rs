@@ -0,0 +1,14 @@
+pub mod accessibility;
+pub mod achievements;
+pub mod console;
+pub mod game;
+pub mod locale;
+pub mod multiplayer;
+pub mod profile;
+pub mod settings;
+pub mod singleplayer;
+pub mod theme;
+pub mod tui;
+pub mod tutorial;
+pub mod util;
+pub mod versus;
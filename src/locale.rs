@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+/// A language the game can be played in.
+pub struct Locale {
+    /// Code identifying the language, like "en".
+    pub code: &'static str,
+
+    /// Every message in the language, as a JSON object of keys to messages.
+    source: &'static str,
+}
+
+/// Every bundled language.
+///
+/// The first one is the default, and messages missing from another language are taken from it.
+pub const LOCALES: [Locale; 2] = [
+    Locale {
+        code: "en",
+        source: include_str!("../locales/en.json"),
+    },
+    Locale {
+        code: "es",
+        source: include_str!("../locales/es.json"),
+    },
+];
+
+/// Index of the current language in `LOCALES`.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Look up a message in the current language, filling in any named values.
+///
+/// `tr!("key")` gives the message as it is, and `tr!("key", name = value)` replaces `{name}` in
+/// the message with `value`.
+#[macro_export]
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::locale::tr($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::locale::format_message(
+            $crate::locale::tr($key),
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
+
+impl Locale {
+    /// Name of the language, in the language itself.
+    pub fn name(&self) -> &'static str {
+        self.message("language").unwrap_or(self.code)
+    }
+
+    /// Get the message for `key`, if this language has one.
+    pub fn message(&self, key: &str) -> Option<&'static str> {
+        let index = LOCALES.iter().position(|locale| locale.code == self.code)?;
+        catalogues()[index].get(key).map(String::as_str)
+    }
+}
+
+/// Get the messages of every bundled language, in the same order as `LOCALES`.
+fn catalogues() -> &'static Vec<HashMap<String, String>> {
+    static CATALOGUES: OnceLock<Vec<HashMap<String, String>>> = OnceLock::new();
+    CATALOGUES.get_or_init(|| {
+        LOCALES
+            .iter()
+            .map(|locale| serde_json::from_str(locale.source).expect("Invalid locale file!"))
+            .collect()
+    })
+}
+
+/// Get the language the game is being played in.
+pub fn current_locale() -> &'static Locale {
+    &LOCALES[CURRENT.load(Ordering::Relaxed)]
+}
+
+/// Switch to the language with the given `code`.
+///
+/// Returns 'false' if there is no such language, in which case nothing changes.
+pub fn set_locale(code: &str) -> bool {
+    match LOCALES.iter().position(|locale| locale.code == code) {
+        Some(index) => {
+            CURRENT.store(index, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Get the language the system is set to, if we have it.
+///
+/// Looks at the usual environment variables, like `LANG=es_ES.UTF-8`.
+pub fn system_locale() -> Option<&'static Locale> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| {
+            let code = value.split(['_', '.', '-']).next()?.to_lowercase();
+            LOCALES.iter().find(|locale| locale.code == code)
+        })
+}
+
+/// Get the message for `key` in the current language.
+///
+/// Falls back to the default language, and then to the key itself, so a missing message never
+/// stops the game.
+pub fn tr(key: &'static str) -> &'static str {
+    current_locale()
+        .message(key)
+        .or_else(|| LOCALES[0].message(key))
+        .unwrap_or(key)
+}
+
+/// Replace every `{name}` in `message` with its value from `args`.
+///
+/// The message is read once from left to right, so values that contain braces themselves are
+/// left as they are. Names that aren't in `args` are kept as written.
+pub fn format_message(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                text.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Testing for locales.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Get the names of the values a message fills in.
+    fn placeholders(message: &str) -> BTreeSet<&str> {
+        message
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    /// Every language has every message, filling in the same values.
+    #[test]
+    fn every_key_in_every_locale() {
+        let default = &catalogues()[0];
+        for (locale, catalogue) in LOCALES.iter().zip(catalogues()) {
+            for (key, message) in default {
+                let translated = catalogue
+                    .get(key)
+                    .unwrap_or_else(|| panic!("\"{}\" is missing {}", locale.code, key));
+                assert_eq!(
+                    placeholders(translated),
+                    placeholders(message),
+                    "\"{}\" fills in different values for {}",
+                    locale.code,
+                    key
+                );
+            }
+
+            for key in catalogue.keys() {
+                assert!(
+                    default.contains_key(key),
+                    "\"{}\" has {}, which isn't used",
+                    locale.code,
+                    key
+                );
+            }
+        }
+    }
+
+    /// Named values are filled in, wherever they are in the message.
+    #[test]
+    fn formatting() {
+        assert_eq!(
+            format_message("{b} then {a}, {a} again", &[("a", &1), ("b", &"two")]),
+            "two then 1, 1 again"
+        );
+        assert_eq!(
+            format_message("{a} and {b}", &[("a", &"{b}"), ("b", &"{a}")]),
+            "{b} and {a}"
+        );
+        assert_eq!(format_message("{c} {a", &[("a", &1)]), "{c} {a");
+        assert_eq!(tr("not a real key"), "not a real key");
+    }
+}
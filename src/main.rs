@@ -1,107 +1,477 @@
-pub mod game;
-pub mod multiplayer;
-pub mod singleplayer;
-pub mod util;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::net::{SocketAddr, ToSocketAddrs};
 
-use multiplayer::client::*;
-use multiplayer::server::*;
+use camp_misty::accessibility::*;
+use camp_misty::achievements::*;
+use camp_misty::console::*;
+use camp_misty::game::difficulty::*;
+use camp_misty::locale::*;
+use camp_misty::multiplayer::bot_server::*;
+use camp_misty::multiplayer::client::*;
+use camp_misty::multiplayer::listen::*;
+use camp_misty::multiplayer::packets::*;
+use camp_misty::multiplayer::recording::*;
+use camp_misty::multiplayer::server::*;
+use camp_misty::profile::*;
+use camp_misty::settings::*;
+use camp_misty::theme::*;
+use camp_misty::tutorial::*;
+use camp_misty::versus::*;
+use camp_misty::{singleplayer, tr, tui, util};
+
+/// Camp Misty, a game of cat and mouse between a killer and their victim.
+///
+/// Run without a command to use the menus.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// Use plain text instead of the full-screen terminal UI.
+    #[arg(long, global = true)]
+    plain: bool,
+
+    /// Use screen reader mode, with no ASCII art or colour and numbered choices.
+    #[arg(long, global = true)]
+    accessible: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Things that can be done straight from the command line.
+#[derive(Subcommand)]
+enum Command {
+    /// Host a game and wait for someone to join.
+    Host {
+        /// Port to listen on.
+        #[arg(long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+
+        /// Address or network interface to listen on, like "127.0.0.1", "[::]" or "eth0".
+        /// Listens everywhere by default.
+        #[arg(long)]
+        bind: Option<String>,
+
+        /// Who the host plays as.
+        #[arg(long, value_enum, default_value_t = Role::Random)]
+        role: Role,
+
+        /// Let spectators see where the car parts are hidden.
+        #[arg(long)]
+        reveal_parts: bool,
+
+        /// Seconds each player gets per turn, where 0 means no limit.
+        #[arg(long, default_value_t = 0)]
+        turn_time: TurnTimerPacket,
+
+        /// Password needed to join or watch the game.
+        #[arg(long)]
+        password: Option<String>,
+
+        /// Record every game so it can be replayed later.
+        #[arg(long)]
+        record: bool,
+    },
+
+    /// Replay a recorded game.
+    Replay {
+        /// Path to the recording.
+        file: std::path::PathBuf,
+    },
+
+    /// Join a game at ADDRESS, given as "IP:PORT".
+    Join {
+        /// Address of the host.
+        address: String,
+
+        /// Watch the game instead of playing.
+        #[arg(long)]
+        spectate: bool,
+
+        /// Password of a private game. Asked for if needed when left out.
+        #[arg(long)]
+        password: Option<String>,
+    },
+
+    /// Play against the AI.
+    Solo {
+        /// Who you play as.
+        #[arg(long, value_enum, default_value_t = Role::Random)]
+        role: Role,
+
+        /// How well the AI plays.
+        #[arg(long, value_enum, default_value_t = Level::Normal)]
+        difficulty: Level,
+
+        /// Seed for the car parts and the AI, to play the same game again.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+
+    /// Run a headless server where everyone who joins plays against the AI.
+    Bot {
+        /// Port to listen on.
+        #[arg(long, default_value_t = DEFAULT_PORT)]
+        port: u16,
+
+        /// Address or network interface to listen on, like "127.0.0.1", "[::]" or "eth0".
+        /// Listens everywhere by default.
+        #[arg(long)]
+        bind: Option<String>,
+
+        /// Who the bot plays as.
+        #[arg(long, value_enum, default_value_t = Role::Random)]
+        role: Role,
+
+        /// How well the bot plays.
+        #[arg(long, value_enum, default_value_t = Level::Normal)]
+        difficulty: Level,
+
+        /// Seconds each player gets per turn, where 0 means no limit.
+        #[arg(long, default_value_t = 0)]
+        turn_time: TurnTimerPacket,
+    },
+
+    /// Have the AI play against itself and report who wins.
+    Simulate {
+        /// Number of games to play.
+        #[arg(long, default_value_t = 10000)]
+        games: u32,
+
+        /// How well both sides play.
+        #[arg(long, value_enum, default_value_t = Level::Hard)]
+        difficulty: Level,
+
+        /// Seed to get the same results every time.
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+}
+
+/// Player type chosen on the command line.
+#[derive(Clone, Copy, ValueEnum)]
+enum Role {
+    Killer,
+    Victim,
+    Random,
+}
+
+impl Role {
+    /// Player type for this role, or `None` to choose randomly.
+    fn player_type(self) -> Option<PlayerType> {
+        match self {
+            Role::Killer => Some(PlayerType::Killer),
+            Role::Victim => Some(PlayerType::Victim),
+            Role::Random => None,
+        }
+    }
+}
+
+/// AI difficulty chosen on the command line.
+#[derive(Clone, Copy, ValueEnum)]
+enum Level {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl From<Level> for Difficulty {
+    fn from(level: Level) -> Difficulty {
+        match level {
+            Level::Easy => Difficulty::Easy,
+            Level::Normal => Difficulty::Normal,
+            Level::Hard => Difficulty::Hard,
+        }
+    }
+}
 
 fn main() {
+    let cli = Cli::parse();
+    if cli.plain {
+        tui::disable();
+    }
+
+    // Play in the language the user picked last time, or else the system's
+    let settings = Settings::load();
+    set_theme(settings.theme);
+    match settings.language {
+        Some(code) => {
+            set_locale(&code);
+        }
+        None => {
+            if let Some(locale) = system_locale() {
+                set_locale(locale.code);
+            }
+        }
+    }
+
+    let mut console = Console::stdio().with_profile(default_profile_path());
+    console.set_accessible(settings.accessible || cli.accessible);
+
+    match cli.command {
+        None => menu(&mut console),
+
+        Some(Command::Host {
+            port,
+            bind,
+            role,
+            reveal_parts,
+            turn_time,
+            password,
+            record,
+        }) => {
+            let mut server = match Server::new(bind_addr(bind.as_deref(), port)) {
+                Ok(server) => server,
+                Err(_) => exit_with_error(tr!("server.create_failed")),
+            };
+
+            let settings = HostSettings {
+                host_type: role.player_type(),
+                reveal_parts,
+                turn_time,
+                password,
+                record,
+            };
+            if server.host(&mut console, &settings).is_err() {
+                exit_with_error(tr!("cli.client_failed"));
+            }
+        }
+
+        Some(Command::Replay { file }) => match GameRecording::load(&file) {
+            Ok(recording) => replay(&mut console, &recording),
+            Err(e) => exit_with_error(&tr!("cli.bad_recording", path = file.display(), error = e)),
+        },
+
+        Some(Command::Join {
+            address,
+            spectate,
+            password,
+        }) => {
+            let addr = match parse_address(&address) {
+                Some(addr) => addr,
+                None => exit_with_error(tr!("cli.bad_address")),
+            };
+
+            let connection_type = if spectate {
+                ConnectionType::Spectator
+            } else {
+                ConnectionType::Player
+            };
+            if !Client::join(&mut console, &addr, connection_type, password.as_deref()) {
+                std::process::exit(1);
+            }
+        }
+
+        Some(Command::Solo {
+            role,
+            difficulty,
+            seed,
+        }) => singleplayer::play_solo(&mut console, role.player_type(), difficulty.into(), seed),
+
+        Some(Command::Bot {
+            port,
+            bind,
+            role,
+            difficulty,
+            turn_time,
+        }) => {
+            let settings = BotSettings {
+                bot_type: role.player_type(),
+                difficulty: difficulty.into(),
+                turn_time,
+            };
+            let server = match BotServer::new(bind_addr(bind.as_deref(), port), settings) {
+                Ok(server) => server,
+                Err(_) => exit_with_error(tr!("cli.bot_server_failed")),
+            };
+
+            // Serve clients forever
+            writeln!(console, "{}", tr!("cli.bot_server_listening"));
+            for addr in server.reachable_addrs() {
+                writeln!(console, "    {}", addr);
+            }
+            server.run(|event| println!("{}", event));
+        }
+
+        Some(Command::Simulate {
+            games,
+            difficulty,
+            seed,
+        }) => singleplayer::simulate(&mut console, games, difficulty.into(), seed),
+    }
+}
+
+/// Figure out the address to listen on from the `--bind` and `--port` options.
+fn bind_addr(bind: Option<&str>, port: u16) -> SocketAddr {
+    match bind {
+        Some(bind) => match parse_bind_addr(bind, port) {
+            Some(addr) => addr,
+            None => exit_with_error(tr!("cli.bad_bind_address")),
+        },
+        None => default_bind_addr(port),
+    }
+}
+
+/// Turn an "IP:PORT" string into an address.
+fn parse_address(address: &str) -> Option<SocketAddr> {
+    address.to_socket_addrs().ok()?.next()
+}
+
+/// Print `msg` and quit with an error code.
+fn exit_with_error(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
+
+/// Options on the main menu, along with their letters, in the order they are listed.
+const MENU: [(&str, char); 13] = [
+    ("menu.host", 'H'),
+    ("menu.join", 'J'),
+    ("menu.singleplayer", 'S'),
+    ("menu.versus", 'V'),
+    ("menu.replay", 'W'),
+    ("menu.stats", 'P'),
+    ("menu.achievements", 'A'),
+    ("menu.instructions", 'I'),
+    ("menu.tutorial", 'G'),
+    ("menu.language", 'L'),
+    ("menu.theme", 'T'),
+    ("menu.accessible", 'R'),
+    ("menu.quit", 'Q'),
+];
+
+/// Show the title screen and let the user pick what to do from the menus.
+fn menu(console: &mut Console) {
     // Title screen
-    util::print_title_screen();
+    util::print_title_screen(console);
 
     // Game loop over choices
     loop {
         // Ask for host, client, singleplayer instructions, or quit
-        println!("          (H)ost a game");
-        println!("          (J)oin a game");
-        println!("          (S)ingleplayer");
-        println!("          (I)nstructions");
-        println!("          (Q)uit");
+        for (i, (key, _)) in MENU.iter().enumerate() {
+            if console.is_accessible() {
+                writeln!(console, "{}. {}", i + 1, plain_name(tr!(key)));
+            } else {
+                writeln!(console, "          {}", tr!(key));
+            }
+        }
 
         // Determine selection
-        match util::pick_char(&['H', 'J', 'Q', 'I', 'S'], "Sorry, that isn't an option.") {
+        let choices: Vec<char> = MENU.iter().map(|(_, letter)| *letter).collect();
+        match util::pick_char(console, &choices, tr!("error.not_option")) {
             // Host a game
             'H' => {
                 // Host game
-                Server::host_game();
+                Server::host_game(console);
 
                 // Print title screen for main menu when finished
-                util::print_title_screen();
+                util::print_title_screen(console);
             }
 
             // Join a game
             'J' => {
                 // Join game
-                Client::join_game();
+                Client::join_game(console);
 
                 // Print title screen for main menu when finished
-                util::print_title_screen();
+                util::print_title_screen(console);
             }
 
             // Singleplayer
             'S' => {
                 // Play singleplayer
-                singleplayer::play_singleplayer();
+                singleplayer::play_singleplayer(console);
 
                 // Print title screen for main menu when finished
-                util::print_title_screen();
+                util::print_title_screen(console);
+            }
+
+            // Local versus
+            'V' => {
+                // Play against someone at the same keyboard
+                play_versus(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
+            }
+
+            // Replays
+            'W' => {
+                // Pick a recording and watch it
+                replay_menu(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
+            }
+
+            // Stats
+            'P' => {
+                stats_menu(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
+            }
+
+            // Achievements
+            'A' => {
+                achievements_menu(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
             }
 
             // Quit
             'Q' => {
                 // End the game
-                println!("Thanks for playing!");
+                writeln!(console, "{}", tr!("menu.goodbye"));
                 return;
             }
 
             // Instructions
             'I' => {
-                println!("Trapped within this hellish domain is a");
-                println!("victim, who is being hunted down by a ruthless killer!");
-                println!("The victim is trying to find 5 car parts so that they");
-                println!("can repair their vehicle and escape.");
-                println!("The killer is trying to stop the victim.\n");
-
-                println!("Layout of Camp Misty:");
-                println!("Camp Misty is broken up into five different locations:");
-                println!("The Cabin, the Abandoned Manor, the Bonfire,");
-                println!("the Old Forest, and Lake Misty itself.");
-                println!("Within each of these locations are five different spots.");
-                println!("Each spot may or may not contain a car part that the");
-                println!("victim needs.\n");
-
-                println!("Goal of the Victim:");
-                println!("You are trying to find 5 car parts.");
-                println!("There is exactly one car part in each location, so if you");
-                println!("find one in a location, you don’t need to keep checking it.");
-                println!("Try to choose locations and spots as randomly as you can so");
-                println!("that the killer can’t predict where you will go next!\n");
-
-                println!("Goal of the Killer:");
-                println!("You are trying to hunt down the victim.");
-                println!("Each time the victim finds a car part, you will be alerted as");
-                println!("to which location the part was found in.");
-                println!("That means you don’t have to check that location anymore");
-                println!("because the victim won’t be searching for parts there.\n");
-
-                println!("The Chase:");
-                println!("In the event that the victim and killer choose the same");
-                println!("location, but not the same spot, a chase will begin.");
-                println!("On the next round, the victim will need to hide in a spot in");
-                println!("the same location, and the killer will search for the victim.\n");
-
-                println!("Winning:");
-                println!("If the killer choses the same location and spot as the");
-                println!("victim, they will wound the victim.");
-                println!("If the victim is wounded two times, they die and the killer wins.");
-                println!("If the victim is able to find all five car parts, they win the game.\n");
-
-                println!("Enter anything to return to the main menu...");
-                util::read_str();
+                writeln!(console, "{}\n", tr!("instructions.story"));
+                writeln!(console, "{}\n", tr!("instructions.layout"));
+                writeln!(console, "{}\n", tr!("instructions.victim"));
+                writeln!(console, "{}\n", tr!("instructions.killer"));
+                writeln!(console, "{}\n", tr!("instructions.chase"));
+                writeln!(console, "{}\n", tr!("instructions.winning"));
+
+                writeln!(console, "{}", tr!("menu.return_to_main"));
+                util::read_str(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
+            }
+
+            // Tutorial
+            'G' => {
+                play_tutorial(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
+            }
+
+            // Language
+            'L' => {
+                pick_language(console);
 
                 // Print title screen for main menu when finished
-                util::print_title_screen();
+                util::print_title_screen(console);
+            }
+
+            // Theme
+            'T' => {
+                pick_theme(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
+            }
+
+            // Screen reader mode
+            'R' => {
+                toggle_accessible(console);
+
+                // Print title screen for main menu when finished
+                util::print_title_screen(console);
             }
 
             // Unknown
@@ -109,3 +479,116 @@ fn main() {
         }
     }
 }
+
+/// Turn screen reader mode on or off, and remember it for next time.
+fn toggle_accessible(console: &mut Console) {
+    let accessible = !console.is_accessible();
+    console.set_accessible(accessible);
+    if accessible {
+        writeln!(console, "{}", tr!("accessible.on"));
+    } else {
+        writeln!(console, "{}", tr!("accessible.off"));
+    }
+
+    let mut settings = Settings::load();
+    settings.accessible = accessible;
+    if settings.save().is_err() {
+        writeln!(console, "{}", tr!("settings.save_failed"));
+    }
+}
+
+/// Let the user pick the language to play in, and remember it for next time.
+fn pick_language(console: &mut Console) {
+    writeln!(console, "{}", tr!("language.pick"));
+    for (i, locale) in LOCALES.iter().enumerate() {
+        writeln!(console, "          ({}) {}", i + 1, locale.name());
+    }
+
+    let choices: Vec<char> = (1..=LOCALES.len())
+        .filter_map(|i| std::char::from_digit(i as u32, 10))
+        .collect();
+    let choice = util::pick_char(console, &choices, tr!("error.not_option"));
+    let locale = &LOCALES[choice.to_digit(10).unwrap() as usize - 1];
+    set_locale(locale.code);
+
+    let mut settings = Settings::load();
+    settings.language = Some(String::from(locale.code));
+    if settings.save().is_err() {
+        writeln!(console, "{}", tr!("settings.save_failed"));
+    }
+}
+
+/// Let the user pick the colours to play in, and remember them for next time.
+fn pick_theme(console: &mut Console) {
+    if !colors_enabled(console) {
+        writeln!(console, "{}", tr!("theme.no_color"));
+    }
+
+    // Each theme is shown in its own colours
+    writeln!(console, "{}", tr!("theme.pick"));
+    for (i, theme) in Theme::ALL.iter().enumerate() {
+        let name = if colors_enabled(console) {
+            theme.style(Tone::Title, theme.name())
+        } else {
+            String::from(theme.name())
+        };
+        writeln!(console, "          ({}) {}", i + 1, name);
+    }
+
+    let choices: Vec<char> = (1..=Theme::ALL.len())
+        .filter_map(|i| std::char::from_digit(i as u32, 10))
+        .collect();
+    let choice = util::pick_char(console, &choices, tr!("error.not_option"));
+    let theme = Theme::ALL[choice.to_digit(10).unwrap() as usize - 1];
+    set_theme(theme);
+
+    let mut settings = Settings::load();
+    settings.theme = theme;
+    if settings.save().is_err() {
+        writeln!(console, "{}", tr!("settings.save_failed"));
+    }
+}
+
+/// Testing for the command line.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Games can be set up with a difficulty and a seed, which are optional.
+    #[test]
+    fn parsing() {
+        let cli = Cli::try_parse_from([
+            "camp-misty",
+            "solo",
+            "--role",
+            "killer",
+            "--difficulty",
+            "hard",
+            "--seed",
+            "42",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Solo {
+                role: Role::Killer,
+                difficulty: Level::Hard,
+                seed: Some(42),
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["camp-misty", "--plain", "simulate"]).unwrap();
+        assert!(cli.plain);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Simulate {
+                games: 10000,
+                difficulty: Level::Hard,
+                seed: None,
+            })
+        ));
+
+        assert!(Cli::try_parse_from(["camp-misty", "solo", "--difficulty", "insane"]).is_err());
+        assert!(Cli::try_parse_from(["camp-misty", "simulate", "--seed", "-1"]).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::connection::*;
+use super::listen::*;
+use super::packets::*;
+use super::recording::*;
+use super::spectator::*;
+use super::turn_timer::*;
+use crate::game::difficulty::*;
+use crate::game::game_state::*;
+use crate::game::killer_ai::*;
+use crate::game::victim_ai::*;
+
+/// How long the bot sleeps between checks for the players move.
+const MOVE_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A headless server where every client that connects plays against the AI.
+pub struct BotServer {
+    /// TCP server listener.
+    listener: TcpListener,
+
+    /// How the bot plays.
+    settings: BotSettings,
+}
+
+/// How the bot on a bot server plays.
+#[derive(Clone, Copy)]
+pub struct BotSettings {
+    /// Player type the bot will be, or `None` to choose randomly each match.
+    pub bot_type: Option<PlayerType>,
+
+    /// How well the bot plays.
+    pub difficulty: Difficulty,
+
+    /// Seconds the player gets per turn, where 0 means no limit.
+    pub turn_time: TurnTimerPacket,
+}
+
+/// Something that happened on a bot server, for whoever is running it to report.
+pub enum BotServerEvent {
+    /// A player at the address started a match against the bot.
+    MatchStarted(String),
+
+    /// The player at the address left.
+    PlayerLeft(String),
+}
+
+impl fmt::Display for BotServerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BotServerEvent::MatchStarted(addr) => write!(f, "{} is playing against the bot.", addr),
+            BotServerEvent::PlayerLeft(addr) => write!(f, "{} has left.", addr),
+        }
+    }
+}
+
+/// The AI playing one side of a game.
+enum Bot {
+    Killer(KillerAI),
+    Victim(VictimAI),
+}
+
+impl BotServer {
+    /// Constructor.
+    ///
+    /// Takes the address to listen for players on.
+    pub fn new(addr: SocketAddr, settings: BotSettings) -> Result<BotServer, BotServerError> {
+        match bind_listener(addr) {
+            Ok(listener) => Ok(BotServer { listener, settings }),
+            Err(_) => Err(BotServerError),
+        }
+    }
+
+    /// Get every address players can use to reach the server.
+    pub fn reachable_addrs(&self) -> Vec<SocketAddr> {
+        reachable_addrs(&self.listener)
+    }
+
+    /// Accept clients forever, giving each one a match against the bot on its own thread.
+    ///
+    /// Everything that happens is passed to `log`.
+    pub fn run(&self, log: impl Fn(BotServerEvent) + Send + Sync + 'static) {
+        let log = Arc::new(log);
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+
+            let settings = self.settings;
+            let log = log.clone();
+            std::thread::spawn(move || {
+                let addr = match stream.peer_addr() {
+                    Ok(addr) => addr.to_string(),
+                    Err(_) => String::from("unknown address"),
+                };
+
+                // Bot matches can't be watched
+                let mut conn = match read_connection_type(stream, None) {
+                    Some((mut conn, ConnectionType::Player)) => {
+                        if try_write_over_tcp(&mut conn, &ConnectionResponse::Accepted).is_err() {
+                            return;
+                        }
+                        conn
+                    }
+
+                    Some((mut conn, ConnectionType::Spectator)) => {
+                        let _ = try_write_over_tcp(&mut conn, &ConnectionResponse::GameFull);
+                        return;
+                    }
+
+                    None => return,
+                };
+
+                log(BotServerEvent::MatchStarted(addr.clone()));
+                let _ = play_bot_match(&mut conn, settings, StdRng::from_entropy());
+                log(BotServerEvent::PlayerLeft(addr));
+            });
+        }
+    }
+}
+
+/// Play games against the player at the other end of `conn` until they don't want a rematch.
+///
+/// Games and the bot's moves are generated with `rng`. Returns an error if the player left or
+/// sent an invalid move.
+fn play_bot_match(
+    conn: &mut Connection,
+    settings: BotSettings,
+    mut rng: StdRng,
+) -> std::io::Result<()> {
+    let mut bot_type = settings.bot_type.unwrap_or_else(|| {
+        if rng.gen_range(0, 2) == 0 {
+            PlayerType::Killer
+        } else {
+            PlayerType::Victim
+        }
+    });
+
+    // The player enforces the turn timer, but we keep track of it too
+    try_write_over_tcp(conn, &settings.turn_time)?;
+    let mut timer = TurnTimer::new(settings.turn_time);
+
+    loop {
+        timer.reset();
+        play_bot_game(conn, bot_type, settings.difficulty, &mut rng, &mut timer)?;
+
+        // Bot matches aren't recorded
+        try_write_over_tcp::<RecordingPacket>(conn, &None)?;
+
+        // The bot always wants whatever the player wants
+        let vote = try_read_over_tcp::<RematchVote>(conn)?;
+        try_write_over_tcp(conn, &vote)?;
+        match vote.combine(vote) {
+            Some(true) => bot_type = bot_type.opposite(),
+            Some(false) => {}
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Play a single game against the player at the other end of `conn`.
+///
+/// Returns an error if the player left or sent an invalid move.
+fn play_bot_game(
+    conn: &mut Connection,
+    bot_type: PlayerType,
+    difficulty: Difficulty,
+    rng: &mut StdRng,
+    timer: &mut TurnTimer,
+) -> std::io::Result<()> {
+    // Generate the game
+    let mut state = GameState::new();
+    state.gen_state_with(rng);
+
+    let bot_rng = StdRng::seed_from_u64(rng.gen());
+    let mut bot = match bot_type {
+        PlayerType::Killer => Bot::Killer(KillerAI::with_difficulty(&state, difficulty, bot_rng)),
+        PlayerType::Victim => Bot::Victim(VictimAI::with_difficulty(&state, difficulty, bot_rng)),
+    };
+
+    // The player is told the bot is the host
+    try_write_over_tcp(conn, &bot_type)?;
+    try_write_over_tcp(conn, &GameStatePacket::from_state(&state))?;
+
+    // Play rounds until someone wins
+    loop {
+        // Nobody can spectate bot matches
+        let count: SpectatorCountPacket = 0;
+        try_write_over_tcp(conn, &count)?;
+
+        // Pick our move now, but keep it to ourselves until the player has moved
+        let bot_move = match &mut bot {
+            Bot::Killer(killer) => killer.play(&state),
+            Bot::Victim(victim) => victim.play(&mut state),
+        };
+
+        // Wait for the players move, ignoring their chat
+        let leave_deadline = timer.leave_deadline();
+        let player_move = loop {
+            // Players who stop responding forfeit the match
+            if leave_deadline.is_some_and(|d| Instant::now() >= d) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "player stopped responding",
+                ));
+            }
+
+            if !has_data(conn)? {
+                std::thread::sleep(MOVE_POLL_INTERVAL);
+                continue;
+            }
+
+            match try_read_over_tcp::<RoundMessage>(conn)? {
+                RoundMessage::Move(m) => break m,
+                RoundMessage::TimedOut(m) => {
+                    timer.record_timeout(bot_type.opposite());
+                    break m;
+                }
+                _ => {}
+            }
+        };
+
+        // Broken clients end the match
+        let player_move = (player_move.0 as usize, player_move.1 as usize);
+        if !state.is_valid_move(player_move) {
+            return Err(invalid_data("invalid move"));
+        }
+
+        // Only now that both moves are in does the player get to see ours
+        let bot_move_packet = MovePacket(bot_move.0 as u32, bot_move.1 as u32);
+        try_write_over_tcp(conn, &RoundMessage::Move(bot_move_packet))?;
+
+        // Let the player finish the round
+        try_write_over_tcp(conn, &RoundMessage::EndOfRound)?;
+        while !matches!(
+            try_read_over_tcp::<RoundMessage>(conn)?,
+            RoundMessage::EndOfRound
+        ) {}
+
+        // Running out of time too often forfeits the game
+        if timer.forfeiter().is_some() {
+            return Ok(());
+        }
+
+        let res = match bot_type {
+            PlayerType::Killer => state.play(player_move, bot_move),
+            PlayerType::Victim => state.play(bot_move, player_move),
+        };
+
+        if res.result == RoundResult::Caught || res.result == RoundResult::AllPartsFound {
+            return Ok(());
+        }
+    }
+}
+
+/// Bot server initialization error.
+#[derive(Debug)]
+pub struct BotServerError;
+
+/// Testing for the bot server.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The bot plays legal moves and the game ends cleanly when the player quits.
+    #[test]
+    fn bot_match() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let bot = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::accept(stream, None).unwrap();
+            let settings = BotSettings {
+                bot_type: Some(PlayerType::Killer),
+                difficulty: Difficulty::Easy,
+                turn_time: 0,
+            };
+            play_bot_match(&mut conn, settings, StdRng::seed_from_u64(1)).is_ok()
+        });
+
+        let mut conn = Connection::connect(&addr, None).unwrap();
+        assert_eq!(try_read_over_tcp::<TurnTimerPacket>(&mut conn).unwrap(), 0);
+        assert_eq!(
+            try_read_over_tcp::<PlayerType>(&mut conn).unwrap(),
+            PlayerType::Killer
+        );
+        let mut state = GameState::new();
+        for (i, j) in try_read_over_tcp::<GameStatePacket>(&mut conn)
+            .unwrap()
+            .hidden_parts
+        {
+            state.hide_part(i as usize, j as usize);
+        }
+
+        // Search at random until the game is over
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut rounds = 0;
+        loop {
+            rounds += 1;
+            assert!(rounds < 1000, "the game never ended");
+            assert_eq!(
+                try_read_over_tcp::<SpectatorCountPacket>(&mut conn).unwrap(),
+                0
+            );
+
+            // The bot doesn't show its move until it has ours
+            assert!(!has_data(&mut conn).unwrap());
+            let victim_move = state.random_move_with(&mut rng);
+            let packet = MovePacket(victim_move.0 as u32, victim_move.1 as u32);
+            write_over_tcp(&mut conn, &RoundMessage::Move(packet));
+
+            let killer_move = match try_read_over_tcp::<RoundMessage>(&mut conn).unwrap() {
+                RoundMessage::Move(m) => (m.0 as usize, m.1 as usize),
+                _ => panic!("the bot didn't move"),
+            };
+            assert!(state.is_valid_move(killer_move));
+
+            write_over_tcp(&mut conn, &RoundMessage::EndOfRound);
+            assert!(matches!(
+                try_read_over_tcp::<RoundMessage>(&mut conn).unwrap(),
+                RoundMessage::EndOfRound
+            ));
+
+            let res = state.play(victim_move, killer_move);
+            if res.result == RoundResult::Caught || res.result == RoundResult::AllPartsFound {
+                break;
+            }
+        }
+
+        // Turn down a rematch
+        assert!(try_read_over_tcp::<RecordingPacket>(&mut conn)
+            .unwrap()
+            .is_none());
+        write_over_tcp(&mut conn, &RematchVote::Quit);
+        assert!(try_read_over_tcp::<RematchVote>(&mut conn).unwrap() == RematchVote::Quit);
+        assert!(bot.join().unwrap());
+    }
+}
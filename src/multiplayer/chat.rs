@@ -0,0 +1,121 @@
+use super::connection::*;
+use super::packets::*;
+use crate::console::*;
+use crate::locale::*;
+use crate::tr;
+use crate::util::*;
+
+/// Longest chat message that can be sent, in characters.
+pub const MAX_CHAT_LENGTH: usize = 200;
+
+/// Keys of the preset taunts that can be sent with a shortcut.
+///
+/// Taunts are sent in the language of whoever sends them.
+pub const TAUNTS: [&str; 5] = [
+    "chat.taunt.breathing",
+    "chat.taunt.hide",
+    "chat.taunt.twig",
+    "chat.taunt.nice_try",
+    "chat.taunt.grave",
+];
+
+/// Print out how to chat with the other player.
+pub fn print_chat_help(console: &mut Console) {
+    writeln!(console, "{}", tr!("chat.help"));
+}
+
+/// Deal with a line the user typed while waiting, sending it to the other player if needed.
+pub fn handle_chat_input(
+    console: &mut Console,
+    stream: &mut Connection,
+    line: &str,
+) -> std::io::Result<()> {
+    match chat_from_input(line) {
+        ChatInput::Send(msg) => try_write_over_tcp(stream, &RoundMessage::Chat(msg))?,
+
+        ChatInput::ListTaunts => {
+            for (i, taunt) in TAUNTS.iter().enumerate() {
+                writeln!(console, "    /{} {}", i + 1, tr(taunt));
+            }
+        }
+
+        ChatInput::Nothing => {}
+    }
+
+    print_prompt(console);
+    Ok(())
+}
+
+/// Print a chat message sent by the other player, who is a `from`.
+pub fn print_chat(console: &mut Console, from: PlayerType, msg: &str) {
+    let name = match from {
+        PlayerType::Killer => tr!("chat.killer"),
+        PlayerType::Victim => tr!("chat.victim"),
+    };
+
+    // Messages are cleaned up in case the other player isn't playing nice
+    writeln!(console, "\n[{}] {}", name, printable(msg, MAX_CHAT_LENGTH));
+}
+
+/// What the user wants to do with a line they typed.
+#[derive(Debug, PartialEq)]
+pub enum ChatInput {
+    /// Send a message.
+    Send(String),
+
+    /// List the taunts.
+    ListTaunts,
+
+    /// Nothing.
+    Nothing,
+}
+
+/// Figure out what the user wants to do with a line they typed while waiting.
+pub fn chat_from_input(line: &str) -> ChatInput {
+    let line = line.trim();
+
+    if line.is_empty() {
+        return ChatInput::Nothing;
+    }
+
+    if line.eq_ignore_ascii_case("/t") {
+        return ChatInput::ListTaunts;
+    }
+
+    // Taunt shortcuts
+    if let Some(num) = line.strip_prefix('/') {
+        return match num.parse::<usize>() {
+            Ok(i) if i >= 1 && i <= TAUNTS.len() => {
+                ChatInput::Send(String::from(tr(TAUNTS[i - 1])))
+            }
+            _ => ChatInput::ListTaunts,
+        };
+    }
+
+    ChatInput::Send(line.chars().take(MAX_CHAT_LENGTH).collect())
+}
+
+/// Testing for chat.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Messages from the other player can't be too long or mess with the terminal.
+    #[test]
+    fn cleaning_up_messages() {
+        let (mut console, output) = Console::scripted(&[]);
+        print_chat(
+            &mut console,
+            PlayerType::Killer,
+            "\x1b[2J\x1b]0;pwned\x07Behind\r\n you\u{9b}31m",
+        );
+        assert_eq!(output.text(), "\n[Killer] [2J]0;pwnedBehind you31m\n");
+
+        let (mut console, output) = Console::scripted(&[]);
+        print_chat(&mut console, PlayerType::Victim, &"a".repeat(500));
+        assert_eq!(
+            output.text().trim().len(),
+            "[Victim] ".len() + MAX_CHAT_LENGTH
+        );
+    }
+}
@@ -1,8 +1,16 @@
-use std::net::ToSocketAddrs;
+use std::net::{SocketAddr, ToSocketAddrs};
 
+use super::connection::*;
+use super::discovery::*;
 use super::net_play::*;
 use super::packets::*;
+use super::recording::*;
+use super::security::*;
+use super::spectator::*;
+use super::turn_timer::*;
+use crate::console::*;
 use crate::game::game_state::*;
+use crate::tr;
 use crate::util::*;
 
 /// A client that joins a hosts game.
@@ -11,64 +19,120 @@ pub struct Client {
     state: GameState,
 
     /// Server stream.
-    server: std::net::TcpStream,
+    server: Connection,
 }
 
 impl Client {
     /// Create a new client stream.
     ///
-    /// The only argument is the address to connect to.
-    pub fn new(addr: &std::net::SocketAddr) -> Result<Client, ClientError> {
-        match std::net::TcpStream::connect(addr) {
+    /// Takes the address to connect to, and the `password` if the game is private.
+    pub fn new(addr: &std::net::SocketAddr, password: Option<&str>) -> Result<Client, ClientError> {
+        match Connection::connect(addr, password) {
             Ok(stream) => Ok(Client {
                 state: GameState::new(),
                 server: stream,
             }),
 
-            Err(_) => Err(ClientError),
+            Err(e) => Err(match AuthError::from_io_error(&e) {
+                Some(AuthError::PasswordRequired) => ClientError::PasswordRequired,
+                Some(AuthError::AccessDenied) => ClientError::AccessDenied,
+                None => ClientError::ConnectionFailed,
+            }),
+        }
+    }
+
+    /// Connect to the host at `addr`, asking for the password if the game is private.
+    fn connect(console: &mut Console, addr: &SocketAddr) -> Result<Client, ClientError> {
+        writeln!(console, "{}", tr!("client.connecting", addr = addr));
+        let mut password: Option<String> = None;
+        loop {
+            match Client::new(addr, password.as_deref()) {
+                Err(ClientError::PasswordRequired) => {
+                    writeln!(console, "{}", tr!("client.enter_password"));
+                    password = Some(read_str(console));
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Join the game at `addr` straight away, without going through the menus.
+    ///
+    /// Returns `false` if we couldn't get in.
+    pub fn join(
+        console: &mut Console,
+        addr: &SocketAddr,
+        connection_type: ConnectionType,
+        password: Option<&str>,
+    ) -> bool {
+        let res = match password {
+            Some(password) => {
+                writeln!(console, "{}", tr!("client.connecting", addr = addr));
+                Client::new(addr, Some(password))
+            }
+            None => Client::connect(console, addr),
+        };
+
+        match res {
+            Ok(mut client) => {
+                writeln!(console, "{}", tr!("client.connected"));
+                client.start(console, connection_type);
+                true
+            }
+
+            Err(ClientError::AccessDenied) => {
+                writeln!(console, "{}", tr!("client.access_denied"));
+                false
+            }
+
+            Err(_) => {
+                writeln!(console, "{}", tr!("client.join_failed"));
+                false
+            }
         }
     }
 
     /// Join a game.
-    pub fn join_game() {
+    pub fn join_game(console: &mut Console) {
+        // Ask if we want to play or just watch
+        writeln!(console, "{}", tr!("client.ask_connection_type"));
+        let connection_type = match pick_char(console, &['P', 'S'], tr!("error.not_option")) {
+            'P' => ConnectionType::Player,
+            'S' => ConnectionType::Spectator,
+            _ => panic!("Invalid input."),
+        };
+
         // Loop to create client
         let mut client: Client;
         loop {
-            // Ask for address of host
-            println!("Please enter the address of the host.");
-            println!("Use the format \"IP:PORT\".");
-
-            let addr = match read_str().to_socket_addrs() {
-                Ok(mut addr_list) => {
-                    // Only take the first address
-                    if let Some(addr) = addr_list.next() {
-                        addr
-                    } else {
-                        println!("Sorry, I couldn't understand you.");
-                        continue;
-                    }
-                }
-
-                Err(_) => {
-                    println!("Sorry, I couldn't understand you.");
-                    continue;
-                }
+            // Ask where the host is
+            writeln!(console, "{}", tr!("client.ask_find_host"));
+            let addr = match pick_char(console, &['E', 'B'], tr!("error.not_option")) {
+                'E' => ask_for_address(console),
+                'B' => match browse_local_games(console) {
+                    Some(addr) => addr,
+                    None => continue,
+                },
+                _ => panic!("Invalid input."),
             };
 
-            // Attempt to create client
-            println!("Attempting to join connect to {}...", addr);
-            match Client::new(&addr) {
+            // Attempt to create client, asking for the password if the game is private
+            match Client::connect(console, &addr) {
                 Ok(c) => {
                     client = c;
                     break;
                 }
 
-                Err(_) => {
+                Err(e) => {
                     // Option to try again or quit to main menu
-                    println!("There was a problem joining the host.");
-                    println!("Would you like to (T)ry again or (R)eturn to the main menu?");
+                    if e == ClientError::AccessDenied {
+                        writeln!(console, "{}", tr!("client.access_denied"));
+                    } else {
+                        writeln!(console, "{}", tr!("client.join_failed"));
+                    }
+                    writeln!(console, "{}", tr!("menu.try_again"));
 
-                    match pick_char(&['T', 'R'], "Sorry, that isn't an option.") {
+                    match pick_char(console, &['T', 'R'], tr!("error.not_option")) {
                         'T' => {}
                         'R' => {
                             return;
@@ -79,47 +143,411 @@ impl Client {
             }
         }
 
-        // Play the game
-        println!("Connected!");
-        client.play();
+        // Play or watch the game
+        writeln!(console, "{}", tr!("client.connected"));
+        client.start(console, connection_type);
+
+        // Return to title
+        writeln!(console, "{}", tr!("menu.return_to_title"));
+        read_str(console);
+    }
+
+    /// Play or watch the game, depending on the `connection_type`.
+    fn start(&mut self, console: &mut Console, connection_type: ConnectionType) {
+        match connection_type {
+            ConnectionType::Player => self.play(console),
+            ConnectionType::Spectator => spectate(console, &mut self.server),
+        }
     }
 
     /// Play the game!
-    pub fn play(&mut self) {
-        // Determine what player type we are
-        println!("Waiting for host to choose player type...");
+    pub fn play(&mut self, console: &mut Console) {
+        // Tell the host we want to play
+        write_over_tcp(&mut self.server, &ConnectionType::Player);
+        match try_read_over_tcp::<ConnectionResponse>(&mut self.server) {
+            Ok(ConnectionResponse::Accepted) => {}
 
-        // The server tells us what their player type is, so ours is the opposite
-        let player_type: PlayerType;
-        match read_over_tcp::<PlayerType>(&mut self.server) {
-            PlayerType::Killer => {
-                println!("You are the victim!");
-                player_type = PlayerType::Victim;
+            Ok(ConnectionResponse::GameFull) => {
+                writeln!(console, "{}", tr!("client.game_full"));
+                return;
             }
 
-            PlayerType::Victim => {
-                println!("You are the killer!");
-                player_type = PlayerType::Killer;
+            // Lobby servers need us to find an opponent first
+            Ok(ConnectionResponse::Lobby) => {
+                if !self.lobby_menu(console) {
+                    return;
+                }
             }
+
+            Err(_) => return host_lost(console),
+        }
+
+        // The host decides how long each turn can take
+        writeln!(console, "{}", tr!("client.waiting_for_setup"));
+        let turn_time = match try_read_over_tcp::<TurnTimerPacket>(&mut self.server) {
+            Ok(turn_time) => turn_time,
+            Err(_) => return host_lost(console),
+        };
+        if turn_time > 0 {
+            writeln!(console, "{}", tr!("client.turn_time", seconds = turn_time));
         }
+        let mut timer = TurnTimer::new(turn_time);
+
+        // Keep playing until someone doesn't want a rematch
+        let mut score = SeriesScore::default();
+        let mut watching = 0;
+        loop {
+            // Determine what player type we are
+            writeln!(console, "{}", tr!("client.waiting_for_role"));
+
+            // The server tells us what their player type is, so ours is the opposite
+            let player_type = match try_read_over_tcp::<PlayerType>(&mut self.server) {
+                Ok(PlayerType::Killer) => {
+                    writeln!(console, "{}", tr!("client.you_are_victim"));
+                    PlayerType::Victim
+                }
+
+                Ok(PlayerType::Victim) => {
+                    writeln!(console, "{}", tr!("client.you_are_killer"));
+                    PlayerType::Killer
+                }
+
+                Err(_) => return host_lost(console),
+            };
+
+            // Read game state
+            let loaded_state = match try_read_over_tcp::<GameStatePacket>(&mut self.server) {
+                Ok(loaded_state) => loaded_state,
+                Err(_) => return host_lost(console),
+            };
+
+            // Update our state with new state
+            self.state = GameState::new();
+            timer.reset();
+            for part in &loaded_state.hidden_parts {
+                self.state.hide_part(part.0 as usize, part.1 as usize);
+            }
 
-        // Read game state
-        let loaded_state = read_over_tcp::<GameStatePacket>(&mut self.server);
+            // Game loop
+            let last_round = loop {
+                // The host tells us how many people are watching before every round
+                let count = match try_read_over_tcp::<SpectatorCountPacket>(&mut self.server) {
+                    Ok(count) => count,
+                    Err(_) => return host_lost(console),
+                };
+                print_spectator_count(console, &mut watching, count as usize);
 
-        // Update our state with new state
-        for part in &loaded_state.hidden_parts {
-            self.state.hide_part(part.0 as usize, part.1 as usize);
+                let round = net_play(
+                    console,
+                    player_type,
+                    &mut self.state,
+                    &mut self.server,
+                    &mut timer,
+                );
+                if round.is_finished() {
+                    break round;
+                }
+            };
+
+            // Update the score and see if we're going again
+            score.record(player_type, &last_round);
+            score.print(console);
+            if last_round.other_player_left {
+                break;
+            }
+            offer_recording(console, &mut self.server);
+            if vote_for_rematch(console, &mut self.server).is_none() {
+                break;
+            }
         }
+    }
 
-        // Game loop
-        while !net_play(player_type, &mut self.state, &mut self.server) {}
+    /// Send a `request` to the lobby server and read its response.
+    fn lobby_request(&mut self, request: &LobbyRequest) -> std::io::Result<LobbyResponse> {
+        try_write_over_tcp(&mut self.server, request)?;
+        try_read_over_tcp(&mut self.server)
+    }
 
-        // Return to title
-        println!("Enter anything to return to the title screen...");
-        read_str();
+    /// Let the user create or join a room on a lobby server.
+    ///
+    /// Returns 'true' once a match is starting, or 'false' if the user wants to leave.
+    fn lobby_menu(&mut self, console: &mut Console) -> bool {
+        writeln!(console, "{}", tr!("lobby.welcome"));
+
+        loop {
+            writeln!(console, "{}", tr!("lobby.menu"));
+            match pick_char(console, &['L', 'C', 'J', 'R'], tr!("error.not_option")) {
+                'L' => {
+                    let response = match self.lobby_request(&LobbyRequest::ListRooms) {
+                        Ok(response) => response,
+                        Err(_) => return lobby_lost(console),
+                    };
+                    if let LobbyResponse::Rooms(rooms) = response {
+                        if rooms.is_empty() {
+                            writeln!(console, "{}", tr!("lobby.no_rooms"));
+                        }
+
+                        for room in rooms {
+                            let role = match room.creator_role {
+                                Some(PlayerType::Killer) => tr!("lobby.creator_is_killer"),
+                                Some(PlayerType::Victim) => tr!("lobby.creator_is_victim"),
+                                None => tr!("menu.random_roles"),
+                            };
+                            let timer = if room.turn_time == 0 {
+                                String::from(tr!("lobby.no_time_limit"))
+                            } else {
+                                tr!("lobby.time_limit", seconds = room.turn_time)
+                            };
+                            writeln!(
+                                console,
+                                "    {}",
+                                tr!(
+                                    "lobby.room",
+                                    name = room.name,
+                                    code = room.code,
+                                    role = role,
+                                    timer = timer
+                                )
+                            );
+                        }
+                    }
+                }
+
+                'C' => {
+                    writeln!(console, "{}", tr!("lobby.ask_name"));
+                    let name = read_str(console);
+
+                    writeln!(console, "{}", tr!("menu.ask_role"));
+                    let role = match pick_char(console, &['K', 'V', 'R'], tr!("error.not_option")) {
+                        'K' => Some(PlayerType::Killer),
+                        'V' => Some(PlayerType::Victim),
+                        'R' => None,
+                        _ => panic!("Invalid option chosen!"),
+                    };
+
+                    let turn_time = ask_turn_time(console);
+
+                    let response = match self.lobby_request(&LobbyRequest::CreateRoom {
+                        name: name.clone(),
+                        role,
+                        turn_time,
+                    }) {
+                        Ok(response) => response,
+                        Err(_) => return lobby_lost(console),
+                    };
+                    match response {
+                        LobbyResponse::RoomCreated(code) => {
+                            writeln!(
+                                console,
+                                "{}",
+                                tr!("lobby.room_created", name = name, code = code)
+                            );
+                            writeln!(console, "{}", tr!("lobby.waiting_for_opponent"));
+
+                            // The server tells us when someone joins
+                            return match try_read_over_tcp::<LobbyResponse>(&mut self.server) {
+                                Ok(_) => true,
+                                Err(_) => lobby_lost(console),
+                            };
+                        }
+
+                        LobbyResponse::Error(LobbyError::NameTaken) => {
+                            writeln!(console, "{}", tr!("lobby.name_taken"));
+                        }
+
+                        _ => writeln!(console, "{}", tr!("lobby.create_failed")),
+                    }
+                }
+
+                'J' => {
+                    writeln!(console, "{}", tr!("lobby.enter_room"));
+                    let request = LobbyRequest::JoinRoom(read_str(console));
+                    match self.lobby_request(&request) {
+                        Ok(LobbyResponse::MatchStarting) => return true,
+                        Ok(_) => writeln!(console, "{}", tr!("lobby.room_not_found")),
+                        Err(_) => return lobby_lost(console),
+                    }
+                }
+
+                'R' => return false,
+
+                _ => panic!("Invalid input."),
+            }
+        }
+    }
+}
+
+/// Tell the user the host went away.
+fn host_lost(console: &mut Console) {
+    writeln!(console, "{}", tr!("client.lost_connection"));
+}
+
+/// Tell the user the lobby server went away, returning 'false' so they leave the lobby.
+fn lobby_lost(console: &mut Console) -> bool {
+    writeln!(console, "{}", tr!("lobby.lost_connection"));
+    false
+}
+
+/// Read the recording the host might have made of the game, and offer to save a copy.
+fn offer_recording(console: &mut Console, stream: &mut Connection) {
+    if let Ok(Some(recording)) = try_read_over_tcp::<RecordingPacket>(stream) {
+        writeln!(console, "{}", tr!("client.ask_save_recording"));
+        if pick_char(console, &['Y', 'N'], tr!("error.not_option")) == 'Y' {
+            match recording.save() {
+                Ok(path) => writeln!(
+                    console,
+                    "{}",
+                    tr!("client.recording_saved", path = path.display())
+                ),
+                Err(_) => writeln!(console, "{}", tr!("client.recording_save_failed")),
+            }
+        }
+    }
+}
+
+/// Ask the user for the address of the host.
+fn ask_for_address(console: &mut Console) -> SocketAddr {
+    loop {
+        writeln!(console, "{}", tr!("client.enter_address"));
+        writeln!(console, "{}", tr!("client.enter_address_hint"));
+
+        match read_str(console).to_socket_addrs() {
+            // Only take the first address
+            Ok(mut addr_list) => match addr_list.next() {
+                Some(addr) => return addr,
+                None => writeln!(console, "{}", tr!("error.not_understood")),
+            },
+
+            Err(_) => writeln!(console, "{}", tr!("error.not_understood")),
+        }
+    }
+}
+
+/// Let the user pick a game on the local network.
+///
+/// Returns `None` if the user wants to go back.
+fn browse_local_games(console: &mut Console) -> Option<SocketAddr> {
+    loop {
+        writeln!(console, "{}", tr!("browse.looking"));
+        let games = match find_local_games(BROWSE_TIME) {
+            Ok(games) => games,
+            Err(_) => {
+                writeln!(console, "{}", tr!("browse.failed"));
+                return None;
+            }
+        };
+
+        if games.is_empty() {
+            writeln!(console, "{}", tr!("browse.none"));
+        }
+
+        for (i, game) in games.iter().enumerate() {
+            let role = match game.announcement.host_type {
+                Some(PlayerType::Killer) => tr!("browse.host_is_killer"),
+                Some(PlayerType::Victim) => tr!("browse.host_is_victim"),
+                None => tr!("menu.random_roles"),
+            };
+            let game_name = tr!(
+                "browse.game",
+                host = game.announcement.host_name,
+                addr = game.addr,
+                role = role
+            );
+            if game.announcement.private {
+                writeln!(
+                    console,
+                    "    {}) {} {}",
+                    i + 1,
+                    game_name,
+                    tr!("browse.private")
+                );
+            } else {
+                writeln!(console, "    {}) {}", i + 1, game_name);
+            }
+        }
+
+        writeln!(console, "{}", tr!("browse.pick"));
+        loop {
+            let input = read_str(console);
+            if let Ok(i) = input.parse::<usize>() {
+                if i >= 1 && i <= games.len() {
+                    return Some(games[i - 1].addr);
+                }
+            }
+
+            match input.to_uppercase().as_str() {
+                "R" => break,
+                "B" => return None,
+                _ => writeln!(console, "{}", tr!("error.not_option")),
+            }
+        }
     }
 }
 
 /// Error that might be thrown if there was an issue creating a client.
-#[derive(Debug)]
-pub struct ClientError;
+#[derive(Debug, PartialEq)]
+pub enum ClientError {
+    /// The host couldn't be reached, or didn't speak our language.
+    ConnectionFailed,
+
+    /// The game is private and we didn't give a password.
+    PasswordRequired,
+
+    /// The game is private and we gave the wrong password.
+    AccessDenied,
+}
+
+/// Testing for joining.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Hosts that can't be reached let the user try again or go back to the menu.
+    #[test]
+    fn join_menu() {
+        // Nothing is listening on a port we just gave back
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = format!("127.0.0.1:{}", port);
+
+        let (mut console, output) =
+            Console::scripted(&["X", "P", "E", "nowhere", &addr, "T", "E", &addr, "R"]);
+        Client::join_game(&mut console);
+
+        let output = output.text();
+        assert!(output.contains("Sorry, that isn't an option."));
+        assert!(output.contains("Sorry, I didn't understand you."));
+        assert_eq!(
+            output
+                .matches("There was a problem joining the host.")
+                .count(),
+            2
+        );
+        assert!(!output.contains("Connected!"));
+    }
+
+    /// Hosts that go away during setup are reported instead of crashing the game.
+    #[test]
+    fn host_leaving() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let host = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::accept(stream, None).unwrap();
+            assert!(
+                try_read_over_tcp::<ConnectionType>(&mut conn).unwrap() == ConnectionType::Player
+            );
+            write_over_tcp(&mut conn, &ConnectionResponse::Accepted);
+        });
+
+        let mut client = Client::new(&addr, None).unwrap();
+        let (mut console, output) = Console::scripted(&[]);
+        client.play(&mut console);
+        host.join().unwrap();
+        assert!(output.text().contains("Lost connection to the host."));
+    }
+}
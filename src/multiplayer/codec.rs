@@ -0,0 +1,221 @@
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// A way of turning packets into bytes and back again.
+pub trait Codec {
+    /// Encode a value into bytes.
+    fn encode<T: Serialize>(&self, val: &T) -> Result<Vec<u8>, CodecError>;
+
+    /// Decode a value from bytes.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// Encodes packets as JSON.
+///
+/// Easy to read and to work with from other languages, but not very compact.
+#[derive(Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, val: &T) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(val).map_err(|_| CodecError)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        serde_json::from_slice(bytes).map_err(|_| CodecError)
+    }
+}
+
+/// Encodes packets in a compact binary format.
+#[derive(Clone, Copy, Default)]
+pub struct BinaryCodec;
+
+impl BinaryCodec {
+    /// Options used for encoding.
+    ///
+    /// Integers are variable length to keep things small, and trailing bytes mean the packet
+    /// wasn't what we expected.
+    fn options() -> impl bincode::Options {
+        bincode::DefaultOptions::new()
+            .with_varint_encoding()
+            .reject_trailing_bytes()
+    }
+}
+
+impl Codec for BinaryCodec {
+    fn encode<T: Serialize>(&self, val: &T) -> Result<Vec<u8>, CodecError> {
+        BinaryCodec::options()
+            .serialize(val)
+            .map_err(|_| CodecError)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        BinaryCodec::options()
+            .deserialize(bytes)
+            .map_err(|_| CodecError)
+    }
+}
+
+/// Identifies a codec, so two ends of a connection can agree on which one to use.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum CodecKind {
+    Json,
+    Binary,
+}
+
+impl CodecKind {
+    /// Every codec we support, from most to least preferred.
+    pub const SUPPORTED: [CodecKind; 2] = [CodecKind::Binary, CodecKind::Json];
+
+    /// Pick the first codec in `offered` that we support.
+    pub fn choose(offered: &[CodecKind]) -> Option<CodecKind> {
+        offered
+            .iter()
+            .find(|codec| CodecKind::SUPPORTED.contains(codec))
+            .copied()
+    }
+}
+
+impl Codec for CodecKind {
+    fn encode<T: Serialize>(&self, val: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            CodecKind::Json => JsonCodec.encode(val),
+            CodecKind::Binary => BinaryCodec.encode(val),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        match self {
+            CodecKind::Json => JsonCodec.decode(bytes),
+            CodecKind::Binary => BinaryCodec.decode(bytes),
+        }
+    }
+}
+
+/// Error thrown when a value couldn't be encoded or decoded.
+#[derive(Debug)]
+pub struct CodecError;
+
+/// Testing for codecs.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::game::game_state::*;
+    use crate::multiplayer::packets::*;
+
+    /// Encode and decode a value with a codec, returning the decoded value.
+    fn round_trip<T: Serialize + DeserializeOwned>(codec: CodecKind, val: &T) -> T {
+        let bytes = codec.encode(val).expect("Encoding failed!");
+        codec.decode(&bytes).expect("Decoding failed!")
+    }
+
+    /// Every packet survives a round trip through every codec.
+    #[test]
+    fn packet_round_trips() {
+        for codec in CodecKind::SUPPORTED {
+            assert!(round_trip(codec, &PlayerType::Killer) == PlayerType::Killer);
+            assert!(round_trip(codec, &ConnectionType::Spectator) == ConnectionType::Spectator);
+            assert!(round_trip(codec, &ConnectionResponse::Lobby) == ConnectionResponse::Lobby);
+            assert!(
+                round_trip(codec, &RematchVote::RematchSwapRoles) == RematchVote::RematchSwapRoles
+            );
+            assert_eq!(round_trip::<TurnTimerPacket>(codec, &30), 30);
+
+            let mut state_packet = GameStatePacket::new();
+            state_packet.hidden_parts = vec![(0, 1), (1, 2), (4, 4)];
+            assert_eq!(
+                round_trip(codec, &state_packet).hidden_parts,
+                state_packet.hidden_parts
+            );
+
+            match round_trip(codec, &RoundMessage::Move(MovePacket(2, 3))) {
+                RoundMessage::Move(MovePacket(2, 3)) => {}
+                _ => panic!("Move didn't survive a round trip!"),
+            }
+
+            match round_trip(
+                codec,
+                &RoundMessage::Chat(String::from("I can hear you...")),
+            ) {
+                RoundMessage::Chat(msg) => assert_eq!(msg, "I can hear you..."),
+                _ => panic!("Chat didn't survive a round trip!"),
+            }
+
+            let round = SpectatorPacket::Round {
+                round: 7,
+                victim_move: MovePacket(1, 2),
+                killer_move: MovePacket(1, 4),
+                result: PlayResult::new(RoundResult::ChaseBegins(1), Some(3)),
+            };
+            match round_trip(codec, &round) {
+                SpectatorPacket::Round {
+                    round: 7,
+                    victim_move: MovePacket(1, 2),
+                    killer_move: MovePacket(1, 4),
+                    result,
+                } => {
+                    assert_eq!(result.result, RoundResult::ChaseBegins(1));
+                    assert_eq!(result.part_section_index, Some(3));
+                }
+                _ => panic!("Spectator round didn't survive a round trip!"),
+            }
+
+            let request = LobbyRequest::CreateRoom {
+                name: String::from("Cabin crew"),
+                role: None,
+                turn_time: 0,
+            };
+            match round_trip(codec, &request) {
+                LobbyRequest::CreateRoom {
+                    name,
+                    role: None,
+                    turn_time: 0,
+                } => assert_eq!(name, "Cabin crew"),
+                _ => panic!("Lobby request didn't survive a round trip!"),
+            }
+        }
+    }
+
+    /// The binary codec is more compact than JSON.
+    #[test]
+    fn binary_is_compact() {
+        let message = RoundMessage::Move(MovePacket(3, 4));
+        let json = CodecKind::Json.encode(&message).unwrap();
+        let binary = CodecKind::Binary.encode(&message).unwrap();
+        assert!(binary.len() < json.len());
+    }
+
+    /// Garbage and truncated packets are rejected by every codec.
+    #[test]
+    fn rejects_bad_packets() {
+        for codec in CodecKind::SUPPORTED {
+            let bytes = codec
+                .encode(&RoundMessage::Chat(String::from("boo")))
+                .unwrap();
+            assert!(codec
+                .decode::<RoundMessage>(&bytes[..bytes.len() - 1])
+                .is_err());
+            assert!(codec.decode::<PlayerType>(&[0xFF; 3]).is_err());
+        }
+
+        // Extra bytes on the end aren't allowed either
+        let mut bytes = CodecKind::Binary.encode(&PlayerType::Victim).unwrap();
+        bytes.push(0);
+        assert!(CodecKind::Binary.decode::<PlayerType>(&bytes).is_err());
+    }
+
+    /// Codecs are chosen by the offerers preference.
+    #[test]
+    fn choose_codec() {
+        assert_eq!(
+            CodecKind::choose(&[CodecKind::Json, CodecKind::Binary]),
+            Some(CodecKind::Json)
+        );
+        assert_eq!(
+            CodecKind::choose(&CodecKind::SUPPORTED),
+            Some(CodecKind::Binary)
+        );
+        assert_eq!(CodecKind::choose(&[]), None);
+    }
+}
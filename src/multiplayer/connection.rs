@@ -0,0 +1,365 @@
+use byteorder::{ByteOrder, LittleEndian};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use super::codec::*;
+use super::security::*;
+use super::transport::*;
+
+/// Largest frame a connection accepts by default, in bytes.
+///
+/// Every packet in the game is tiny, so anything bigger than this is a broken or malicious peer.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// How long a new connection has to finish the handshake before we give up on it.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of bytes used to hold the size of a frame.
+const FRAME_HEADER_SIZE: usize = 4;
+
+/// A connection to another game that sends and receives packets.
+///
+/// Packets are sent as frames containing the packet encoded with the codec both ends agreed on
+/// when connecting. Over plain TCP a frame is a 4 byte little endian size followed by the packet,
+/// and over a WebSocket each frame is a message.
+pub struct Connection {
+    /// Transport frames are sent over.
+    transport: Box<dyn Transport>,
+
+    /// Codec used to encode packets.
+    codec: CodecKind,
+
+    /// Largest frame we accept, in bytes.
+    max_frame_size: usize,
+
+    /// Cipher every frame is encrypted with, if the game is private.
+    cipher: Option<SessionCipher>,
+}
+
+impl Connection {
+    /// Connect to `addr`, agreeing on a codec with the other end.
+    ///
+    /// Private games need the right `password`, and fail with an `AuthError` otherwise.
+    pub fn connect(addr: &SocketAddr, password: Option<&str>) -> std::io::Result<Connection> {
+        let mut conn = Connection::new(Box::new(TcpTransport(TcpStream::connect(addr)?)));
+        conn.stream().set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+        // Offer every codec we support, and the other end picks one
+        conn.send(&CodecKind::SUPPORTED)?;
+        let codec = conn.recv::<Option<CodecKind>>()?;
+        conn.codec = match codec {
+            Some(codec) if CodecKind::SUPPORTED.contains(&codec) => codec,
+            _ => return Err(invalid_data("no codec in common")),
+        };
+
+        // Private games make us prove we know the password
+        if let Some(challenge) = conn.recv::<Option<AuthChallenge>>()? {
+            let password = password.ok_or_else(|| AuthError::PasswordRequired.into_io_error())?;
+            let key = RoomKey::with_salt(password, challenge.salt);
+            let proof = key.prove(&challenge);
+            conn.send(&proof)?;
+
+            match conn.recv::<AuthResult>()? {
+                AuthResult::Accepted(mac) if key.check_host_proof(&challenge, &proof, &mac) => {
+                    conn.cipher = Some(key.session(&challenge, &proof, false));
+                }
+                AuthResult::Accepted(_) => {
+                    return Err(invalid_data("host doesn't know the password"))
+                }
+                AuthResult::AccessDenied => return Err(AuthError::AccessDenied.into_io_error()),
+            }
+        }
+
+        conn.stream().set_read_timeout(None)?;
+        Ok(conn)
+    }
+
+    /// Accept a connection on a freshly accepted `stream`, agreeing on a codec with the other end.
+    ///
+    /// The other end can be another game speaking plain TCP, or a browser using a WebSocket.
+    ///
+    /// Private games pass their `room_key`, and the other end has to prove it knows the password.
+    pub fn accept(stream: TcpStream, room_key: Option<&RoomKey>) -> std::io::Result<Connection> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut conn = Connection::new(accept_transport(stream)?);
+
+        // The other end offers codecs and we pick the one they like most
+        let offered = conn.recv::<Vec<CodecKind>>()?;
+        let codec = CodecKind::choose(&offered);
+        conn.send(&codec)?;
+        conn.codec = codec.ok_or_else(|| invalid_data("no codec in common"))?;
+
+        // Private games make the other end prove it knows the password
+        let challenge = room_key.map(|key| key.challenge());
+        conn.send(&challenge)?;
+        if let (Some(key), Some(challenge)) = (room_key, challenge) {
+            let proof = conn.recv::<AuthProof>()?;
+            match key.check_proof(&challenge, &proof) {
+                Some(mac) => {
+                    conn.send(&AuthResult::Accepted(mac))?;
+                    conn.cipher = Some(key.session(&challenge, &proof, true));
+                }
+                None => {
+                    conn.send(&AuthResult::AccessDenied)?;
+                    return Err(AuthError::AccessDenied.into_io_error());
+                }
+            }
+        }
+
+        conn.stream().set_read_timeout(None)?;
+        Ok(conn)
+    }
+
+    /// Create a connection that uses JSON, which is what the handshake is done in.
+    fn new(transport: Box<dyn Transport>) -> Connection {
+        Connection {
+            transport,
+            codec: CodecKind::Json,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            cipher: None,
+        }
+    }
+
+    /// Codec used to encode packets.
+    pub fn codec(&self) -> CodecKind {
+        self.codec
+    }
+
+    /// Largest frame we accept, in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Set the largest frame we accept, in bytes.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    /// TCP stream to the other end.
+    pub fn stream(&self) -> &TcpStream {
+        self.transport.stream()
+    }
+
+    /// Check if there is a packet waiting to be received without blocking.
+    ///
+    /// A closed connection counts as having data, so the next receive reports the error.
+    pub fn has_data(&mut self) -> std::io::Result<bool> {
+        self.transport.has_data()
+    }
+
+    /// Send a packet.
+    pub fn send<T: Serialize>(&mut self, val: &T) -> std::io::Result<()> {
+        let mut payload = self
+            .codec
+            .encode(val)
+            .map_err(|_| invalid_data("unable to encode packet"))?;
+
+        if let Some(cipher) = &mut self.cipher {
+            payload = cipher
+                .encrypt(&payload)
+                .ok_or_else(|| invalid_data("unable to encrypt packet"))?;
+        }
+
+        self.transport.send_frame(&payload, self.max_frame_size)
+    }
+
+    /// Receive a packet.
+    pub fn recv<T: DeserializeOwned>(&mut self) -> std::io::Result<T> {
+        let mut payload = self.transport.recv_frame(self.max_frame_size)?;
+
+        if let Some(cipher) = &mut self.cipher {
+            payload = cipher
+                .decrypt(&payload)
+                .ok_or_else(|| invalid_data("unable to decrypt packet"))?;
+        }
+
+        self.codec
+            .decode(&payload)
+            .map_err(|_| invalid_data("unable to decode packet"))
+    }
+}
+
+/// Write a frame containing `payload` to `writer`.
+///
+/// Payloads larger than `max_frame_size` are refused, since the other end would reject them.
+pub fn write_frame<W: Write>(
+    writer: &mut W,
+    payload: &[u8],
+    max_frame_size: usize,
+) -> std::io::Result<()> {
+    if payload.len() > max_frame_size || payload.len() > u32::MAX as usize {
+        return Err(invalid_data("frame is too large"));
+    }
+
+    // Create a buffer to hold size of data and the data itself
+    let mut data = vec![0; FRAME_HEADER_SIZE];
+    data.reserve(payload.len());
+
+    // Write size of serialized data to a buffer
+    LittleEndian::write_u32(&mut data, payload.len() as u32);
+
+    // Write serialized data to buffer
+    data.extend_from_slice(payload);
+
+    writer.write_all(&data)
+}
+
+/// Read a frame from `reader`, returning its payload.
+///
+/// Frames larger than `max_frame_size` are rejected before anything is allocated for them, and
+/// frames cut short by the other end are rejected too.
+pub fn read_frame<R: Read>(reader: &mut R, max_frame_size: usize) -> std::io::Result<Vec<u8>> {
+    // Read size of structure
+    let mut header = [0u8; FRAME_HEADER_SIZE];
+    reader.read_exact(&mut header)?;
+
+    // Don't trust the size until we know it's reasonable
+    let frame_size = LittleEndian::read_u32(&header) as usize;
+    if frame_size > max_frame_size {
+        return Err(invalid_data("frame is too large"));
+    }
+
+    // Read serialized data
+    let mut payload = vec![0; frame_size];
+    reader.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+/// Create an error for data that doesn't make sense.
+pub fn invalid_data(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// Testing for connections.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiplayer::packets::*;
+    use std::io::Cursor;
+
+    /// Frames survive being written and read back.
+    #[test]
+    fn frame_round_trip() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"camp misty", DEFAULT_MAX_FRAME_SIZE).unwrap();
+        write_frame(&mut buf, b"", DEFAULT_MAX_FRAME_SIZE).unwrap();
+
+        let mut reader = Cursor::new(buf);
+        assert_eq!(
+            read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE).unwrap(),
+            b"camp misty"
+        );
+        assert!(read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE)
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Frames claiming to be huge are rejected without reading them.
+    #[test]
+    fn rejects_oversized_frames() {
+        // A 4 GiB frame shouldn't make us allocate 4 GiB
+        let mut reader = Cursor::new(vec![0xFF, 0xFF, 0xFF, 0xFF, 1, 2, 3]);
+        let err = read_frame(&mut reader, DEFAULT_MAX_FRAME_SIZE).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        // The limit is configurable
+        let mut buf = Vec::new();
+        write_frame(&mut buf, &[7; 16], DEFAULT_MAX_FRAME_SIZE).unwrap();
+        assert!(read_frame(&mut Cursor::new(&buf), 15).is_err());
+        assert!(read_frame(&mut Cursor::new(&buf), 16).is_ok());
+
+        // We won't send frames the other end would reject
+        assert!(write_frame(&mut Vec::new(), &[7; 16], 15).is_err());
+    }
+
+    /// Frames that end early are rejected.
+    #[test]
+    fn rejects_truncated_frames() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, b"camp misty", DEFAULT_MAX_FRAME_SIZE).unwrap();
+
+        // Cut off in the payload
+        let err = read_frame(
+            &mut Cursor::new(&buf[..buf.len() - 1]),
+            DEFAULT_MAX_FRAME_SIZE,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+        // Cut off in the header
+        let err = read_frame(&mut Cursor::new(&buf[..2]), DEFAULT_MAX_FRAME_SIZE).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+
+    /// Both ends of a connection agree on a codec and can talk to each other.
+    #[test]
+    fn handshake() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let host = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut conn = Connection::accept(stream, None).unwrap();
+            let player_type = conn.recv::<PlayerType>().unwrap();
+            conn.send(&player_type.opposite()).unwrap();
+            conn.codec()
+        });
+
+        let mut conn = Connection::connect(&addr, None).unwrap();
+        conn.send(&PlayerType::Killer).unwrap();
+        assert!(conn.recv::<PlayerType>().unwrap() == PlayerType::Victim);
+
+        assert_eq!(conn.codec(), CodecKind::Binary);
+        assert_eq!(host.join().unwrap(), CodecKind::Binary);
+    }
+
+    /// Private games let in whoever knows the password, and turn everyone else away.
+    #[test]
+    fn private_handshake() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let host = std::thread::spawn(move || {
+            let key = RoomKey::new("crystal lake");
+            let mut results = Vec::new();
+            for _ in 0..3 {
+                let (stream, _) = listener.accept().unwrap();
+                results.push(match Connection::accept(stream, Some(&key)) {
+                    Ok(mut conn) => {
+                        let player_type = conn.recv::<PlayerType>().unwrap();
+                        conn.send(&player_type.opposite()).unwrap();
+                        None
+                    }
+                    Err(e) => AuthError::from_io_error(&e),
+                });
+            }
+            results
+        });
+
+        let err = Connection::connect(&addr, None).err().unwrap();
+        assert_eq!(
+            AuthError::from_io_error(&err),
+            Some(AuthError::PasswordRequired)
+        );
+
+        let err = Connection::connect(&addr, Some("camp misty"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            AuthError::from_io_error(&err),
+            Some(AuthError::AccessDenied)
+        );
+
+        let mut conn = Connection::connect(&addr, Some("crystal lake")).unwrap();
+        conn.send(&PlayerType::Killer).unwrap();
+        assert!(conn.recv::<PlayerType>().unwrap() == PlayerType::Victim);
+
+        let results = host.join().unwrap();
+        assert_eq!(results[1], Some(AuthError::AccessDenied));
+        assert_eq!(results[2], None);
+    }
+}
@@ -0,0 +1,225 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::packets::*;
+use crate::util::*;
+
+/// UDP port hosts announce themselves on.
+pub const DISCOVERY_PORT: u16 = 7778;
+
+/// How long to listen for announcements when browsing for local games.
+pub const BROWSE_TIME: Duration = Duration::from_secs(2);
+
+/// Most characters of a host's name we show, since anyone on the network can send one.
+pub const MAX_HOST_NAME_LENGTH: usize = 40;
+
+/// How often a waiting host announces itself.
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long the announcer sleeps between checks to see if it should stop.
+const ANNOUNCE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Bytes every announcement starts with, so we can ignore anything else on the port.
+const ANNOUNCEMENT_MAGIC: &[u8] = b"CAMP-MISTY";
+
+/// A game found on the local network.
+#[derive(Clone)]
+pub struct LocalGame {
+    /// Address to connect to.
+    pub addr: SocketAddr,
+
+    /// What the host said about itself.
+    pub announcement: HostAnnouncement,
+}
+
+/// Announces a waiting host to the local network in the background.
+///
+/// The announcer stops when it is dropped.
+pub struct Announcer {
+    /// Flag telling the announcer thread to keep running.
+    running: Arc<AtomicBool>,
+
+    /// Announcer thread.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Announcer {
+    /// Start announcing `announcement` over UDP broadcast.
+    pub fn start(announcement: HostAnnouncement) -> std::io::Result<Announcer> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        socket.set_broadcast(true)?;
+        let packet = encode_announcement(&announcement)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+
+        let thread = std::thread::spawn(move || {
+            let mut next_announcement = Instant::now();
+            while thread_running.load(Ordering::Relaxed) {
+                if Instant::now() >= next_announcement {
+                    // Without a network to broadcast on, at least games on this machine can find us
+                    if socket
+                        .send_to(&packet, (Ipv4Addr::BROADCAST, DISCOVERY_PORT))
+                        .is_err()
+                    {
+                        let _ = socket.send_to(&packet, (Ipv4Addr::LOCALHOST, DISCOVERY_PORT));
+                    }
+                    next_announcement = Instant::now() + ANNOUNCE_INTERVAL;
+                }
+
+                std::thread::sleep(ANNOUNCE_POLL_INTERVAL);
+            }
+        });
+
+        Ok(Announcer {
+            running,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Announcer {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Listen for hosts announcing themselves for `duration`, returning every game found.
+pub fn find_local_games(duration: Duration) -> std::io::Result<Vec<LocalGame>> {
+    let socket = bind_discovery_socket()?;
+    let end = Instant::now() + duration;
+    let mut games = Vec::<LocalGame>::new();
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let now = Instant::now();
+        if now >= end {
+            break;
+        }
+
+        socket.set_read_timeout(Some(end - now))?;
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(ref e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                break
+            }
+            Err(e) => return Err(e),
+        };
+
+        // Hosts announce themselves over and over, so only keep the first announcement
+        if let Some(announcement) = decode_announcement(&buf[..len]) {
+            let addr = SocketAddr::new(from.ip(), announcement.port);
+            if games.iter().all(|game| game.addr != addr) {
+                games.push(LocalGame { addr, announcement });
+            }
+        }
+    }
+
+    Ok(games)
+}
+
+/// Start listening on the discovery port.
+///
+/// Address reuse is turned on first, so more than one game on this computer can browse at once.
+fn bind_discovery_socket() -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT)).into())?;
+    Ok(socket.into())
+}
+
+/// Get the name of this computer, to tell other players who is hosting.
+pub fn host_name() -> String {
+    let name = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .unwrap_or_default();
+
+    let name = name.trim();
+    if name.is_empty() {
+        String::from("Unknown host")
+    } else {
+        String::from(name)
+    }
+}
+
+/// Turn an announcement into the bytes sent over the network.
+fn encode_announcement(announcement: &HostAnnouncement) -> std::io::Result<Vec<u8>> {
+    let mut packet = Vec::from(ANNOUNCEMENT_MAGIC);
+    packet.extend(serde_json::to_vec(announcement)?);
+    Ok(packet)
+}
+
+/// Read an announcement sent over the network, returning `None` if it isn't one.
+///
+/// The host's name is cleaned up here, since it gets printed for the user to pick from.
+fn decode_announcement(packet: &[u8]) -> Option<HostAnnouncement> {
+    let json = packet.strip_prefix(ANNOUNCEMENT_MAGIC)?;
+    let mut announcement: HostAnnouncement = serde_json::from_slice(json).ok()?;
+    announcement.host_name = printable(&announcement.host_name, MAX_HOST_NAME_LENGTH);
+    Some(announcement)
+}
+
+/// Testing for discovery.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Announcements survive being sent, and anything else on the port is ignored.
+    #[test]
+    fn announcements() {
+        let announcement = HostAnnouncement {
+            host_name: String::from("cabin"),
+            port: 7777,
+            host_type: Some(PlayerType::Killer),
+            private: true,
+        };
+
+        let packet = encode_announcement(&announcement).unwrap();
+        let decoded = decode_announcement(&packet).unwrap();
+        assert_eq!(decoded.host_name, "cabin");
+        assert_eq!(decoded.port, 7777);
+        assert!(decoded.host_type == Some(PlayerType::Killer));
+        assert!(decoded.private);
+
+        assert!(decode_announcement(&packet[ANNOUNCEMENT_MAGIC.len()..]).is_none());
+        assert!(decode_announcement(&packet[..packet.len() - 1]).is_none());
+        assert!(decode_announcement(b"CAMP-MISTY").is_none());
+    }
+
+    /// Names from other hosts can't send escape sequences or fill the screen.
+    #[test]
+    fn cleaning_up_host_names() {
+        let announcement = HostAnnouncement {
+            host_name: format!("\x1b[2Jcabin\n{}", "x".repeat(100)),
+            port: 7777,
+            host_type: None,
+            private: false,
+        };
+
+        let packet = encode_announcement(&announcement).unwrap();
+        let decoded = decode_announcement(&packet).unwrap();
+        assert!(decoded.host_name.starts_with("[2Jcabinx"));
+        assert!(!decoded.host_name.chars().any(char::is_control));
+        assert_eq!(decoded.host_name.chars().count(), MAX_HOST_NAME_LENGTH);
+    }
+
+    /// Two games on the same computer can browse for hosts at the same time.
+    #[test]
+    fn browsing_twice() {
+        let _first = bind_discovery_socket().unwrap();
+        let _second = bind_discovery_socket().unwrap();
+    }
+}
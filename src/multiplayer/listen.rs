@@ -0,0 +1,174 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+
+/// Number of connections that can wait to be accepted.
+const LISTEN_BACKLOG: i32 = 128;
+
+/// Address to listen on when only a `port` is given.
+///
+/// This listens on every interface, for both IPv4 and IPv6 where the system supports it.
+pub fn default_bind_addr(port: u16) -> SocketAddr {
+    SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port)
+}
+
+/// Figure out what address the user wants to listen on.
+///
+/// `input` can be a port, an IP address with or without a port (IPv6 addresses with a port go in
+/// brackets, like "[::]:7777"), or the name of a network interface. `default_port` is used when
+/// no port is given.
+pub fn parse_bind_addr(input: &str, default_port: u16) -> Option<SocketAddr> {
+    let input = input.trim();
+
+    if let Ok(port) = input.parse::<u16>() {
+        return Some(default_bind_addr(port));
+    }
+
+    if let Ok(addr) = input.parse::<SocketAddr>() {
+        return Some(addr);
+    }
+
+    // IPv6 addresses can be given in brackets even without a port
+    let ip = input
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(input);
+    if let Ok(ip) = ip.parse::<IpAddr>() {
+        return Some(SocketAddr::new(ip, default_port));
+    }
+
+    // Anything else might be a network interface, optionally with a port
+    let (name, port) = match input.rsplit_once(':') {
+        Some((name, port)) => (name, port.parse::<u16>().ok()?),
+        None => (input, default_port),
+    };
+    interface_ip(name).map(|ip| SocketAddr::new(ip, port))
+}
+
+/// Start listening on `addr`.
+///
+/// Listening on the unspecified IPv6 address accepts IPv4 connections too. If the system doesn't
+/// support IPv6 at all, we fall back to listening on every IPv4 interface instead.
+pub fn bind_listener(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    match bind_socket(addr) {
+        Err(_) if addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED) => bind_socket(SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            addr.port(),
+        )),
+        res => res,
+    }
+}
+
+/// Get every address other players can use to reach `listener`.
+pub fn reachable_addrs(listener: &TcpListener) -> Vec<SocketAddr> {
+    let local = match listener.local_addr() {
+        Ok(local) => local,
+        Err(_) => return Vec::new(),
+    };
+
+    if !local.ip().is_unspecified() {
+        return vec![local];
+    }
+
+    // Dual-stack listeners can be reached over both IPv4 and IPv6
+    let dual_stack =
+        local.is_ipv6() && socket2::SockRef::from(listener).only_v6().ok() == Some(false);
+    let mut addrs: Vec<SocketAddr> = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .iter()
+        .filter(|iface| !iface.is_link_local())
+        .map(|iface| iface.ip())
+        .filter(|ip| ip.is_ipv6() == local.is_ipv6() || dual_stack)
+        .map(|ip| SocketAddr::new(ip, local.port()))
+        .collect();
+
+    // Put the addresses people are most likely to use first
+    addrs.sort_by_key(|addr| (addr.ip().is_loopback(), addr.is_ipv6(), *addr));
+    addrs.dedup();
+    addrs
+}
+
+/// Create a socket listening on `addr`.
+fn bind_socket(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+
+    // Some systems only take IPv6 connections on IPv6 sockets unless told otherwise
+    if addr.ip() == IpAddr::V6(Ipv6Addr::UNSPECIFIED) {
+        socket.set_only_v6(false)?;
+    }
+
+    // Like the standard library, only reuse addresses where that can't steal another sockets port
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    Ok(socket.into())
+}
+
+/// Get the IP address of the network interface called `name`, preferring IPv4.
+fn interface_ip(name: &str) -> Option<IpAddr> {
+    let mut ips: Vec<IpAddr> = if_addrs::get_if_addrs()
+        .ok()?
+        .iter()
+        .filter(|iface| iface.name == name && !iface.is_link_local())
+        .map(|iface| iface.ip())
+        .collect();
+
+    ips.sort_by_key(|ip| ip.is_ipv6());
+    ips.first().copied()
+}
+
+/// Testing for listening.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpStream;
+
+    /// Ports, addresses, and interfaces are all understood.
+    #[test]
+    fn bind_addresses() {
+        assert_eq!(parse_bind_addr("7777", 1), Some(default_bind_addr(7777)));
+        assert_eq!(
+            parse_bind_addr("127.0.0.1:8000", 1),
+            Some("127.0.0.1:8000".parse().unwrap())
+        );
+        assert_eq!(
+            parse_bind_addr("127.0.0.1", 7777),
+            Some("127.0.0.1:7777".parse().unwrap())
+        );
+        assert_eq!(
+            parse_bind_addr("[::]", 7777),
+            Some("[::]:7777".parse().unwrap())
+        );
+        assert_eq!(
+            parse_bind_addr("::1", 7777),
+            Some("[::1]:7777".parse().unwrap())
+        );
+        assert_eq!(parse_bind_addr("not a real interface", 7777), None);
+        assert_eq!(parse_bind_addr("127.0.0.1:99999", 7777), None);
+
+        // Every machine has a loopback interface, but its name depends on the system
+        let loopback = if_addrs::get_if_addrs()
+            .unwrap()
+            .into_iter()
+            .find(|iface| iface.ip() == IpAddr::V4(Ipv4Addr::LOCALHOST));
+        if let Some(loopback) = loopback {
+            assert_eq!(
+                parse_bind_addr(&format!("{}:8000", loopback.name), 7777),
+                Some("127.0.0.1:8000".parse().unwrap())
+            );
+        }
+    }
+
+    /// Listening everywhere lets IPv4 clients in.
+    #[test]
+    fn dual_stack() {
+        let listener = bind_listener(default_bind_addr(0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        listener.accept().unwrap();
+
+        assert!(reachable_addrs(&listener)
+            .iter()
+            .any(|addr| addr.ip() == IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    }
+}
@@ -0,0 +1,730 @@
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::connection::*;
+use super::listen::*;
+use super::packets::*;
+use super::recording::*;
+use super::spectator::*;
+use super::turn_timer::*;
+use crate::game::game_state::*;
+
+/// How often the lobby checks for rooms whose creator has left.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long the referee sleeps between checks for messages from players.
+const RELAY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long the referee waits for a player who owes it a message before assuming they left.
+const PLAYER_READ_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Number of letters in a room code.
+const ROOM_CODE_LENGTH: usize = 4;
+
+/// A headless server that lets many clients create and join rooms, and referees every match.
+pub struct LobbyServer {
+    /// TCP server listener.
+    listener: TcpListener,
+
+    /// Every room on the server, keyed by room code.
+    rooms: Rooms,
+}
+
+/// Shared handle to the rooms on a lobby server.
+type Rooms = Arc<Mutex<HashMap<String, Room>>>;
+
+/// Something that happened on a lobby server, for whoever is running it to see.
+///
+/// Each event holds the name and code of the room it happened in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LobbyEvent {
+    /// A room was created.
+    RoomCreated(String, String),
+
+    /// Someone joined a room, so its match is starting.
+    MatchStarting(String, String),
+
+    /// The creator of a room left before anyone joined.
+    RoomAbandoned(String, String),
+
+    /// The match in a room is over.
+    RoomClosed(String, String),
+}
+
+impl fmt::Display for LobbyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LobbyEvent::RoomCreated(name, code) => {
+                write!(f, "Room \"{}\" ({}) was created.", name, code)
+            }
+            LobbyEvent::MatchStarting(name, code) => {
+                write!(f, "Room \"{}\" ({}) is starting a match.", name, code)
+            }
+            LobbyEvent::RoomAbandoned(name, code) => {
+                write!(f, "Room \"{}\" ({}) was abandoned.", name, code)
+            }
+            LobbyEvent::RoomClosed(name, code) => {
+                write!(f, "Room \"{}\" ({}) was closed.", name, code)
+            }
+        }
+    }
+}
+
+/// Shared handle to whatever is told about events on a lobby server.
+type EventLog = Arc<dyn Fn(LobbyEvent) + Send + Sync>;
+
+/// A room on a lobby server.
+struct Room {
+    /// Name of the room.
+    name: String,
+
+    /// How the creator set up the room.
+    settings: RoomSettings,
+
+    /// What's happening in the room.
+    status: RoomStatus,
+}
+
+/// How the creator of a room set it up.
+#[derive(Clone, Copy)]
+struct RoomSettings {
+    /// Player type the creator wants to be, or `None` to choose randomly.
+    creator_role: Option<PlayerType>,
+
+    /// Seconds each player gets per turn.
+    turn_time: TurnTimerPacket,
+}
+
+/// What's happening in a room.
+enum RoomStatus {
+    /// The creator is being told the room code.
+    Creating,
+
+    /// The creator is waiting for an opponent on the given stream.
+    Waiting(Connection),
+
+    /// A match is being played.
+    Playing,
+}
+
+impl LobbyServer {
+    /// Constructor.
+    pub fn new(port: u16) -> Result<LobbyServer, LobbyServerError> {
+        match bind_listener(default_bind_addr(port)) {
+            Ok(listener) => Ok(LobbyServer {
+                listener,
+                rooms: Arc::new(Mutex::new(HashMap::new())),
+            }),
+
+            Err(_) => Err(LobbyServerError),
+        }
+    }
+
+    /// Accept clients forever, giving each one its own thread.
+    ///
+    /// Everything that happens to a room is passed to `log`.
+    pub fn run(&self, log: impl Fn(LobbyEvent) + Send + Sync + 'static) {
+        let log: EventLog = Arc::new(log);
+
+        // Clean up abandoned rooms in the background
+        let rooms = self.rooms.clone();
+        let cleanup_log = log.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(CLEANUP_INTERVAL);
+            remove_abandoned_rooms(&rooms, &cleanup_log);
+        });
+
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+
+            let rooms = self.rooms.clone();
+            let log = log.clone();
+            std::thread::spawn(move || greet_client(stream, &rooms, &log));
+        }
+    }
+}
+
+/// Welcome a client who just connected to the lobby, then serve them.
+fn greet_client(stream: TcpStream, rooms: &Rooms, log: &EventLog) {
+    // Everyone who connects to a lobby is told to pick a room
+    let mut conn = match read_connection_type(stream, None) {
+        Some((conn, _)) => conn,
+        None => return,
+    };
+    if try_write_over_tcp(&mut conn, &ConnectionResponse::Lobby).is_err() {
+        return;
+    }
+
+    serve_client(conn, rooms, log);
+}
+
+/// Answer a clients lobby requests until they create or join a room, or leave.
+fn serve_client(mut stream: Connection, rooms: &Rooms, log: &EventLog) {
+    loop {
+        let request = match try_read_over_tcp::<LobbyRequest>(&mut stream) {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+
+        match request {
+            LobbyRequest::ListRooms => {
+                let list = list_rooms(rooms);
+                if try_write_over_tcp(&mut stream, &LobbyResponse::Rooms(list)).is_err() {
+                    return;
+                }
+            }
+
+            LobbyRequest::CreateRoom {
+                name,
+                role,
+                turn_time,
+            } => {
+                let settings = RoomSettings {
+                    creator_role: role,
+                    turn_time,
+                };
+                let code = match reserve_room(rooms, &name, settings) {
+                    Some(code) => code,
+                    None => {
+                        let response = LobbyResponse::Error(LobbyError::NameTaken);
+                        if try_write_over_tcp(&mut stream, &response).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                };
+
+                // Tell the creator the code before handing their stream over to the room,
+                // without holding up every other client while we do
+                let response = LobbyResponse::RoomCreated(code.clone());
+                if try_write_over_tcp(&mut stream, &response).is_err() {
+                    rooms.lock().unwrap().remove(&code);
+                    return;
+                }
+
+                log(LobbyEvent::RoomCreated(name, code.clone()));
+                if let Some(room) = rooms.lock().unwrap().get_mut(&code) {
+                    room.status = RoomStatus::Waiting(stream);
+                }
+                return;
+            }
+
+            LobbyRequest::JoinRoom(name_or_code) => match take_room(rooms, &name_or_code, log) {
+                Some((name, code, mut creator, settings)) => {
+                    // Make sure the creator is still around before starting
+                    if is_disconnected(&mut creator)
+                        || try_write_over_tcp(&mut creator, &LobbyResponse::MatchStarting).is_err()
+                    {
+                        rooms.lock().unwrap().remove(&code);
+                        log(LobbyEvent::RoomAbandoned(name, code));
+
+                        let response = LobbyResponse::Error(LobbyError::RoomNotFound);
+                        if try_write_over_tcp(&mut stream, &response).is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+
+                    // This thread becomes the referee for the match
+                    referee_match(creator, stream, settings);
+                    rooms.lock().unwrap().remove(&code);
+                    log(LobbyEvent::RoomClosed(name, code));
+                    return;
+                }
+
+                None => {
+                    let response = LobbyResponse::Error(LobbyError::RoomNotFound);
+                    if try_write_over_tcp(&mut stream, &response).is_err() {
+                        return;
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// List every room waiting for an opponent.
+fn list_rooms(rooms: &Rooms) -> Vec<RoomInfo> {
+    let rooms = rooms.lock().unwrap();
+    let mut list: Vec<RoomInfo> = rooms
+        .iter()
+        .filter(|(_, room)| matches!(room.status, RoomStatus::Waiting(_)))
+        .map(|(code, room)| RoomInfo {
+            name: room.name.clone(),
+            code: code.clone(),
+            creator_role: room.settings.creator_role,
+            turn_time: room.settings.turn_time,
+        })
+        .collect();
+
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+/// Reserve a room called `name` while its creator is told the code, returning the code.
+///
+/// Returns `None` if another room already has the name, since rooms can be joined by name.
+fn reserve_room(rooms: &Rooms, name: &str, settings: RoomSettings) -> Option<String> {
+    let mut rooms = rooms.lock().unwrap();
+    if rooms
+        .values()
+        .any(|room| room.name.eq_ignore_ascii_case(name))
+    {
+        return None;
+    }
+
+    let code = new_room_code(&rooms);
+    rooms.insert(
+        code.clone(),
+        Room {
+            name: name.to_string(),
+            settings,
+            status: RoomStatus::Creating,
+        },
+    );
+    Some(code)
+}
+
+/// Take the creators stream out of a waiting room, marking the room as playing.
+///
+/// Returns the room name and code, the creators stream, and the rooms settings.
+fn take_room(
+    rooms: &Rooms,
+    name_or_code: &str,
+    log: &EventLog,
+) -> Option<(String, String, Connection, RoomSettings)> {
+    let mut rooms = rooms.lock().unwrap();
+
+    // Codes are checked before names
+    let code = rooms
+        .keys()
+        .find(|code| code.eq_ignore_ascii_case(name_or_code))
+        .or_else(|| {
+            rooms
+                .iter()
+                .find(|(_, room)| room.name.eq_ignore_ascii_case(name_or_code))
+                .map(|(code, _)| code)
+        })?
+        .clone();
+
+    let room = rooms.get_mut(&code)?;
+    match std::mem::replace(&mut room.status, RoomStatus::Playing) {
+        RoomStatus::Waiting(creator) => {
+            log(LobbyEvent::MatchStarting(room.name.clone(), code.clone()));
+            Some((room.name.clone(), code, creator, room.settings))
+        }
+
+        // Someone else already joined, or the creator hasn't been told the code yet
+        status => {
+            room.status = status;
+            None
+        }
+    }
+}
+
+/// Generate a room code that isn't in use.
+fn new_room_code(rooms: &HashMap<String, Room>) -> String {
+    loop {
+        let code: String = (0..ROOM_CODE_LENGTH)
+            .map(|_| rand::thread_rng().gen_range(b'A', b'Z' + 1) as char)
+            .collect();
+
+        if !rooms.contains_key(&code) {
+            return code;
+        }
+    }
+}
+
+/// Remove every waiting room whose creator has disconnected.
+fn remove_abandoned_rooms(rooms: &Rooms, log: &EventLog) {
+    let mut rooms = rooms.lock().unwrap();
+    rooms.retain(|code, room| {
+        let abandoned = match &mut room.status {
+            RoomStatus::Waiting(stream) => is_disconnected(stream),
+            RoomStatus::Creating | RoomStatus::Playing => false,
+        };
+
+        if abandoned {
+            log(LobbyEvent::RoomAbandoned(room.name.clone(), code.clone()));
+        }
+
+        !abandoned
+    });
+}
+
+/// Check if the other end of an idle connection has gone away.
+fn is_disconnected(conn: &mut Connection) -> bool {
+    // Waiting clients don't send anything, so any readable data or EOF means they're gone
+    conn.has_data().unwrap_or(true)
+}
+
+/// Referee a match between the creator of a room and the player who joined it.
+///
+/// Both players are clients, so each is told their opponent is the "host" and the server
+/// relays moves between them while keeping its own copy of the game state. Games are played
+/// until either player turns down a rematch or leaves.
+fn referee_match(mut creator: Connection, mut joiner: Connection, settings: RoomSettings) {
+    // The creator already knows the match is on
+    if try_write_over_tcp(&mut joiner, &LobbyResponse::MatchStarting).is_err() {
+        return;
+    }
+
+    // Players who stop answering forfeit the match, instead of tying up the referee forever
+    if creator
+        .stream()
+        .set_read_timeout(Some(PLAYER_READ_TIMEOUT))
+        .is_err()
+        || joiner
+            .stream()
+            .set_read_timeout(Some(PLAYER_READ_TIMEOUT))
+            .is_err()
+    {
+        return;
+    }
+
+    // Pick roles
+    let mut creator_role = settings.creator_role.unwrap_or_else(|| {
+        if rand::thread_rng().gen_range(0, 2) == 0 {
+            PlayerType::Killer
+        } else {
+            PlayerType::Victim
+        }
+    });
+
+    // Both players enforce the turn timer, but we keep track of it too
+    if try_write_over_tcp(&mut creator, &settings.turn_time).is_err()
+        || try_write_over_tcp(&mut joiner, &settings.turn_time).is_err()
+    {
+        return;
+    }
+    let mut timer = TurnTimer::new(settings.turn_time);
+
+    loop {
+        timer.reset();
+        if referee_game(&mut creator, &mut joiner, creator_role, &mut timer).is_err() {
+            return;
+        }
+
+        // Lobby matches aren't recorded
+        let recording: RecordingPacket = None;
+        if try_write_over_tcp(&mut creator, &recording).is_err()
+            || try_write_over_tcp(&mut joiner, &recording).is_err()
+        {
+            return;
+        }
+
+        // Relay rematch votes, since both players send theirs before reading
+        let creator_vote = match try_read_over_tcp::<RematchVote>(&mut creator) {
+            Ok(vote) => vote,
+            Err(_) => RematchVote::Quit,
+        };
+        let joiner_vote = match try_read_over_tcp::<RematchVote>(&mut joiner) {
+            Ok(vote) => vote,
+            Err(_) => RematchVote::Quit,
+        };
+        let _ = try_write_over_tcp(&mut creator, &joiner_vote);
+        let _ = try_write_over_tcp(&mut joiner, &creator_vote);
+
+        match creator_vote.combine(joiner_vote) {
+            Some(true) => creator_role = creator_role.opposite(),
+            Some(false) => {}
+            None => return,
+        }
+    }
+}
+
+/// Referee a single game of a match.
+///
+/// Returns an error if a player left or sent an invalid move.
+fn referee_game(
+    creator: &mut Connection,
+    joiner: &mut Connection,
+    creator_role: PlayerType,
+    timer: &mut TurnTimer,
+) -> std::io::Result<()> {
+    // Generate the game
+    let mut state = GameState::new();
+    state.gen_state();
+    let state_packet = GameStatePacket::from_state(&state);
+
+    // Clients are told the player type of their opponent
+    try_write_over_tcp(creator, &creator_role.opposite())?;
+    try_write_over_tcp(joiner, &creator_role)?;
+    try_write_over_tcp(creator, &state_packet)?;
+    try_write_over_tcp(joiner, &state_packet)?;
+
+    // Referee rounds until someone wins
+    loop {
+        // Nobody can spectate lobby matches
+        let count: SpectatorCountPacket = 0;
+        try_write_over_tcp(creator, &count)?;
+        try_write_over_tcp(joiner, &count)?;
+
+        // Relay chat until both players have sent their move
+        let leave_deadline = timer.leave_deadline();
+        let mut creator_message = None;
+        let mut joiner_message = None;
+        while creator_message.is_none() || joiner_message.is_none() {
+            // Players who stop responding forfeit the match
+            if leave_deadline.is_some_and(|d| Instant::now() >= d) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "player stopped responding",
+                ));
+            }
+
+            relay_round_message(creator, joiner, &mut creator_message)?;
+            relay_round_message(joiner, creator, &mut joiner_message)?;
+            std::thread::sleep(RELAY_POLL_INTERVAL);
+        }
+        let (creator_message, joiner_message) = (creator_message.unwrap(), joiner_message.unwrap());
+
+        // Keep count of the times players ran out of time
+        let mut read_move = |message: &RoundMessage, role: PlayerType| match message {
+            RoundMessage::Move(m) => Some(*m),
+            RoundMessage::TimedOut(m) => {
+                timer.record_timeout(role);
+                Some(*m)
+            }
+            _ => None,
+        };
+        let creator_move = read_move(&creator_message, creator_role);
+        let joiner_move = read_move(&joiner_message, creator_role.opposite());
+
+        // Cheaters and broken clients end the match
+        let to_tup = |m: MovePacket| (m.0 as usize, m.1 as usize);
+        let (creator_move, joiner_move) = match (creator_move, joiner_move) {
+            (Some(c), Some(j))
+                if state.is_valid_move(to_tup(c)) && state.is_valid_move(to_tup(j)) =>
+            {
+                (c, j)
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "invalid move",
+                ))
+            }
+        };
+
+        // Relay the moves
+        try_write_over_tcp(creator, &joiner_message)?;
+        try_write_over_tcp(joiner, &creator_message)?;
+
+        // Relay the rest of the chat until both players are done with the round
+        let mut creator_done = false;
+        let mut joiner_done = false;
+        while !creator_done || !joiner_done {
+            if !creator_done {
+                creator_done = relay_until_end_of_round(creator, joiner)?;
+            }
+            if !joiner_done {
+                joiner_done = relay_until_end_of_round(joiner, creator)?;
+            }
+        }
+
+        // Running out of time too often forfeits the game
+        if timer.forfeiter().is_some() {
+            return Ok(());
+        }
+
+        // Play the round ourselves to know when the game is over
+        let res = if creator_role == PlayerType::Victim {
+            state.play(to_tup(creator_move), to_tup(joiner_move))
+        } else {
+            state.play(to_tup(joiner_move), to_tup(creator_move))
+        };
+
+        if res.result == RoundResult::Caught || res.result == RoundResult::AllPartsFound {
+            return Ok(());
+        }
+    }
+}
+
+/// Relay a message sent by a player during a round to their opponent, if one is waiting.
+///
+/// The players move is stored in `player_move` instead of being relayed, so it can be checked
+/// first.
+fn relay_round_message(
+    from: &mut Connection,
+    to: &mut Connection,
+    player_move: &mut Option<RoundMessage>,
+) -> std::io::Result<()> {
+    if !has_data(from)? {
+        return Ok(());
+    }
+
+    match try_read_over_tcp::<RoundMessage>(from)? {
+        RoundMessage::Chat(msg) => try_write_over_tcp(to, &RoundMessage::Chat(msg))?,
+        RoundMessage::EndOfRound => {}
+        m => *player_move = Some(m),
+    }
+
+    Ok(())
+}
+
+/// Relay a message sent by a player after both moves were made to their opponent.
+///
+/// Returns 'true' once the player has finished the round.
+fn relay_until_end_of_round(from: &mut Connection, to: &mut Connection) -> std::io::Result<bool> {
+    let message = try_read_over_tcp::<RoundMessage>(from)?;
+    try_write_over_tcp(to, &message)?;
+    Ok(matches!(message, RoundMessage::EndOfRound))
+}
+
+/// Lobby server initialization error.
+#[derive(Debug)]
+pub struct LobbyServerError;
+
+/// Testing for the lobby server.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Connect a client to the lobby at `addr`.
+    fn join_lobby(addr: &std::net::SocketAddr) -> Connection {
+        let mut conn = Connection::connect(addr, None).unwrap();
+        write_over_tcp(&mut conn, &ConnectionType::Player);
+        assert!(
+            try_read_over_tcp::<ConnectionResponse>(&mut conn).unwrap()
+                == ConnectionResponse::Lobby
+        );
+        conn
+    }
+
+    /// Send a lobby request and read the response.
+    fn request(conn: &mut Connection, request: LobbyRequest) -> LobbyResponse {
+        write_over_tcp(conn, &request);
+        try_read_over_tcp::<LobbyResponse>(conn).unwrap()
+    }
+
+    /// Create a room called `name` in `rooms`, returning its code once it can be joined.
+    fn create_room(conn: &mut Connection, rooms: &Rooms, name: &str) -> String {
+        let create = LobbyRequest::CreateRoom {
+            name: name.to_string(),
+            role: Some(PlayerType::Killer),
+            turn_time: 0,
+        };
+        let code = match request(conn, create) {
+            LobbyResponse::RoomCreated(code) => code,
+            _ => panic!("room wasn't created"),
+        };
+
+        // The creator is told the code just before their stream is put in the room
+        while !matches!(
+            rooms.lock().unwrap().get(&code).map(|room| &room.status),
+            Some(RoomStatus::Waiting(_))
+        ) {
+            std::thread::sleep(RELAY_POLL_INTERVAL);
+        }
+        code
+    }
+
+    /// Get the names of every room waiting for an opponent.
+    fn room_names(conn: &mut Connection) -> Vec<String> {
+        match request(conn, LobbyRequest::ListRooms) {
+            LobbyResponse::Rooms(rooms) => rooms.into_iter().map(|room| room.name).collect(),
+            _ => panic!("rooms weren't listed"),
+        }
+    }
+
+    /// Rooms can be listed and joined by name or code, and rooms left by their creator go away.
+    #[test]
+    fn lobby() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let rooms: Rooms = Arc::new(Mutex::new(HashMap::new()));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let server_events = events.clone();
+        let log: EventLog = Arc::new(move |event| server_events.lock().unwrap().push(event));
+        let server_rooms = rooms.clone();
+        let server_log = log.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let rooms = server_rooms.clone();
+                let log = server_log.clone();
+                std::thread::spawn(move || greet_client(stream.unwrap(), &rooms, &log));
+            }
+        });
+
+        // Join by name
+        let mut creator = join_lobby(&addr);
+        let code = create_room(&mut creator, &rooms, "Cabin");
+        let mut joiner = join_lobby(&addr);
+        assert_eq!(room_names(&mut joiner), ["Cabin"]);
+        let taken = LobbyRequest::CreateRoom {
+            name: "CABIN".to_string(),
+            role: None,
+            turn_time: 0,
+        };
+        assert!(matches!(
+            request(&mut joiner, taken),
+            LobbyResponse::Error(LobbyError::NameTaken)
+        ));
+        assert!(matches!(
+            request(&mut joiner, LobbyRequest::JoinRoom("cabin".to_string())),
+            LobbyResponse::MatchStarting
+        ));
+        assert!(matches!(
+            try_read_over_tcp::<LobbyResponse>(&mut creator).unwrap(),
+            LobbyResponse::MatchStarting
+        ));
+        assert_eq!(
+            try_read_over_tcp::<TurnTimerPacket>(&mut creator).unwrap(),
+            0
+        );
+        assert_eq!(
+            try_read_over_tcp::<TurnTimerPacket>(&mut joiner).unwrap(),
+            0
+        );
+        assert!(rooms.lock().unwrap()[&code].name == "Cabin");
+
+        // Join by code, in any case
+        let mut creator = join_lobby(&addr);
+        let code = create_room(&mut creator, &rooms, "Lake");
+        let mut joiner = join_lobby(&addr);
+        assert!(matches!(
+            request(&mut joiner, LobbyRequest::JoinRoom(code.to_lowercase())),
+            LobbyResponse::MatchStarting
+        ));
+        assert!(room_names(&mut join_lobby(&addr)).is_empty());
+
+        // A room whose creator left is removed when someone tries to join it
+        let mut creator = join_lobby(&addr);
+        let code = create_room(&mut creator, &rooms, "Manor");
+        drop(creator);
+        std::thread::sleep(Duration::from_millis(100));
+        let mut joiner = join_lobby(&addr);
+        assert!(matches!(
+            request(&mut joiner, LobbyRequest::JoinRoom("Manor".to_string())),
+            LobbyResponse::Error(LobbyError::RoomNotFound)
+        ));
+        assert!(!rooms.lock().unwrap().contains_key(&code));
+
+        // Or when the lobby cleans up
+        let mut creator = join_lobby(&addr);
+        create_room(&mut creator, &rooms, "Forest");
+        assert_eq!(room_names(&mut joiner), ["Forest"]);
+        drop(creator);
+        std::thread::sleep(Duration::from_millis(100));
+        remove_abandoned_rooms(&rooms, &log);
+        assert!(room_names(&mut joiner).is_empty());
+
+        // Whoever runs the lobby hears about all of it
+        let events = events.lock().unwrap();
+        assert!(events.contains(&LobbyEvent::RoomAbandoned("Manor".to_string(), code)));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, LobbyEvent::RoomAbandoned(name, _) if name == "Forest")));
+        assert!(events
+            .iter()
+            .any(|event| matches!(event, LobbyEvent::MatchStarting(name, _) if name == "Cabin")));
+    }
+}
@@ -1,4 +1,16 @@
+pub mod bot_server;
+pub mod chat;
 pub mod client;
+pub mod codec;
+pub mod connection;
+pub mod discovery;
+pub mod listen;
+pub mod lobby;
 pub mod net_play;
 pub mod packets;
+pub mod recording;
+pub mod security;
 pub mod server;
+pub mod spectator;
+pub mod transport;
+pub mod turn_timer;
@@ -1,64 +1,466 @@
+use std::time::Instant;
+
+use super::chat::*;
+use super::connection::*;
 use super::packets::*;
+use super::turn_timer::*;
+use crate::console::*;
+use crate::game::debrief::*;
 use crate::game::game_state::*;
 use crate::game::killer_user::*;
 use crate::game::victim_user::*;
+use crate::profile::*;
+use crate::tr;
 use crate::util::*;
 
+/// Summary of a round played over the network.
+#[derive(Clone, Copy)]
+pub struct NetRound {
+    /// Move made by the victim.
+    pub victim_move: (usize, usize),
+
+    /// Move made by the killer.
+    pub killer_move: (usize, usize),
+
+    /// Result of the round.
+    pub result: PlayResult,
+
+    /// Player who forfeited the game this round, if any.
+    pub forfeit: Option<PlayerType>,
+
+    /// Flag indicating the other player left during the round, forfeiting the game.
+    pub other_player_left: bool,
+}
+
+impl NetRound {
+    /// Returns 'true' if the round ended the game.
+    pub fn is_finished(&self) -> bool {
+        self.forfeit.is_some()
+            || self.result.result == RoundResult::Caught
+            || self.result.result == RoundResult::AllPartsFound
+    }
+
+    /// Get the player type of whoever won the game this round, if anyone did.
+    pub fn winner(&self) -> Option<PlayerType> {
+        if let Some(forfeiter) = self.forfeit {
+            Some(forfeiter.opposite())
+        } else if self.result.result == RoundResult::Caught {
+            Some(PlayerType::Killer)
+        } else if self.result.result == RoundResult::AllPartsFound {
+            Some(PlayerType::Victim)
+        } else {
+            None
+        }
+    }
+}
+
 /// Play the game with another user over the internet.
 ///
-/// Takes in our 'player_type' and a 'stream' to send our moves over, along with
-/// the current game 'state'.
+/// Takes in the 'console' to talk to the user on, our 'player_type' and a 'stream' to send our
+/// moves over, along with the current game 'state' and the 'timer' limiting how long each turn
+/// can take.
 ///
-/// Returns 'true' if the game is finished and 'false' otherwise.
+/// Returns a summary of the round that was played.
 pub fn net_play(
+    console: &mut Console,
+    player_type: PlayerType,
+    state: &mut GameState,
+    stream: &mut Connection,
+    timer: &mut TurnTimer,
+) -> NetRound {
+    let round = play_round(console, player_type, state, stream, timer);
+
+    // Once the game is over, show how it played out and remember it
+    if round.is_finished() {
+        print_debrief(console, state, Some(player_type));
+        record_match(
+            console,
+            state,
+            player_type,
+            Opponent::Player,
+            round.winner() == Some(player_type),
+        );
+    }
+
+    round
+}
+
+/// Play a round with another user over the internet, as described for `net_play`.
+fn play_round(
+    console: &mut Console,
     player_type: PlayerType,
     state: &mut GameState,
-    stream: &mut std::net::TcpStream,
-) -> bool {
+    stream: &mut Connection,
+    timer: &mut TurnTimer,
+) -> NetRound {
     // Play game
+    let deadline = timer.deadline();
     let our_move = if player_type == PlayerType::Killer {
-        play_killer(state)
+        play_killer_before(console, state, deadline)
     } else {
-        play_victim(state)
+        play_victim_before(console, state, deadline)
     };
 
-    // Send our move to the other player
-    write_over_tcp::<MovePacket>(
-        stream,
-        &MovePacket {
-            0: our_move.0 as u32,
-            1: our_move.1 as u32,
-        },
-    );
+    // Send our move to the other player, making a random one if we ran out of time
+    let (our_move, our_message) = match our_move {
+        Some(m) => (m, RoundMessage::Move(MovePacket(m.0 as u32, m.1 as u32))),
+        None => {
+            let m = state.random_move();
+            let timeouts = timer.record_timeout(player_type);
+            writeln!(
+                console,
+                "{}",
+                tr!("net.you_timed_out", count = timeouts, max = MAX_TIMEOUTS)
+            );
+            (
+                m,
+                RoundMessage::TimedOut(MovePacket(m.0 as u32, m.1 as u32)),
+            )
+        }
+    };
+    write_over_tcp::<RoundMessage>(stream, &our_message);
 
-    // Wait for other player to tell us their move
-    println!("Waiting for the other player move...");
-    let other_player_move = read_over_tcp::<MovePacket>(stream);
+    // Wait for other player to tell us their move, chatting while we wait
+    writeln!(console, "{}", tr!("net.waiting_for_move"));
+    print_chat_help(console);
+    print_prompt(console);
+    let leave_deadline = timer.leave_deadline();
+    let other_player_move = loop {
+        // Give up on the other player if they've been gone for too long
+        if leave_deadline.is_some_and(|d| Instant::now() >= d) {
+            break None;
+        }
 
-    // Submit moves to the game state
-    let res = if player_type == PlayerType::Killer {
-        state.play(
-            (other_player_move.0 as usize, other_player_move.1 as usize),
-            our_move,
-        )
+        // Show anything the other player sent
+        if has_data(stream).unwrap_or(true) {
+            match try_read_over_tcp::<RoundMessage>(stream) {
+                Ok(RoundMessage::Move(m)) => break Some(m),
+                Ok(RoundMessage::TimedOut(m)) => {
+                    let timeouts = timer.record_timeout(player_type.opposite());
+                    writeln!(
+                        console,
+                        "\n{}",
+                        tr!(
+                            "net.other_player_timed_out",
+                            count = timeouts,
+                            max = MAX_TIMEOUTS
+                        )
+                    );
+                    break Some(m);
+                }
+                Ok(RoundMessage::Chat(msg)) => {
+                    print_chat(console, player_type.opposite(), &msg);
+                    print_prompt(console);
+                }
+                Ok(RoundMessage::EndOfRound) => {}
+                Err(_) => break None,
+            }
+        }
+        // Send anything we type
+        else if let Some(line) = try_read_str(console, INPUT_POLL_INTERVAL) {
+            if handle_chat_input(console, stream, &line).is_err() {
+                break None;
+            }
+        }
+    };
+    writeln!(console);
+
+    // The other player has to send a move that makes sense, or we assume they have left
+    let other_player_move = match other_player_move {
+        Some(m) if state.is_valid_move((m.0 as usize, m.1 as usize)) => {
+            (m.0 as usize, m.1 as usize)
+        }
+        _ => return other_player_left(console, player_type, our_move),
+    };
+
+    // The other player might have chatted after sending their move, so read until they're done
+    write_over_tcp::<RoundMessage>(stream, &RoundMessage::EndOfRound);
+    let leave_deadline = timer.leave_deadline();
+    let finished = loop {
+        if leave_deadline.is_some_and(|d| Instant::now() >= d) {
+            break false;
+        }
+
+        if !has_data(stream).unwrap_or(true) {
+            std::thread::sleep(INPUT_POLL_INTERVAL);
+            continue;
+        }
+
+        match try_read_over_tcp::<RoundMessage>(stream) {
+            Ok(RoundMessage::Chat(msg)) => print_chat(console, player_type.opposite(), &msg),
+            Ok(RoundMessage::EndOfRound) => break true,
+            Ok(_) => {}
+            Err(_) => break false,
+        }
+    };
+    if !finished {
+        return other_player_left(console, player_type, our_move);
+    }
+
+    // Figure out whose move is whose
+    let (victim_move, killer_move) = if player_type == PlayerType::Killer {
+        (other_player_move, our_move)
     } else {
-        state.play(
-            our_move,
-            (other_player_move.0 as usize, other_player_move.1 as usize),
-        )
+        (our_move, other_player_move)
     };
 
+    // Running out of time too often forfeits the game
+    if let Some(forfeiter) = timer.forfeiter() {
+        if forfeiter == player_type {
+            writeln!(console, "{}", tr!("net.you_forfeit"));
+            print_lose(console);
+        } else {
+            writeln!(console, "{}", tr!("net.other_player_forfeits"));
+            print_win(console);
+        }
+
+        return NetRound {
+            victim_move,
+            killer_move,
+            result: PlayResult::default(),
+            forfeit: Some(forfeiter),
+            other_player_left: false,
+        };
+    }
+
+    // Submit moves to the game state
+    let res = state.play(victim_move, killer_move);
+
     // Killer wins
     if res.result == RoundResult::Caught {
-        killer_win_message(player_type);
-        return true;
+        killer_win_message(console, player_type);
     }
     // Victim wins
     else if res.result == RoundResult::AllPartsFound {
-        victim_win_message(player_type);
-        return true;
+        victim_win_message(console, player_type);
+    }
+
+    NetRound {
+        victim_move,
+        killer_move,
+        result: res,
+        forfeit: None,
+        other_player_left: false,
     }
+}
 
-    // Nobody won
-    false
+/// Tell the `player_type` player the other player left during a round where we played
+/// `our_move`, which forfeits the game.
+fn other_player_left(
+    console: &mut Console,
+    player_type: PlayerType,
+    our_move: (usize, usize),
+) -> NetRound {
+    writeln!(console, "{}", tr!("net.other_player_left"));
+    print_win(console);
+    NetRound {
+        victim_move: our_move,
+        killer_move: our_move,
+        result: PlayResult::default(),
+        forfeit: Some(player_type.opposite()),
+        other_player_left: true,
+    }
+}
+
+/// Running score of a series of games against the same opponent.
+#[derive(Default)]
+pub struct SeriesScore {
+    /// Number of games we won.
+    pub wins: u32,
+
+    /// Number of games the other player won.
+    pub losses: u32,
+}
+
+impl SeriesScore {
+    /// Record the result of the final round of a game, given our 'player_type'.
+    pub fn record(&mut self, player_type: PlayerType, round: &NetRound) {
+        match round.winner() {
+            Some(winner) if winner == player_type => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => {}
+        }
+    }
+
+    /// Print out the score.
+    pub fn print(&self, console: &mut Console) {
+        writeln!(
+            console,
+            "{}",
+            tr!("net.score", wins = self.wins, losses = self.losses)
+        );
+    }
+}
+
+/// Ask the user if they want a rematch and find out what the other player wants.
+///
+/// Returns `None` if there won't be a rematch, otherwise `Some` with 'true' if roles should be
+/// swapped.
+pub fn vote_for_rematch(console: &mut Console, stream: &mut Connection) -> Option<bool> {
+    writeln!(console, "{}", tr!("rematch.ask"));
+    let our_vote = match pick_char(console, &['R', 'S', 'Q'], tr!("error.not_option")) {
+        'R' => RematchVote::Rematch,
+        'S' => RematchVote::RematchSwapRoles,
+        'Q' => RematchVote::Quit,
+        _ => panic!("Invalid input."),
+    };
+
+    // Exchange votes, treating a lost connection as the other player quitting
+    if our_vote != RematchVote::Quit {
+        writeln!(console, "{}", tr!("rematch.waiting"));
+    }
+    let their_vote = match try_write_over_tcp(stream, &our_vote) {
+        Ok(_) => try_read_over_tcp::<RematchVote>(stream).unwrap_or(RematchVote::Quit),
+        Err(_) => RematchVote::Quit,
+    };
+
+    let decision = our_vote.combine(their_vote);
+    match decision {
+        None if our_vote != RematchVote::Quit => {
+            writeln!(console, "{}", tr!("rematch.declined"))
+        }
+        None => {}
+        Some(true) => writeln!(console, "{}", tr!("rematch.swap_roles")),
+        Some(false) => {
+            if our_vote != their_vote {
+                writeln!(console, "{}", tr!("rematch.no_swap"));
+            }
+            writeln!(console, "{}", tr!("rematch.same_roles"));
+        }
+    }
+
+    decision
+}
+
+/// Testing for playing over the network.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Get a connected pair of connections, being ours and the other player's.
+    fn connection_pair() -> (Connection, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let other = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            Connection::accept(stream, None).unwrap()
+        });
+        let ours = Connection::connect(&addr, None).unwrap();
+        (ours, other.join().unwrap())
+    }
+
+    /// Chat sent before and after the other player's move is shown, and the round still ends.
+    #[test]
+    fn chatting() {
+        let (mut ours, mut theirs) = connection_pair();
+        let other = std::thread::spawn(move || {
+            write_over_tcp(&mut theirs, &RoundMessage::Chat("Hello?".to_string()));
+            write_over_tcp(&mut theirs, &RoundMessage::Move(MovePacket(1, 0)));
+            write_over_tcp(&mut theirs, &RoundMessage::Chat("Found you!".to_string()));
+            write_over_tcp(&mut theirs, &RoundMessage::EndOfRound);
+
+            // Wait for our move and for us to finish the round
+            assert!(matches!(
+                try_read_over_tcp::<RoundMessage>(&mut theirs).unwrap(),
+                RoundMessage::Move(MovePacket(0, 1))
+            ));
+            assert!(matches!(
+                try_read_over_tcp::<RoundMessage>(&mut theirs).unwrap(),
+                RoundMessage::EndOfRound
+            ));
+        });
+
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.hide_part(1, 1);
+        let (mut console, output) = Console::scripted(&["C", "K"]);
+        let round = net_play(
+            &mut console,
+            PlayerType::Victim,
+            &mut state,
+            &mut ours,
+            &mut TurnTimer::new(0),
+        );
+        other.join().unwrap();
+
+        assert_eq!(round.killer_move, (1, 0));
+        assert!(!round.is_finished());
+        assert_eq!(state.history.len(), 1);
+        let text = output.text();
+        assert!(text.contains("[Killer] Hello?"));
+        assert!(text.contains("[Killer] Found you!"));
+    }
+
+    /// Running out of time sends a random move for us, which is counted against us.
+    #[test]
+    fn timing_out() {
+        let (mut ours, mut theirs) = connection_pair();
+        let other = std::thread::spawn(move || {
+            let our_move = try_read_over_tcp::<RoundMessage>(&mut theirs).unwrap();
+            write_over_tcp(&mut theirs, &RoundMessage::Move(MovePacket(1, 0)));
+            write_over_tcp(&mut theirs, &RoundMessage::EndOfRound);
+            let _ = try_read_over_tcp::<RoundMessage>(&mut theirs);
+            our_move
+        });
+
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        let mut timer = TurnTimer::new(1);
+        let (mut console, output) = Console::scripted(&[]);
+        let round = net_play(
+            &mut console,
+            PlayerType::Victim,
+            &mut state,
+            &mut ours,
+            &mut timer,
+        );
+
+        let m = match other.join().unwrap() {
+            RoundMessage::TimedOut(m) => (m.0 as usize, m.1 as usize),
+            _ => panic!("no timeout was sent"),
+        };
+        assert!(GameState::new().is_valid_move(m));
+        assert_eq!(round.victim_move, m);
+        assert_eq!(timer.record_timeout(PlayerType::Victim), 2);
+        assert!(output
+            .text()
+            .contains("Time's up! A random move was made for you."));
+    }
+
+    /// Roles are only swapped if both players want to, and anyone quitting ends the match.
+    #[test]
+    fn rematch_votes() {
+        use RematchVote::*;
+        for (ours, theirs, decision) in [
+            (Rematch, Rematch, Some(false)),
+            (Rematch, RematchSwapRoles, Some(false)),
+            (RematchSwapRoles, Rematch, Some(false)),
+            (RematchSwapRoles, RematchSwapRoles, Some(true)),
+            (Rematch, Quit, None),
+            (RematchSwapRoles, Quit, None),
+            (Quit, Rematch, None),
+            (Quit, RematchSwapRoles, None),
+            (Quit, Quit, None),
+        ] {
+            assert_eq!(ours.combine(theirs), decision);
+        }
+
+        // Votes are exchanged over the connection
+        let (mut ours, mut theirs) = connection_pair();
+        write_over_tcp(&mut theirs, &RematchVote::RematchSwapRoles);
+        let (mut console, output) = Console::scripted(&["S"]);
+        assert_eq!(vote_for_rematch(&mut console, &mut ours), Some(true));
+        assert!(output.text().contains("Rematch! You are swapping roles."));
+        assert!(try_read_over_tcp::<RematchVote>(&mut theirs).unwrap() == RematchSwapRoles);
+
+        // Losing the other player counts as them quitting
+        let (mut ours, theirs) = connection_pair();
+        drop(theirs);
+        let (mut console, output) = Console::scripted(&["R"]);
+        assert_eq!(vote_for_rematch(&mut console, &mut ours), None);
+        assert!(output
+            .text()
+            .contains("The other player doesn't want a rematch."));
+    }
 }
@@ -1,71 +1,65 @@
-use byteorder::{ByteOrder, LittleEndian};
 use serde::{Deserialize, Serialize};
-use std::io::prelude::*;
+
+use super::connection::Connection;
+use crate::game::game_state::{GameState, PlayResult};
 
 /// Function to write the contents of a structure over a TCP connection.
-pub fn write_over_tcp<T>(stream: &mut std::net::TcpStream, val: &T)
+pub fn write_over_tcp<T>(conn: &mut Connection, val: &T)
 where
     T: Serialize,
 {
-    // Convert the data to a JSON string
-    let serialized = serde_json::to_string(val).expect("Serialization of structure failed!");
-
-    // Create a buffer to hold size of data and the data itself
-    let mut data = Vec::<u8>::with_capacity(4 + serialized.len());
-    data.resize(4, 0);
-
-    // Write size of serialized data to a buffer
-    LittleEndian::write_u32(&mut data, serialized.len() as u32);
-
-    // Write serialized data to buffer
-    data.extend_from_slice(serialized.as_bytes());
-
-    // Write data to TCP stream.
-    stream
-        .write_all(&data)
-        .expect("Error writing data to TCP stream!");
-}
-
-/// Function to read the contents of a structure from a TCP stream.
-pub fn read_over_tcp<T: serde::de::DeserializeOwned>(stream: &mut std::net::TcpStream) -> T {
-    // Read size of structure
-    let mut buf = [0u8; 4];
-    let mut pos: usize = 0;
-    while pos < 4 {
-        match stream.read(&mut buf[pos..]) {
-            Ok(n) => {
-                pos += n;
-            }
-            Err(_) => panic!("Error reading data over TCP stream!"),
-        }
-    }
+    try_write_over_tcp(conn, val).expect("Error writing data to TCP stream!");
+}
 
-    // Create new buffer to hold serialized data
-    let buf_size = LittleEndian::read_u32(&buf) as usize;
-    let mut buf = vec![0; buf_size];
+/// Function to write the contents of a structure over a TCP connection, returning an error instead
+/// of panicking if the write fails.
+///
+/// Useful for connections that are allowed to drop out, like spectators.
+pub fn try_write_over_tcp<T>(conn: &mut Connection, val: &T) -> std::io::Result<()>
+where
+    T: Serialize,
+{
+    conn.send(val)
+}
 
-    // Read serialized data
-    let mut pos: usize = 0;
-    while pos < buf_size {
-        match stream.read(&mut buf[pos..]) {
-            Ok(n) => {
-                pos += n;
-            }
-            Err(_) => panic!("Error reading data over TCP stream!"),
-        }
-    }
+/// Function to read the contents of a structure from a TCP connection.
+pub fn read_over_tcp<T: serde::de::DeserializeOwned>(conn: &mut Connection) -> T {
+    try_read_over_tcp(conn).expect("Error reading data over TCP stream!")
+}
+
+/// Function to read the contents of a structure from a TCP connection, returning an error
+/// instead of panicking if the read fails, the connection was closed, or the other end sent
+/// something we don't accept.
+pub fn try_read_over_tcp<T: serde::de::DeserializeOwned>(
+    conn: &mut Connection,
+) -> std::io::Result<T> {
+    conn.recv()
+}
 
-    // Deserialize data
-    serde_json::from_slice::<T>(&buf).expect("Unable to deserialized structure!")
+/// Check if there is data waiting to be read from a TCP connection without blocking.
+///
+/// A closed connection counts as having data, so the next read reports the error.
+pub fn has_data(conn: &mut Connection) -> std::io::Result<bool> {
+    conn.has_data()
 }
 
 /// An enum used to identify a type of player (either a victim or killer)
-#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum PlayerType {
     Killer,
     Victim,
 }
 
+impl PlayerType {
+    /// Get the player type of the other player.
+    pub fn opposite(self) -> PlayerType {
+        match self {
+            PlayerType::Killer => PlayerType::Victim,
+            PlayerType::Victim => PlayerType::Killer,
+        }
+    }
+}
+
 /// A structure used to describe the state of the game to a client.
 #[derive(Serialize, Deserialize)]
 pub struct GameStatePacket {
@@ -86,11 +80,211 @@ impl GameStatePacket {
             hidden_parts: Vec::<(u32, u32)>::new(),
         }
     }
+
+    /// Create a packet describing where the parts are hidden in `state`.
+    pub fn from_state(state: &GameState) -> GameStatePacket {
+        let mut packet = GameStatePacket::new();
+        for (i, section) in state.sections.iter().enumerate() {
+            for (j, sub_section) in section.sub_sections.iter().enumerate() {
+                if sub_section.part {
+                    packet.hidden_parts.push((i as u32, j as u32));
+                }
+            }
+        }
+
+        packet
+    }
 }
 
 /// A structure used to communicate the move made during the game.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Copy, Serialize, Deserialize)]
 pub struct MovePacket(pub u32, pub u32);
 
+/// A message sent by a player while a round is being played.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum RoundMessage {
+    /// The move the player made this round.
+    Move(MovePacket),
+
+    /// A random move made for the player because they ran out of time.
+    TimedOut(MovePacket),
+
+    /// A chat message for the other player.
+    Chat(String),
+
+    /// Sent once a player has received the other players move. Nothing else is sent by that
+    /// player until the next round.
+    EndOfRound,
+}
+
 /// A trap used to communicate where the victim has placed a trap.
 pub type TrapPacket = u32;
+
+/// Sent by the host once a game is set up, with the number of seconds each player gets per turn.
+///
+/// A value of 0 means there is no time limit.
+pub type TurnTimerPacket = u32;
+
+/// Sent by the host to the other player before every round with the number of spectators watching.
+pub type SpectatorCountPacket = u32;
+
+/// A players vote on what to do once a game is over.
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum RematchVote {
+    /// Play again with the same roles.
+    Rematch,
+
+    /// Play again with the killer and victim swapped.
+    RematchSwapRoles,
+
+    /// Stop playing.
+    Quit,
+}
+
+impl RematchVote {
+    /// Combine our vote with the other players vote.
+    ///
+    /// Returns `None` if either player wants to quit, otherwise `Some` with 'true' if both
+    /// players want to swap roles.
+    pub fn combine(self, other: RematchVote) -> Option<bool> {
+        if self == RematchVote::Quit || other == RematchVote::Quit {
+            None
+        } else {
+            Some(self == RematchVote::RematchSwapRoles && other == RematchVote::RematchSwapRoles)
+        }
+    }
+}
+
+/// Sent by a client right after connecting to tell the host what it wants to do.
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum ConnectionType {
+    /// The client wants to play the game.
+    Player,
+
+    /// The client only wants to watch the game.
+    Spectator,
+}
+
+/// The hosts response to a `ConnectionType`.
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum ConnectionResponse {
+    /// The client was accepted.
+    Accepted,
+
+    /// Someone is already playing against the host.
+    GameFull,
+
+    /// The host is a lobby server, so the client should pick a room to play in.
+    Lobby,
+}
+
+/// Broadcast by a waiting host so players on the local network can find it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HostAnnouncement {
+    /// Name of the hosts computer.
+    pub host_name: String,
+
+    /// Port the host is listening on.
+    pub port: u16,
+
+    /// Player type the host will be, or `None` if it will be chosen randomly.
+    pub host_type: Option<PlayerType>,
+
+    /// Flag indicating a password is needed to join.
+    pub private: bool,
+}
+
+/// A structure used to keep spectators up to date on the game.
+#[derive(Clone, Serialize, Deserialize)]
+pub enum SpectatorPacket {
+    /// The game has started.
+    MatchStart {
+        /// Which player type the host is.
+        host: PlayerType,
+
+        /// Where the car parts are hidden, if the host chose to reveal them.
+        hidden_parts: Option<Vec<(u32, u32)>>,
+    },
+
+    /// A round was played.
+    Round {
+        /// Number of the round, starting at 1.
+        round: u32,
+
+        /// Move made by the victim.
+        victim_move: MovePacket,
+
+        /// Move made by the killer.
+        killer_move: MovePacket,
+
+        /// Result of the round.
+        result: PlayResult,
+    },
+
+    /// A player ran out of time too many times and forfeits.
+    Forfeit(PlayerType),
+}
+
+/// A request sent by a client to a lobby server.
+#[derive(Serialize, Deserialize)]
+pub enum LobbyRequest {
+    /// List every room waiting for an opponent.
+    ListRooms,
+
+    /// Create a room and wait for an opponent to join it.
+    CreateRoom {
+        /// Name of the room.
+        name: String,
+
+        /// Player type the creator wants to be, or `None` to choose randomly.
+        role: Option<PlayerType>,
+
+        /// Seconds each player gets per turn.
+        turn_time: TurnTimerPacket,
+    },
+
+    /// Join a room by its name or code.
+    JoinRoom(String),
+}
+
+/// A lobby servers response to a `LobbyRequest`.
+#[derive(Serialize, Deserialize)]
+pub enum LobbyResponse {
+    /// Every room waiting for an opponent.
+    Rooms(Vec<RoomInfo>),
+
+    /// A room was created with the given code.
+    RoomCreated(String),
+
+    /// An opponent was found and the game is about to begin.
+    MatchStarting,
+
+    /// The request couldn't be completed.
+    Error(LobbyError),
+}
+
+/// Description of a room waiting for an opponent.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RoomInfo {
+    /// Name of the room.
+    pub name: String,
+
+    /// Short code identifying the room.
+    pub code: String,
+
+    /// Player type the creator will be, or `None` if it will be chosen randomly.
+    pub creator_role: Option<PlayerType>,
+
+    /// Seconds each player gets per turn.
+    pub turn_time: TurnTimerPacket,
+}
+
+/// Reasons a `LobbyRequest` might fail.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum LobbyError {
+    /// No room has that name or code.
+    RoomNotFound,
+
+    /// Another room already has that name.
+    NameTaken,
+}
@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::net_play::*;
+use super::packets::*;
+use super::spectator::*;
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::settings::*;
+use crate::tr;
+use crate::util::*;
+
+/// Folder recordings are saved in, inside the user's data folder.
+pub const RECORDINGS_DIR: &str = "recordings";
+
+/// Longest pause between rounds when replaying a recording.
+const MAX_REPLAY_PAUSE: Duration = Duration::from_secs(2);
+
+/// Sent by the host at the end of every game, with a recording of the game if it made one.
+pub type RecordingPacket = Option<GameRecording>;
+
+/// A recording of a game played over the network, which can be replayed later.
+#[derive(Serialize, Deserialize)]
+pub struct GameRecording {
+    /// When the game started, in milliseconds since the Unix epoch.
+    pub started_at: u64,
+
+    /// Player type of the host.
+    pub host_type: PlayerType,
+
+    /// Where the car parts were hidden.
+    pub state: GameStatePacket,
+
+    /// Every round that was played.
+    pub rounds: Vec<RecordedRound>,
+}
+
+/// A round in a recording.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct RecordedRound {
+    /// When the round finished, in milliseconds after the game started.
+    pub time: u64,
+
+    /// Move made by the victim.
+    pub victim_move: MovePacket,
+
+    /// Move made by the killer.
+    pub killer_move: MovePacket,
+
+    /// Result of the round.
+    pub result: PlayResult,
+
+    /// Player who forfeited the game this round, if any.
+    pub forfeit: Option<PlayerType>,
+}
+
+/// Records a game as it is played.
+pub struct Recorder {
+    /// Recording so far.
+    recording: GameRecording,
+
+    /// When the game started.
+    start: Instant,
+}
+
+impl Recorder {
+    /// Start recording a game where the host is a `host_type` and the parts are hidden as
+    /// described by `state`.
+    pub fn start(host_type: PlayerType, state: GameStatePacket) -> Recorder {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or(0);
+
+        Recorder {
+            recording: GameRecording {
+                started_at,
+                host_type,
+                state,
+                rounds: Vec::new(),
+            },
+            start: Instant::now(),
+        }
+    }
+
+    /// Record a round that was just played.
+    pub fn record_round(&mut self, round: &NetRound) {
+        self.recording.rounds.push(RecordedRound {
+            time: self.start.elapsed().as_millis() as u64,
+            victim_move: MovePacket(round.victim_move.0 as u32, round.victim_move.1 as u32),
+            killer_move: MovePacket(round.killer_move.0 as u32, round.killer_move.1 as u32),
+            result: round.result,
+            forfeit: round.forfeit,
+        });
+    }
+
+    /// Stop recording, returning the finished recording.
+    pub fn finish(self) -> GameRecording {
+        self.recording
+    }
+}
+
+impl GameRecording {
+    /// Save the recording in the recordings folder, returning where it was saved.
+    pub fn save(&self) -> std::io::Result<PathBuf> {
+        let dir = recordings_dir();
+        std::fs::create_dir_all(&dir)?;
+
+        // Both players might save the same game on the same computer
+        let mut path = dir.join(format!("game-{}.json", self.started_at));
+        let mut copy = 1;
+        while path.exists() {
+            copy += 1;
+            path = dir.join(format!("game-{}-{}.json", self.started_at, copy));
+        }
+
+        std::fs::write(&path, serde_json::to_vec_pretty(self)?)?;
+        Ok(path)
+    }
+
+    /// Load a recording saved at `path`.
+    pub fn load(path: &Path) -> std::io::Result<GameRecording> {
+        let recording: GameRecording = serde_json::from_slice(&std::fs::read(path)?)?;
+        if recording.is_valid() {
+            Ok(recording)
+        } else {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "recording has places that aren't in Camp Misty",
+            ))
+        }
+    }
+
+    /// Check every place in the recording is somewhere in Camp Misty, in case it was edited.
+    ///
+    /// Recordings are replayed as the packets a spectator would have been sent, so this checks
+    /// those packets the same way spectators do.
+    fn is_valid(&self) -> bool {
+        let view = SpectatorView::new();
+        self.spectator_packets()
+            .iter()
+            .all(|(_, packet)| view.is_valid(packet))
+    }
+
+    /// Get the packets a spectator would have been sent, along with when they would have been
+    /// sent, in milliseconds after the game started.
+    fn spectator_packets(&self) -> Vec<(u64, SpectatorPacket)> {
+        let mut packets = vec![(
+            0,
+            SpectatorPacket::MatchStart {
+                host: self.host_type,
+                hidden_parts: Some(self.state.hidden_parts.clone()),
+            },
+        )];
+
+        for (i, round) in self.rounds.iter().enumerate() {
+            let packet = match round.forfeit {
+                Some(forfeiter) => SpectatorPacket::Forfeit(forfeiter),
+                None => SpectatorPacket::Round {
+                    round: i as u32 + 1,
+                    victim_move: round.victim_move,
+                    killer_move: round.killer_move,
+                    result: round.result,
+                },
+            };
+            packets.push((round.time, packet));
+        }
+
+        packets
+    }
+}
+
+/// Get the folder recordings are saved in.
+pub fn recordings_dir() -> PathBuf {
+    data_dir().join(RECORDINGS_DIR)
+}
+
+/// Let the user pick a saved recording and replay it.
+pub fn replay_menu(console: &mut Console) {
+    // List the recordings we know about, oldest first
+    let dir = recordings_dir();
+    let mut paths: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+
+    if paths.is_empty() {
+        writeln!(console, "{}", tr!("replay.none", folder = dir.display()));
+    }
+    for (i, path) in paths.iter().enumerate() {
+        writeln!(console, "    {}) {}", i + 1, path.display());
+    }
+
+    writeln!(console, "{}", tr!("replay.pick"));
+    let input = read_str(console);
+    if input.is_empty() {
+        return;
+    }
+
+    let path = match input.parse::<usize>() {
+        Ok(i) if i >= 1 && i <= paths.len() => paths[i - 1].clone(),
+        _ => PathBuf::from(input),
+    };
+
+    match GameRecording::load(&path) {
+        Ok(recording) => replay(console, &recording),
+        Err(_) => writeln!(
+            console,
+            "{}",
+            tr!("replay.load_failed", path = path.display())
+        ),
+    }
+
+    writeln!(console, "{}", tr!("menu.return_to_title"));
+    read_str(console);
+}
+
+/// Replay a recording, showing it the same way spectators see a game.
+///
+/// Rounds are shown with the same pauses between them as when they were played, up to a limit.
+pub fn replay(console: &mut Console, recording: &GameRecording) {
+    writeln!(console, "{}", tr!("replay.started"));
+
+    let mut view = SpectatorView::new();
+    let mut last_time = 0;
+    for (time, packet) in recording.spectator_packets() {
+        let pause = Duration::from_millis(time.saturating_sub(last_time));
+        std::thread::sleep(pause.min(MAX_REPLAY_PAUSE));
+        last_time = time;
+
+        view.show(console, &packet);
+    }
+
+    writeln!(console, "\n{}", tr!("replay.finished"));
+}
+
+/// Testing for recordings.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Recordings survive being saved as JSON, and edited ones that go outside Camp Misty are
+    /// caught.
+    #[test]
+    fn recordings() {
+        let mut state = GameState::new();
+        state.gen_state();
+        let mut recorder = Recorder::start(PlayerType::Killer, GameStatePacket::from_state(&state));
+        recorder.record_round(&NetRound {
+            victim_move: (0, 1),
+            killer_move: (2, 3),
+            result: state.play((0, 1), (2, 3)),
+            forfeit: None,
+            other_player_left: false,
+        });
+
+        let json = serde_json::to_vec(&recorder.finish()).unwrap();
+        let mut recording: GameRecording = serde_json::from_slice(&json).unwrap();
+        assert!(recording.is_valid());
+        assert_eq!(recording.rounds.len(), 1);
+        assert_eq!(recording.spectator_packets().len(), 2);
+
+        recording.rounds[0].killer_move = MovePacket(2, 99);
+        assert!(!recording.is_valid());
+    }
+}
@@ -0,0 +1,292 @@
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Number of PBKDF2 rounds used to turn a password into a key.
+const KEY_ROUNDS: u32 = 100_000;
+
+/// Size of the random salt mixed into a room key, in bytes.
+const SALT_SIZE: usize = 16;
+
+/// Size of the random nonces each end contributes to a session, in bytes.
+const HANDSHAKE_NONCE_SIZE: usize = 16;
+
+/// Size of the key used for a session, in bytes.
+const KEY_SIZE: usize = 32;
+
+/// Key derived from the password of a private game.
+///
+/// The host derives this once, and anyone who wants to join has to prove they know the password
+/// before a session key is made for them.
+#[derive(Clone)]
+pub struct RoomKey {
+    /// Random salt the key was derived with.
+    salt: [u8; SALT_SIZE],
+
+    /// The key itself.
+    key: [u8; KEY_SIZE],
+}
+
+impl RoomKey {
+    /// Derive a key from `password` with a fresh salt.
+    pub fn new(password: &str) -> RoomKey {
+        let mut salt = [0u8; SALT_SIZE];
+        rand::thread_rng().fill_bytes(&mut salt);
+        RoomKey::with_salt(password, salt)
+    }
+
+    /// Derive a key from `password` and the hosts `salt`.
+    pub fn with_salt(password: &str, salt: [u8; SALT_SIZE]) -> RoomKey {
+        let mut key = [0u8; KEY_SIZE];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, KEY_ROUNDS, &mut key);
+        RoomKey { salt, key }
+    }
+
+    /// Create a challenge for someone who wants to join.
+    pub fn challenge(&self) -> AuthChallenge {
+        AuthChallenge {
+            salt: self.salt,
+            nonce: random_nonce(),
+        }
+    }
+
+    /// Prove to the host that we know the password, given their `challenge`.
+    pub fn prove(&self, challenge: &AuthChallenge) -> AuthProof {
+        let nonce = random_nonce();
+        AuthProof {
+            nonce,
+            mac: self.mac(b"client", &challenge.nonce, &nonce),
+        }
+    }
+
+    /// Check a `proof` that the other end knows the password, given the `challenge` we sent.
+    ///
+    /// Returns the hosts proof that they know the password too if it checks out.
+    pub fn check_proof(&self, challenge: &AuthChallenge, proof: &AuthProof) -> Option<Vec<u8>> {
+        if self.verify(b"client", &challenge.nonce, &proof.nonce, &proof.mac) {
+            Some(self.mac(b"server", &proof.nonce, &challenge.nonce))
+        } else {
+            None
+        }
+    }
+
+    /// Check the hosts proof that they know the password.
+    pub fn check_host_proof(
+        &self,
+        challenge: &AuthChallenge,
+        proof: &AuthProof,
+        host_mac: &[u8],
+    ) -> bool {
+        self.verify(b"server", &proof.nonce, &challenge.nonce, host_mac)
+    }
+
+    /// Create the cipher for a session once both ends have proved they know the password.
+    ///
+    /// `is_host` tells us which end we are, since each end encrypts with its own nonces.
+    pub fn session(
+        &self,
+        challenge: &AuthChallenge,
+        proof: &AuthProof,
+        is_host: bool,
+    ) -> SessionCipher {
+        let session_key = self.mac(b"session", &challenge.nonce, &proof.nonce);
+        SessionCipher {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&session_key)),
+            send_direction: if is_host { 0 } else { 1 },
+            send_count: 0,
+            recv_count: 0,
+        }
+    }
+
+    /// Create a MAC over a `label` and two nonces.
+    fn mac(&self, label: &[u8], a: &[u8], b: &[u8]) -> Vec<u8> {
+        self.hmac(label, a, b).finalize().into_bytes().to_vec()
+    }
+
+    /// Check a MAC over a `label` and two nonces in constant time.
+    fn verify(&self, label: &[u8], a: &[u8], b: &[u8], mac: &[u8]) -> bool {
+        self.hmac(label, a, b).verify_slice(mac).is_ok()
+    }
+
+    /// Create an HMAC over a `label` and two nonces.
+    fn hmac(&self, label: &[u8], a: &[u8], b: &[u8]) -> Hmac<Sha256> {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(&self.key).expect("HMAC takes any key size!");
+        mac.update(label);
+        mac.update(a);
+        mac.update(b);
+        mac
+    }
+}
+
+/// Encrypts and decrypts the frames of a session with an AEAD cipher.
+///
+/// Every frame uses a new nonce made from a counter, so frames that are replayed, dropped, or
+/// reordered fail to decrypt.
+pub struct SessionCipher {
+    /// Cipher keyed with the session key.
+    cipher: ChaCha20Poly1305,
+
+    /// Direction we send in, which keeps our nonces apart from the other ends.
+    send_direction: u8,
+
+    /// Number of frames sent.
+    send_count: u64,
+
+    /// Number of frames received.
+    recv_count: u64,
+}
+
+impl SessionCipher {
+    /// Encrypt a frame.
+    pub fn encrypt(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
+        let nonce = frame_nonce(self.send_direction, self.send_count);
+        self.send_count += 1;
+        self.cipher.encrypt(&nonce, payload).ok()
+    }
+
+    /// Decrypt a frame, returning `None` if it wasn't encrypted with the session key or was
+    /// tampered with.
+    pub fn decrypt(&mut self, frame: &[u8]) -> Option<Vec<u8>> {
+        let nonce = frame_nonce(1 - self.send_direction, self.recv_count);
+        let payload = self.cipher.decrypt(&nonce, frame).ok()?;
+        self.recv_count += 1;
+        Some(payload)
+    }
+}
+
+/// Sent by the host of a private game to someone who wants to join.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuthChallenge {
+    /// Salt the room key was derived with.
+    pub salt: [u8; SALT_SIZE],
+
+    /// Random nonce from the host.
+    pub nonce: [u8; HANDSHAKE_NONCE_SIZE],
+}
+
+/// Sent in answer to an `AuthChallenge` to prove we know the password.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AuthProof {
+    /// Random nonce from whoever is joining.
+    pub nonce: [u8; HANDSHAKE_NONCE_SIZE],
+
+    /// MAC over both nonces made with the room key.
+    pub mac: Vec<u8>,
+}
+
+/// The hosts answer to an `AuthProof`.
+#[derive(Serialize, Deserialize)]
+pub enum AuthResult {
+    /// The password was right. Includes the hosts own proof that it knows the password.
+    Accepted(Vec<u8>),
+
+    /// The password was wrong.
+    AccessDenied,
+}
+
+/// Reasons we couldn't get into a private game.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AuthError {
+    /// The game has a password, but we didn't give one.
+    PasswordRequired,
+
+    /// The password was wrong.
+    AccessDenied,
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AuthError::PasswordRequired => write!(f, "password required"),
+            AuthError::AccessDenied => write!(f, "access denied"),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl AuthError {
+    /// Create an IO error for this error, so it can be returned while connecting.
+    pub fn into_io_error(self) -> std::io::Error {
+        std::io::Error::new(std::io::ErrorKind::PermissionDenied, self)
+    }
+
+    /// Get the error that caused an IO error, if it was this kind of error.
+    pub fn from_io_error(e: &std::io::Error) -> Option<AuthError> {
+        e.get_ref()?.downcast_ref::<AuthError>().copied()
+    }
+}
+
+/// Generate a random handshake nonce.
+fn random_nonce() -> [u8; HANDSHAKE_NONCE_SIZE] {
+    let mut nonce = [0u8; HANDSHAKE_NONCE_SIZE];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    nonce
+}
+
+/// Make the nonce for frame number `count` sent in `direction`.
+fn frame_nonce(direction: u8, count: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[0] = direction;
+    nonce[4..].copy_from_slice(&count.to_le_bytes());
+    *Nonce::from_slice(&nonce)
+}
+
+/// Testing for security.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Both ends agree on a session when they know the password, and frames only decrypt once,
+    /// in order, and untouched.
+    #[test]
+    fn session() {
+        let host_key = RoomKey::new("hunter2");
+        let challenge = host_key.challenge();
+
+        let client_key = RoomKey::with_salt("hunter2", challenge.salt);
+        let proof = client_key.prove(&challenge);
+        let host_mac = host_key.check_proof(&challenge, &proof).unwrap();
+        assert!(client_key.check_host_proof(&challenge, &proof, &host_mac));
+
+        let mut host = host_key.session(&challenge, &proof, true);
+        let mut client = client_key.session(&challenge, &proof, false);
+
+        let first = host.encrypt(b"hidden parts").unwrap();
+        let second = host.encrypt(b"more hidden parts").unwrap();
+        assert!(client.decrypt(&second).is_none());
+        assert_eq!(client.decrypt(&first).unwrap(), b"hidden parts");
+        assert!(client.decrypt(&first).is_none());
+
+        let mut tampered = second.clone();
+        tampered[0] ^= 1;
+        assert!(client.decrypt(&tampered).is_none());
+        assert_eq!(client.decrypt(&second).unwrap(), b"more hidden parts");
+
+        // Each direction has its own nonces
+        let reply = client.encrypt(b"thanks").unwrap();
+        assert_eq!(host.decrypt(&reply).unwrap(), b"thanks");
+    }
+
+    /// The wrong password is turned away.
+    #[test]
+    fn wrong_password() {
+        let host_key = RoomKey::new("hunter2");
+        let challenge = host_key.challenge();
+
+        let client_key = RoomKey::with_salt("hunter3", challenge.salt);
+        let proof = client_key.prove(&challenge);
+        assert!(host_key.check_proof(&challenge, &proof).is_none());
+
+        // A host that doesn't know the password can't fake its proof either
+        let imposter = RoomKey::with_salt("guess", challenge.salt);
+        let real_key = RoomKey::with_salt("hunter2", challenge.salt);
+        let proof = real_key.prove(&challenge);
+        let fake_mac = imposter.mac(b"server", &proof.nonce, &challenge.nonce);
+        assert!(!real_key.check_host_proof(&challenge, &proof, &fake_mac));
+    }
+}
@@ -1,10 +1,40 @@
 use rand::Rng;
 
+use super::connection::*;
+use super::discovery::*;
+use super::listen::*;
 use super::net_play::*;
 use super::packets::*;
+use super::recording::*;
+use super::security::*;
+use super::spectator::*;
+use super::turn_timer::*;
+use crate::console::*;
 use crate::game::game_state::*;
+use crate::tr;
 use crate::util::*;
 
+/// How the host wants their game set up.
+pub struct HostSettings {
+    /// Player type the host will be, or `None` to choose randomly.
+    pub host_type: Option<PlayerType>,
+
+    /// Whether spectators get to see where the car parts are hidden.
+    pub reveal_parts: bool,
+
+    /// Seconds each player gets per turn, where 0 means no limit.
+    pub turn_time: TurnTimerPacket,
+
+    /// Password needed to join or watch, if the game is private.
+    pub password: Option<String>,
+
+    /// Whether every game is recorded so it can be replayed later.
+    pub record: bool,
+}
+
+/// Port used when the host doesn't pick one.
+pub const DEFAULT_PORT: u16 = 7777;
+
 /// A server that hosts a game
 pub struct Server {
     /// Game state.
@@ -14,71 +44,107 @@ pub struct Server {
     listener: std::net::TcpListener,
 
     /// Client stream.
-    client: Option<std::net::TcpStream>,
+    client: Option<Connection>,
+
+    /// Spectators watching the game.
+    spectators: Spectators,
+
+    /// Key derived from the games password, if it is private.
+    room_key: Option<RoomKey>,
 }
 
 impl Server {
     /// Constructor.
-    pub fn new(port: u16) -> Result<Server, ServerError> {
-        match std::net::TcpListener::bind(std::net::SocketAddr::from(([0, 0, 0, 0], port))) {
+    ///
+    /// Takes the address to listen for players on.
+    pub fn new(addr: std::net::SocketAddr) -> Result<Server, ServerError> {
+        match bind_listener(addr) {
             Ok(listener) => Ok(Server {
                 state: GameState::new(),
                 listener,
                 client: None,
+                spectators: Spectators::new(),
+                room_key: None,
             }),
 
             Err(_) => Err(ServerError),
         }
     }
 
-    /// Wait for a client to connect
-    pub fn wait_for_client(&mut self) -> Result<(), ConnectionError> {
-        match self.listener.accept() {
-            Ok((socket, _addr)) => {
-                self.client = Some(socket);
-                Ok(())
-            }
+    /// Make the game private, so only people who know `password` can join or watch.
+    ///
+    /// Everything sent over the network is encrypted too.
+    pub fn set_password(&mut self, password: &str) {
+        self.room_key = Some(RoomKey::new(password));
+    }
+
+    /// Wait for a client to connect.
+    ///
+    /// Spectators who connect while we wait are added to the list of spectators.
+    pub fn wait_for_client(&mut self, console: &mut Console) -> Result<(), ConnectionError> {
+        loop {
+            match self.listener.accept() {
+                Ok((socket, _addr)) => match read_connection_type(socket, self.room_key.as_ref()) {
+                    Some((mut socket, ConnectionType::Player)) => {
+                        // Let the player know they got in
+                        let accepted =
+                            try_write_over_tcp(&mut socket, &ConnectionResponse::Accepted);
+                        if accepted.is_ok() {
+                            self.client = Some(socket);
+                            return Ok(());
+                        }
+                    }
 
-            Err(_e) => Err(ConnectionError),
+                    Some((socket, ConnectionType::Spectator)) => {
+                        self.spectators.add(socket);
+                        print_spectator_count(console, &mut 0, self.spectators.count());
+                    }
+
+                    // Misbehaving connections are ignored
+                    None => {}
+                },
+
+                Err(_e) => return Err(ConnectionError),
+            }
         }
     }
 
     /// Host game logic.
-    pub fn host_game() {
+    pub fn host_game(console: &mut Console) {
         // Loop to make server
         let mut server: Server;
         loop {
             // Ask for port number
-            println!("Please enter the port you wish to use.");
+            writeln!(console, "{}", tr!("server.enter_port"));
+            writeln!(console, "{}", tr!("server.enter_port_hint"));
 
-            // Loop to get port
-            let port: u16;
+            // Loop to get address
+            let addr: std::net::SocketAddr;
             loop {
-                // Try to convert to int
-                match read_str().parse::<u16>() {
-                    Ok(i) => {
-                        port = i;
+                match parse_bind_addr(&read_str(console), DEFAULT_PORT) {
+                    Some(a) => {
+                        addr = a;
                         break;
                     }
 
-                    Err(_) => {
-                        println!("Sorry, I didn't understand you.");
+                    None => {
+                        writeln!(console, "{}", tr!("error.not_understood"));
                     }
                 }
             }
 
             // Attempt to create server
-            match Server::new(port) {
+            match Server::new(addr) {
                 Ok(s) => {
                     server = s;
                     break;
                 }
 
                 Err(_) => {
-                    println!("There was a problem creating the server.");
-                    println!("Would you like to (T)ry again or (R)eturn to the main menu?");
+                    writeln!(console, "{}", tr!("server.create_failed"));
+                    writeln!(console, "{}", tr!("menu.try_again"));
 
-                    match pick_char(&['T', 'R'], "Sorry, that isn't an option.") {
+                    match pick_char(console, &['T', 'R'], tr!("error.not_option")) {
                         'T' => {}
                         'R' => {
                             return;
@@ -89,71 +155,220 @@ impl Server {
             }
         }
 
-        // Wait for client
-        println!("Waiting for client...");
-        match server.wait_for_client() {
-            Ok(_) => {}
-            Err(_) => {
-                println!("Woops! Looks like something went wrong when the client tried to connect. Returning to the main menu.");
-                return;
+        // Host gets to choose if they want to be the killer or the victim
+        writeln!(console, "{}", tr!("menu.ask_role"));
+        let host_type = match pick_char(console, &['K', 'V', 'R'], tr!("error.not_option")) {
+            'K' => Some(PlayerType::Killer),
+            'V' => Some(PlayerType::Victim),
+            'R' => None,
+            _ => panic!("Invalid option chosen!"),
+        };
+
+        // Host decides how much spectators get to know
+        writeln!(console, "{}", tr!("server.ask_reveal_parts"));
+        let reveal_parts = pick_char(console, &['Y', 'N'], tr!("error.not_option")) == 'Y';
+
+        // Host decides how long each turn can take, and the client enforces it too
+        let turn_time = ask_turn_time(console);
+
+        // Private games need a password to get in
+        writeln!(console, "{}", tr!("server.ask_password"));
+        let password = if pick_char(console, &['Y', 'N'], tr!("error.not_option")) == 'Y' {
+            writeln!(console, "{}", tr!("server.enter_password"));
+            Some(read_str(console))
+        } else {
+            None
+        };
+
+        // Recordings settle arguments about who did what
+        writeln!(console, "{}", tr!("server.ask_record"));
+        let record = pick_char(console, &['Y', 'N'], tr!("error.not_option")) == 'Y';
+
+        let settings = HostSettings {
+            host_type,
+            reveal_parts,
+            turn_time,
+            password,
+            record,
+        };
+        if server.host(console, &settings).is_err() {
+            writeln!(console, "{}", tr!("server.client_failed"));
+            return;
+        }
+
+        // Return to title screen
+        writeln!(console, "{}", tr!("menu.return_to_title"));
+        read_str(console);
+    }
+
+    /// Wait for a client and play against them with the given `settings`.
+    pub fn host(
+        &mut self,
+        console: &mut Console,
+        settings: &HostSettings,
+    ) -> Result<(), ConnectionError> {
+        if let Some(password) = &settings.password {
+            self.set_password(password);
+        }
+
+        // Let players on the local network find us while we wait
+        let announcer = self.announce(console, settings.host_type);
+
+        // Let the host know where players can find them
+        let addrs = reachable_addrs(&self.listener);
+        if !addrs.is_empty() {
+            writeln!(console, "{}", tr!("server.join_at"));
+            for addr in addrs {
+                writeln!(console, "    {}", addr);
             }
         }
 
+        // Wait for client
+        writeln!(console, "{}", tr!("server.waiting_for_client"));
+        self.wait_for_client(console)?;
+        drop(announcer);
+
         // Play the game
-        println!("Client connected!");
-        server.play();
+        writeln!(console, "{}", tr!("server.client_connected"));
+        self.play(console, settings);
+        Ok(())
     }
 
-    /// Play the game!
-    pub fn play(&mut self) {
-        // Randomize game state
-        self.state.gen_state();
+    /// Announce the game to the local network until the returned announcer is dropped.
+    ///
+    /// `host_type` is the player type the host will be, or `None` if it will be chosen randomly.
+    fn announce(&self, console: &mut Console, host_type: Option<PlayerType>) -> Option<Announcer> {
+        let announcement = HostAnnouncement {
+            host_name: host_name(),
+            port: self.listener.local_addr().ok()?.port(),
+            host_type,
+            private: self.room_key.is_some(),
+        };
+
+        match Announcer::start(announcement) {
+            Ok(announcer) => Some(announcer),
+            Err(_) => {
+                writeln!(console, "{}", tr!("server.announce_failed"));
+                None
+            }
+        }
+    }
 
+    /// Play the game with the given `settings`!
+    pub fn play(&mut self, console: &mut Console, settings: &HostSettings) {
         // Client must exist
-        match &mut self.client {
-            Some(client) => {
-                // Host gets to choose if they want to be the killer or the victim
-                println!("Would you like to be the (K)iller, the (V)ictim, or (R)andomly choose?");
-                let player_type =
-                    match pick_char(&['K', 'V', 'R'], "Sorry, that isn't a valid option.") {
-                        'K' => PlayerType::Killer,
-                        'V' => PlayerType::Victim,
-                        'R' => {
-                            if rand::thread_rng().gen_range(0, 2) == 0 {
-                                PlayerType::Killer
-                            } else {
-                                PlayerType::Victim
-                            }
-                        }
-                        _ => panic!("Invalid option chosen!"),
-                    };
+        if let Some(client) = &mut self.client {
+            let mut player_type = settings.host_type.unwrap_or_else(|| {
+                if rand::thread_rng().gen_range(0, 2) == 0 {
+                    PlayerType::Killer
+                } else {
+                    PlayerType::Victim
+                }
+            });
+
+            // The client enforces the turn time too
+            write_over_tcp::<TurnTimerPacket>(client, &settings.turn_time);
+            let mut timer = TurnTimer::new(settings.turn_time);
+
+            // Keep letting new spectators in while we play
+            let _acceptor = match SpectatorAcceptor::start(
+                &self.listener,
+                self.spectators.clone(),
+                self.room_key.clone(),
+            ) {
+                Ok(acceptor) => Some(acceptor),
+                Err(_) => {
+                    writeln!(console, "{}", tr!("server.acceptor_failed"));
+                    None
+                }
+            };
+
+            // Keep playing until someone doesn't want a rematch
+            let mut score = SeriesScore::default();
+            let mut watching = 0;
+            loop {
+                // Randomize game state
+                self.state = GameState::new();
+                self.state.gen_state();
+                timer.reset();
 
                 // Tell the client what player type we are
                 write_over_tcp::<PlayerType>(client, &player_type);
 
                 // Generate packet to send to client that describes the game state
-                let mut state_packet = GameStatePacket::new();
-                for (i, section) in self.state.sections.iter().enumerate() {
-                    for (j, sub_section) in section.sub_sections.iter().enumerate() {
-                        if sub_section.part {
-                            state_packet.hidden_parts.push((i as u32, j as u32));
-                        }
-                    }
-                }
+                let state_packet = GameStatePacket::from_state(&self.state);
 
                 // Send client the game state
                 write_over_tcp::<GameStatePacket>(client, &state_packet);
 
+                // Let spectators know the game started
+                self.spectators.start_match(
+                    player_type,
+                    if settings.reveal_parts {
+                        Some(state_packet.hidden_parts.clone())
+                    } else {
+                        None
+                    },
+                );
+
+                // Record the game if the host wants to
+                let mut recorder = if settings.record {
+                    Some(Recorder::start(
+                        player_type,
+                        GameStatePacket::from_state(&self.state),
+                    ))
+                } else {
+                    None
+                };
+
                 // Game loop
-                while !net_play(player_type, &mut self.state, client) {}
+                let last_round = loop {
+                    // Tell the other player how many people are watching
+                    let count = self.spectators.count();
+                    write_over_tcp::<SpectatorCountPacket>(client, &(count as u32));
+                    print_spectator_count(console, &mut watching, count);
 
-                // Return to title screen
-                println!("Enter anything to return to the title screen...");
-                read_str();
-            }
+                    // Play a round and show it to the spectators
+                    let round = net_play(console, player_type, &mut self.state, client, &mut timer);
+                    self.spectators.broadcast_round(&round);
+                    if let Some(recorder) = &mut recorder {
+                        recorder.record_round(&round);
+                    }
+
+                    if round.is_finished() {
+                        break round;
+                    }
+                };
+
+                // Update the score and see if we're going again
+                score.record(player_type, &last_round);
+                score.print(console);
+
+                // Keep a copy of the recording, and offer one to the other player
+                let recording = recorder.map(Recorder::finish);
+                if let Some(recording) = &recording {
+                    match recording.save() {
+                        Ok(path) => {
+                            writeln!(
+                                console,
+                                "{}",
+                                tr!("server.recording_saved", path = path.display())
+                            )
+                        }
+                        Err(_) => writeln!(console, "{}", tr!("server.recording_save_failed")),
+                    }
+                }
+                if last_round.other_player_left {
+                    break;
+                }
+                let _ = try_write_over_tcp::<RecordingPacket>(client, &recording);
 
-            // No client loaded
-            None => {}
+                match vote_for_rematch(console, client) {
+                    Some(true) => player_type = player_type.opposite(),
+                    Some(false) => {}
+                    None => break,
+                }
+            }
         }
     }
 }
@@ -165,3 +380,78 @@ pub struct ServerError;
 /// Client connection error.
 #[derive(Debug)]
 pub struct ConnectionError;
+
+/// Testing for hosting.
+#[cfg(test)]
+mod test {
+    use super::super::client::*;
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    /// Wait until `text` has been written to `output`.
+    fn wait_for(output: &MemoryOutput, text: &str) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !output.text().contains(text) {
+            assert!(Instant::now() < deadline, "Never saw \"{}\"", text);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// A whole match played through the menus, with somebody watching.
+    #[test]
+    fn hosted_match() {
+        // Both players search the same spot twice, so the killer wins in two rounds
+        let (mut host_console, host_output) = Console::scripted(&[
+            "127.0.0.1:0",
+            "K",
+            "N",
+            "0",
+            "N",
+            "N",
+            "C",
+            "B",
+            "C",
+            "B",
+            "Q",
+            "",
+        ]);
+        let host = std::thread::spawn(move || Server::host_game(&mut host_console));
+
+        // The host says where to find them
+        wait_for(&host_output, "Waiting for client...");
+        let addr = host_output
+            .text()
+            .lines()
+            .skip_while(|line| !line.contains("Players can join you at:"))
+            .nth(1)
+            .unwrap()
+            .trim()
+            .to_string();
+
+        let (mut spectator_console, spectator_output) = Console::scripted(&["S", "E", &addr, ""]);
+        let spectator = std::thread::spawn(move || Client::join_game(&mut spectator_console));
+        wait_for(&host_output, "1 person is watching this game.");
+
+        let (mut client_console, client_output) =
+            Console::scripted(&["P", "E", &addr, "C", "B", "C", "B", "Q", ""]);
+        Client::join_game(&mut client_console);
+        host.join().unwrap();
+        spectator.join().unwrap();
+
+        let host_output = host_output.text();
+        assert!(host_output
+            .contains("Muahaha! You found the victim and were able to get a good swing in."));
+        assert!(host_output.contains("Muahahahaha!!! You slice the victim across their back"));
+        assert!(host_output.contains("Series score: You 1 - 0 Them"));
+
+        let client_output = client_output.text();
+        assert!(client_output.contains("You are the victim!"));
+        assert!(client_output.contains("Noooo!!! The killer slices your back and you fall dead..."));
+        assert!(client_output.contains("Series score: You 0 - 1 Them"));
+
+        let spectator_output = spectator_output.text();
+        assert!(spectator_output.contains("The host is the killer."));
+        assert!(spectator_output.contains("The killer caught the victim! The killer wins!"));
+        assert!(spectator_output.contains("The host has closed the game."));
+    }
+}
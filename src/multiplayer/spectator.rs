@@ -0,0 +1,522 @@
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use super::connection::*;
+use super::net_play::*;
+use super::packets::*;
+use super::security::*;
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::tr;
+
+/// How long the spectator acceptor sleeps between checks for new connections.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long sending to a spectator can take before they are dropped, so a stalled spectator
+/// can't hold up the game.
+const SPECTATOR_WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A list of spectators watching a hosted game.
+///
+/// Cloning the list gives another handle to the same spectators, so it can be shared with the
+/// thread accepting new spectators.
+#[derive(Clone, Default)]
+pub struct Spectators {
+    inner: Arc<Mutex<SpectatorList>>,
+}
+
+/// The data behind a `Spectators` handle.
+#[derive(Default)]
+struct SpectatorList {
+    /// Streams of every connected spectator.
+    streams: Vec<Connection>,
+
+    /// Packet describing the start of the match, so late spectators can catch up.
+    match_start: Option<SpectatorPacket>,
+
+    /// Number of rounds played so far.
+    round: u32,
+}
+
+impl Spectators {
+    /// Constructor.
+    pub fn new() -> Spectators {
+        Spectators::default()
+    }
+
+    /// Number of spectators currently watching.
+    pub fn count(&self) -> usize {
+        self.inner.lock().unwrap().streams.len()
+    }
+
+    /// Add a spectator who has already told us they want to watch.
+    pub fn add(&self, mut stream: Connection) {
+        if stream
+            .stream()
+            .set_write_timeout(Some(SPECTATOR_WRITE_TIMEOUT))
+            .is_err()
+        {
+            return;
+        }
+
+        let mut list = self.inner.lock().unwrap();
+
+        // Let the spectator know they got in
+        if try_write_over_tcp(&mut stream, &ConnectionResponse::Accepted).is_err() {
+            return;
+        }
+
+        // Catch them up if the game already started
+        if let Some(start) = &list.match_start {
+            if try_write_over_tcp(&mut stream, start).is_err() {
+                return;
+            }
+        }
+
+        list.streams.push(stream);
+    }
+
+    /// Tell every spectator the game has started.
+    pub fn start_match(&self, host: PlayerType, hidden_parts: Option<Vec<(u32, u32)>>) {
+        let packet = SpectatorPacket::MatchStart { host, hidden_parts };
+        let mut list = self.inner.lock().unwrap();
+        list.broadcast(&packet);
+        list.match_start = Some(packet);
+        list.round = 0;
+    }
+
+    /// Tell every spectator what happened during a round.
+    pub fn broadcast_round(&self, round: &NetRound) {
+        let mut list = self.inner.lock().unwrap();
+        list.round += 1;
+
+        // Forfeited rounds aren't played
+        if let Some(forfeiter) = round.forfeit {
+            list.broadcast(&SpectatorPacket::Forfeit(forfeiter));
+            return;
+        }
+
+        let packet = SpectatorPacket::Round {
+            round: list.round,
+            victim_move: MovePacket(round.victim_move.0 as u32, round.victim_move.1 as u32),
+            killer_move: MovePacket(round.killer_move.0 as u32, round.killer_move.1 as u32),
+            result: round.result,
+        };
+        list.broadcast(&packet);
+    }
+}
+
+impl SpectatorList {
+    /// Send a packet to every spectator, dropping the ones who have disconnected.
+    fn broadcast(&mut self, packet: &SpectatorPacket) {
+        self.streams
+            .retain_mut(|stream| try_write_over_tcp(stream, packet).is_ok());
+    }
+}
+
+/// Accepts spectators in the background while a game is being played.
+///
+/// The acceptor stops when it is dropped.
+pub struct SpectatorAcceptor {
+    /// Flag telling the acceptor thread to keep running.
+    running: Arc<AtomicBool>,
+
+    /// Acceptor thread.
+    thread: Option<JoinHandle<()>>,
+}
+
+impl SpectatorAcceptor {
+    /// Start accepting spectators on `listener`, adding them to `spectators`.
+    ///
+    /// Anyone trying to join as a player is told the game is full. Private games pass their
+    /// `room_key` so spectators need the password too.
+    pub fn start(
+        listener: &TcpListener,
+        spectators: Spectators,
+        room_key: Option<RoomKey>,
+    ) -> std::io::Result<SpectatorAcceptor> {
+        let listener = listener.try_clone()?;
+        listener.set_nonblocking(true)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+
+        let thread = std::thread::spawn(move || {
+            while thread_running.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _addr)) => match read_connection_type(stream, room_key.as_ref()) {
+                        Some((conn, ConnectionType::Spectator)) => spectators.add(conn),
+                        Some((mut conn, ConnectionType::Player)) => {
+                            let _ = try_write_over_tcp(&mut conn, &ConnectionResponse::GameFull);
+                        }
+                        None => {}
+                    },
+
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                    }
+
+                    Err(_) => {}
+                }
+            }
+
+            // The listener is shared with the server, so put it back the way we found it
+            let _ = listener.set_nonblocking(false);
+        });
+
+        Ok(SpectatorAcceptor {
+            running,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for SpectatorAcceptor {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Finish setting up a freshly accepted stream and read what it wants to do.
+///
+/// Private games pass their `room_key`. Returns `None` if the connection misbehaved, didn't know
+/// the password, or took too long to answer.
+pub fn read_connection_type(
+    stream: TcpStream,
+    room_key: Option<&RoomKey>,
+) -> Option<(Connection, ConnectionType)> {
+    let mut conn = Connection::accept(stream, room_key).ok()?;
+    conn.stream()
+        .set_read_timeout(Some(HANDSHAKE_TIMEOUT))
+        .ok()?;
+    let connection_type = try_read_over_tcp::<ConnectionType>(&mut conn).ok();
+    conn.stream().set_read_timeout(None).ok()?;
+    Some((conn, connection_type?))
+}
+
+/// Let a player know how many people are watching, if it changed since we last told them.
+///
+/// `last_count` is the number we told them last time, and is updated to `count`.
+pub fn print_spectator_count(console: &mut Console, last_count: &mut usize, count: usize) {
+    if *last_count == count {
+        return;
+    }
+
+    match count {
+        0 => writeln!(console, "{}", tr!("spectators.none")),
+        1 => writeln!(console, "{}", tr!("spectators.one")),
+        _ => writeln!(console, "{}", tr!("spectators.many", count = count)),
+    }
+
+    *last_count = count;
+}
+
+/// Watch a game being played on the host at the other end of `stream`.
+pub fn spectate(console: &mut Console, stream: &mut Connection) {
+    // Tell the host we only want to watch
+    write_over_tcp(stream, &ConnectionType::Spectator);
+    match try_read_over_tcp::<ConnectionResponse>(stream) {
+        Ok(ConnectionResponse::Accepted) => {}
+
+        Ok(ConnectionResponse::Lobby) => {
+            writeln!(console, "{}", tr!("spectate.lobby"));
+            return;
+        }
+
+        _ => {
+            writeln!(console, "{}", tr!("spectate.refused"));
+            return;
+        }
+    }
+
+    writeln!(console, "{}", tr!("spectate.started"));
+
+    let mut view = SpectatorView::new();
+    loop {
+        match try_read_over_tcp::<SpectatorPacket>(stream) {
+            // Anything that isn't somewhere in Camp Misty means the host is misbehaving
+            Ok(packet) if !view.is_valid(&packet) => {
+                writeln!(console, "{}", tr!("spectate.bad_data"));
+                break;
+            }
+
+            Ok(packet) => {
+                // The players might go for a rematch
+                if view.show(console, &packet) {
+                    writeln!(console, "{}", tr!("spectate.waiting_for_rematch"));
+                }
+            }
+
+            Err(_) => {
+                if view.game_over {
+                    writeln!(console, "{}", tr!("spectate.host_closed"));
+                } else {
+                    writeln!(console, "{}", tr!("spectate.lost_connection"));
+                }
+                break;
+            }
+        }
+    }
+}
+
+/// Shows spectators what is happening in a game.
+pub struct SpectatorView {
+    /// Our own copy of the game state, so we can name the places being searched.
+    state: GameState,
+
+    /// Number of car parts found so far.
+    parts_found: usize,
+
+    /// Flag indicating the game is over.
+    pub game_over: bool,
+}
+
+impl Default for SpectatorView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpectatorView {
+    /// Constructor.
+    pub fn new() -> SpectatorView {
+        SpectatorView {
+            state: GameState::new(),
+            parts_found: 0,
+            game_over: false,
+        }
+    }
+
+    /// Check every place in `packet` is somewhere in Camp Misty, so it can be shown.
+    pub fn is_valid(&self, packet: &SpectatorPacket) -> bool {
+        let state = &self.state;
+        let is_spot = |spot: (u32, u32)| state.is_valid_move((spot.0 as usize, spot.1 as usize));
+        let is_section = |section: usize| section < state.sections.len();
+
+        match packet {
+            SpectatorPacket::MatchStart { hidden_parts, .. } => {
+                hidden_parts.iter().flatten().all(|&part| is_spot(part))
+            }
+            SpectatorPacket::Round {
+                victim_move,
+                killer_move,
+                result,
+                ..
+            } => {
+                is_spot((victim_move.0, victim_move.1))
+                    && is_spot((killer_move.0, killer_move.1))
+                    && result.part_section_index.is_none_or(is_section)
+                    && match result.result {
+                        RoundResult::ChaseBegins(section) => is_section(section),
+                        _ => true,
+                    }
+            }
+            SpectatorPacket::Forfeit(_) => true,
+        }
+    }
+
+    /// Print out what a packet sent to spectators says happened.
+    ///
+    /// The packet must be valid, as checked by `is_valid`. Returns 'true' if the packet ended the
+    /// game.
+    pub fn show(&mut self, console: &mut Console, packet: &SpectatorPacket) -> bool {
+        let state = &self.state;
+        let part_count = state.sections.len();
+
+        match packet {
+            SpectatorPacket::MatchStart { host, hidden_parts } => {
+                self.parts_found = 0;
+                self.game_over = false;
+
+                writeln!(
+                    console,
+                    "\n================ {} ================",
+                    tr!("spectate.title")
+                );
+                match host {
+                    PlayerType::Killer => writeln!(console, "{}", tr!("spectate.host_is_killer")),
+                    PlayerType::Victim => writeln!(console, "{}", tr!("spectate.host_is_victim")),
+                }
+
+                // Reveal the car parts if we are allowed to know where they are
+                if let Some(hidden_parts) = hidden_parts {
+                    writeln!(console, "{}", tr!("spectate.parts_hidden_in"));
+                    for part in hidden_parts {
+                        writeln!(
+                            console,
+                            "    {}",
+                            tr!(
+                                "spectate.hidden_part",
+                                spot = state.sections[part.0 as usize].sub_sections
+                                    [part.1 as usize]
+                                    .name,
+                                section = state.sections[part.0 as usize].name
+                            )
+                        );
+                    }
+                }
+
+                false
+            }
+
+            SpectatorPacket::Round {
+                round,
+                victim_move,
+                killer_move,
+                result,
+            } => {
+                let spot_name = |m: &MovePacket| {
+                    tr!(
+                        "spectate.spot",
+                        spot = state.sections[m.0 as usize].sub_sections[m.1 as usize].name,
+                        section = state.sections[m.0 as usize].name
+                    )
+                };
+
+                writeln!(
+                    console,
+                    "\n----------------- {} -----------------",
+                    tr!("spectate.round", round = round)
+                );
+                writeln!(
+                    console,
+                    "{}",
+                    tr!("spectate.victim_searched", spot = spot_name(victim_move))
+                );
+                writeln!(
+                    console,
+                    "{}",
+                    tr!("spectate.killer_searched", spot = spot_name(killer_move))
+                );
+
+                if let Some(section) = result.part_section_index {
+                    self.parts_found += 1;
+                    writeln!(
+                        console,
+                        "{}",
+                        tr!(
+                            "spectate.part_found",
+                            section = state.sections[section].name,
+                            count = self.parts_found,
+                            total = part_count
+                        )
+                    );
+                }
+
+                match result.result {
+                    RoundResult::Nothing => writeln!(console, "{}", tr!("spectate.nothing")),
+                    RoundResult::ChaseBegins(section) => writeln!(
+                        console,
+                        "{}",
+                        tr!(
+                            "spectate.chase_begins",
+                            section = state.sections[section].name
+                        )
+                    ),
+                    RoundResult::Evaded => writeln!(console, "{}", tr!("spectate.evaded")),
+                    RoundResult::Wounded => writeln!(console, "{}", tr!("spectate.wounded")),
+                    RoundResult::TrapTriggered => {
+                        writeln!(console, "{}", tr!("spectate.trap_triggered"))
+                    }
+                    RoundResult::Caught => {
+                        writeln!(console, "{}", tr!("spectate.caught"));
+                        self.game_over = true;
+                    }
+                    RoundResult::AllPartsFound => {
+                        writeln!(console, "{}", tr!("spectate.all_parts_found"));
+                        self.game_over = true;
+                    }
+                }
+
+                self.game_over
+            }
+
+            SpectatorPacket::Forfeit(forfeiter) => {
+                match forfeiter {
+                    PlayerType::Killer => {
+                        writeln!(console, "\n{}", tr!("spectate.killer_forfeits"))
+                    }
+                    PlayerType::Victim => {
+                        writeln!(console, "\n{}", tr!("spectate.victim_forfeits"))
+                    }
+                }
+
+                self.game_over = true;
+                true
+            }
+        }
+    }
+}
+
+/// Testing for spectators.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Spectators are shown each round as it is played, and hosts sending nonsense are left.
+    #[test]
+    fn spectating() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let host = std::thread::spawn(move || {
+            // Play a round in front of the spectator
+            let (stream, _) = listener.accept().unwrap();
+            let (conn, connection_type) = read_connection_type(stream, None).unwrap();
+            assert!(connection_type == ConnectionType::Spectator);
+            let spectators = Spectators::new();
+            spectators.add(conn);
+            assert_eq!(spectators.count(), 1);
+            spectators.start_match(PlayerType::Killer, Some(vec![(0, 0)]));
+            spectators.broadcast_round(&NetRound {
+                victim_move: (0, 0),
+                killer_move: (0, 1),
+                result: PlayResult::new(RoundResult::ChaseBegins(0), Some(0)),
+                forfeit: None,
+                other_player_left: false,
+            });
+            drop(spectators);
+
+            // Send a spot that doesn't exist
+            let (stream, _) = listener.accept().unwrap();
+            let (mut conn, _) = read_connection_type(stream, None).unwrap();
+            try_write_over_tcp(&mut conn, &ConnectionResponse::Accepted).unwrap();
+            let packet = SpectatorPacket::Round {
+                round: 1,
+                victim_move: MovePacket(9, 0),
+                killer_move: MovePacket(0, 0),
+                result: PlayResult::new(RoundResult::Nothing, None),
+            };
+            try_write_over_tcp(&mut conn, &packet).unwrap();
+
+            // Wait for the spectator to leave
+            let _ = try_read_over_tcp::<ConnectionType>(&mut conn);
+        });
+
+        let (mut console, output) = Console::scripted(&[]);
+        let mut stream = Connection::connect(&addr, None).unwrap();
+        spectate(&mut console, &mut stream);
+        let text = output.text();
+        assert!(text.contains("The host is the killer."));
+        assert!(text.contains("The (B)edroom in the (C)abin"));
+        assert!(text.contains("The killer searched the (K)itchen in the (C)abin."));
+        assert!(text.contains("The victim found a car part in the (C)abin! (1 of 5)"));
+        assert!(text.contains("A chase begins!"));
+        assert!(text.contains("Lost connection to the host."));
+
+        let (mut console, output) = Console::scripted(&[]);
+        let mut stream = Connection::connect(&addr, None).unwrap();
+        spectate(&mut console, &mut stream);
+        drop(stream);
+        assert!(output
+            .text()
+            .contains("The host sent something that makes no sense"));
+        host.join().unwrap();
+    }
+}
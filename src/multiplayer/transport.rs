@@ -0,0 +1,327 @@
+use std::io::prelude::*;
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use tungstenite::protocol::WebSocketConfig;
+use tungstenite::{Message, WebSocket};
+
+use super::connection::*;
+
+/// Page served to browsers so they can join a game.
+const WEB_CLIENT: &str = include_str!("web/index.html");
+
+/// Largest HTTP request we look at before giving up on it, in bytes.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// How long to wait between checks for more of a request.
+const REQUEST_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A way of sending frames to and receiving frames from the other end of a connection.
+pub trait Transport: Send {
+    /// Send a frame containing `payload`.
+    fn send_frame(&mut self, payload: &[u8], max_frame_size: usize) -> std::io::Result<()>;
+
+    /// Receive a frame, returning its payload.
+    fn recv_frame(&mut self, max_frame_size: usize) -> std::io::Result<Vec<u8>>;
+
+    /// Check if there is a frame waiting to be received without blocking.
+    ///
+    /// A closed connection counts as having data, so the next receive reports the error.
+    fn has_data(&mut self) -> std::io::Result<bool>;
+
+    /// TCP stream underneath the transport.
+    fn stream(&self) -> &TcpStream;
+}
+
+/// Sends frames over plain TCP with a length prefix.
+pub struct TcpTransport(pub TcpStream);
+
+impl Transport for TcpTransport {
+    fn send_frame(&mut self, payload: &[u8], max_frame_size: usize) -> std::io::Result<()> {
+        write_frame(&mut self.0, payload, max_frame_size)
+    }
+
+    fn recv_frame(&mut self, max_frame_size: usize) -> std::io::Result<Vec<u8>> {
+        read_frame(&mut self.0, max_frame_size)
+    }
+
+    fn has_data(&mut self) -> std::io::Result<bool> {
+        self.0.set_nonblocking(true)?;
+
+        let mut buf = [0u8; 1];
+        let res = match self.0.peek(&mut buf) {
+            Ok(_) => Ok(true),
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        };
+
+        self.0.set_nonblocking(false)?;
+        res
+    }
+
+    fn stream(&self) -> &TcpStream {
+        &self.0
+    }
+}
+
+/// Sends frames as WebSocket messages, so browsers can connect.
+pub struct WebSocketTransport {
+    /// WebSocket to the other end.
+    socket: WebSocket<TcpStream>,
+
+    /// Frame read while checking for data, waiting to be received.
+    pending: Option<Vec<u8>>,
+}
+
+impl WebSocketTransport {
+    /// Wrap a WebSocket that has finished its handshake.
+    pub fn new(socket: WebSocket<TcpStream>) -> WebSocketTransport {
+        WebSocketTransport {
+            socket,
+            pending: None,
+        }
+    }
+
+    /// Read the next message that carries a frame, skipping pings and pongs.
+    fn read_payload(&mut self) -> std::io::Result<Vec<u8>> {
+        loop {
+            match self.socket.read().map_err(websocket_error)? {
+                Message::Text(text) => return Ok(text.into_bytes()),
+                Message::Binary(bytes) => return Ok(bytes),
+                Message::Close(_) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionAborted,
+                        "the other end closed the connection",
+                    ))
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn send_frame(&mut self, payload: &[u8], max_frame_size: usize) -> std::io::Result<()> {
+        if payload.len() > max_frame_size {
+            return Err(invalid_data("frame is too large"));
+        }
+
+        // Browsers find text messages easier to deal with, so JSON is sent as text
+        let message = match String::from_utf8(payload.to_vec()) {
+            Ok(text) => Message::Text(text),
+            Err(e) => Message::Binary(e.into_bytes()),
+        };
+        self.socket.send(message).map_err(websocket_error)
+    }
+
+    fn recv_frame(&mut self, max_frame_size: usize) -> std::io::Result<Vec<u8>> {
+        let payload = match self.pending.take() {
+            Some(payload) => payload,
+            None => self.read_payload()?,
+        };
+
+        if payload.len() > max_frame_size {
+            return Err(invalid_data("frame is too large"));
+        }
+
+        Ok(payload)
+    }
+
+    fn has_data(&mut self) -> std::io::Result<bool> {
+        if self.pending.is_some() {
+            return Ok(true);
+        }
+
+        // Messages can arrive in pieces, so try to read a whole one without blocking
+        self.socket.get_ref().set_nonblocking(true)?;
+        let res = match self.read_payload() {
+            Ok(payload) => {
+                self.pending = Some(payload);
+                Ok(true)
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(false),
+            Err(e) => Err(e),
+        };
+
+        self.socket.get_ref().set_nonblocking(false)?;
+        res
+    }
+
+    fn stream(&self) -> &TcpStream {
+        self.socket.get_ref()
+    }
+}
+
+/// Figure out what kind of transport a freshly accepted `stream` is using.
+///
+/// Browsers asking for a page are sent the web client, and an error is returned since there is
+/// no connection to use afterwards.
+pub fn accept_transport(stream: TcpStream) -> std::io::Result<Box<dyn Transport>> {
+    // Frames start with their size, which never looks like the start of an HTTP request
+    let start = peek_until(&stream, |bytes| bytes.len() >= 4)?;
+    if !start.starts_with(b"GET ") {
+        return Ok(Box::new(TcpTransport(stream)));
+    }
+
+    let request = peek_until(&stream, |bytes| {
+        bytes.windows(4).any(|window| window == b"\r\n\r\n")
+    })?;
+    let request_len = request.len();
+    let request = String::from_utf8_lossy(&request).to_lowercase();
+    let is_upgrade = request
+        .lines()
+        .any(|line| line.starts_with("upgrade:") && line.contains("websocket"));
+
+    if is_upgrade {
+        let config = WebSocketConfig {
+            max_message_size: Some(DEFAULT_MAX_FRAME_SIZE),
+            max_frame_size: Some(DEFAULT_MAX_FRAME_SIZE),
+            ..Default::default()
+        };
+
+        let socket = tungstenite::accept_with_config(stream, Some(config))
+            .map_err(|_| invalid_data("bad WebSocket handshake"))?;
+        Ok(Box::new(WebSocketTransport::new(socket)))
+    } else {
+        serve_web_client(stream, &request, request_len)?;
+        Err(std::io::Error::other("served the web client"))
+    }
+}
+
+/// Answer a browsers `request`, which is `request_len` bytes long, with the web client.
+fn serve_web_client(
+    mut stream: TcpStream,
+    request: &str,
+    request_len: usize,
+) -> std::io::Result<()> {
+    // Throw away the request, since we only peeked at it
+    let mut buf = vec![0; request_len];
+    stream.read_exact(&mut buf)?;
+
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+    let response = if path == "/" || path == "/index.html" {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            WEB_CLIENT.len(),
+            WEB_CLIENT
+        )
+    } else {
+        String::from("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+    };
+
+    stream.write_all(response.as_bytes())
+}
+
+/// Peek at the start of a stream until `done` is happy with what has arrived.
+///
+/// Nothing is read from the stream, so whoever uses it next sees everything.
+fn peek_until<F>(stream: &TcpStream, done: F) -> std::io::Result<Vec<u8>>
+where
+    F: Fn(&[u8]) -> bool,
+{
+    let give_up = Instant::now() + HANDSHAKE_TIMEOUT;
+    let mut buf = vec![0; MAX_REQUEST_SIZE];
+    loop {
+        let len = stream.peek(&mut buf)?;
+        if len == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "the other end closed the connection",
+            ));
+        }
+
+        if done(&buf[..len]) {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+
+        if len == buf.len() || Instant::now() >= give_up {
+            return Err(invalid_data("request is too large or too slow"));
+        }
+
+        std::thread::sleep(REQUEST_POLL_INTERVAL);
+    }
+}
+
+/// Turn a WebSocket error into an IO error.
+fn websocket_error(e: tungstenite::Error) -> std::io::Error {
+    match e {
+        tungstenite::Error::Io(e) => e,
+        e => std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// Testing for transports.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::multiplayer::codec::*;
+    use crate::multiplayer::packets::*;
+    use crate::multiplayer::spectator::*;
+    use std::net::TcpListener;
+
+    /// A WebSocket client can join a host and swap JSON messages with it.
+    #[test]
+    fn websocket_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let host = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let (mut conn, connection_type) = read_connection_type(stream, None).unwrap();
+            assert!(connection_type == ConnectionType::Player);
+            assert_eq!(conn.codec(), CodecKind::Json);
+
+            conn.send(&ConnectionResponse::Accepted).unwrap();
+            while !conn.has_data().unwrap() {
+                std::thread::sleep(REQUEST_POLL_INTERVAL);
+            }
+            match conn.recv::<RoundMessage>().unwrap() {
+                RoundMessage::Chat(msg) => assert_eq!(msg, "hello from the browser"),
+                _ => panic!("Expected a chat message!"),
+            }
+        });
+
+        // Talk to the host the same way the web client does
+        let stream = TcpStream::connect(addr).unwrap();
+        let (mut socket, _) = tungstenite::client(format!("ws://{}/", addr), stream).unwrap();
+        let mut send = |text: &str| socket.send(Message::Text(String::from(text))).unwrap();
+        send("[\"Json\"]");
+        send("\"Player\"");
+        send("{\"Chat\":\"hello from the browser\"}");
+
+        assert_eq!(
+            socket.read().unwrap(),
+            Message::Text(String::from("\"Json\""))
+        );
+        assert_eq!(socket.read().unwrap(), Message::Text(String::from("null")));
+        assert_eq!(
+            socket.read().unwrap(),
+            Message::Text(String::from("\"Accepted\""))
+        );
+
+        host.join().unwrap();
+    }
+
+    /// Browsers asking for a page get the web client.
+    #[test]
+    fn serves_web_client() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let host = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            assert!(read_connection_type(stream, None).is_none());
+        });
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains(WEB_CLIENT));
+        host.join().unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+use std::time::{Duration, Instant};
+
+use super::packets::*;
+use crate::console::*;
+use crate::tr;
+use crate::util::*;
+
+/// Number of times a player can run out of time in a game before they forfeit.
+pub const MAX_TIMEOUTS: u32 = 3;
+
+/// Extra time given to the other player before we assume they have left.
+///
+/// Their own timer should always make a move for them first, so this only covers players whose
+/// game has stopped responding.
+pub const LEAVE_GRACE_PERIOD: Duration = Duration::from_secs(15);
+
+/// A per-turn time limit, along with how many times each player has run out of time this game.
+pub struct TurnTimer {
+    /// Time each player gets per turn, or `None` if there is no limit.
+    turn_time: Option<Duration>,
+
+    /// Number of turns the killer ran out of time on.
+    killer_timeouts: u32,
+
+    /// Number of turns the victim ran out of time on.
+    victim_timeouts: u32,
+}
+
+impl TurnTimer {
+    /// Constructor.
+    ///
+    /// The only argument is the number of seconds each player gets per turn, where 0 means there
+    /// is no limit.
+    pub fn new(seconds: TurnTimerPacket) -> TurnTimer {
+        TurnTimer {
+            turn_time: if seconds == 0 {
+                None
+            } else {
+                Some(Duration::from_secs(seconds as u64))
+            },
+            killer_timeouts: 0,
+            victim_timeouts: 0,
+        }
+    }
+
+    /// Reset the timeout counts for a new game.
+    pub fn reset(&mut self) {
+        self.killer_timeouts = 0;
+        self.victim_timeouts = 0;
+    }
+
+    /// Deadline for a turn starting now, if there is a time limit.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.turn_time.map(|time| Instant::now() + time)
+    }
+
+    /// Deadline after which we assume the other player has left, if there is a time limit.
+    pub fn leave_deadline(&self) -> Option<Instant> {
+        self.turn_time
+            .map(|time| Instant::now() + time + LEAVE_GRACE_PERIOD)
+    }
+
+    /// Record that `player` ran out of time, returning how many times they have now.
+    pub fn record_timeout(&mut self, player: PlayerType) -> u32 {
+        let timeouts = match player {
+            PlayerType::Killer => &mut self.killer_timeouts,
+            PlayerType::Victim => &mut self.victim_timeouts,
+        };
+
+        *timeouts += 1;
+        *timeouts
+    }
+
+    /// Get the player who has run out of time too many times and forfeits, if there is one.
+    pub fn forfeiter(&self) -> Option<PlayerType> {
+        if self.victim_timeouts >= MAX_TIMEOUTS {
+            Some(PlayerType::Victim)
+        } else if self.killer_timeouts >= MAX_TIMEOUTS {
+            Some(PlayerType::Killer)
+        } else {
+            None
+        }
+    }
+}
+
+/// Ask the user how many seconds each player should get per turn, where 0 means no limit.
+pub fn ask_turn_time(console: &mut Console) -> TurnTimerPacket {
+    writeln!(console, "{}", tr!("menu.ask_turn_time"));
+    loop {
+        match read_str(console).parse::<TurnTimerPacket>() {
+            Ok(seconds) => return seconds,
+            Err(_) => writeln!(console, "{}", tr!("error.not_understood")),
+        }
+    }
+}
+
+/// Testing for turn timers.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Each player forfeits on their own `MAX_TIMEOUTS`th timeout, until the timer is reset.
+    #[test]
+    fn timeouts() {
+        let mut timer = TurnTimer::new(10);
+        for i in 1..MAX_TIMEOUTS {
+            assert_eq!(timer.record_timeout(PlayerType::Killer), i);
+            assert_eq!(timer.record_timeout(PlayerType::Victim), i);
+            assert_eq!(timer.forfeiter(), None);
+        }
+
+        assert_eq!(timer.record_timeout(PlayerType::Killer), MAX_TIMEOUTS);
+        assert_eq!(timer.forfeiter(), Some(PlayerType::Killer));
+
+        timer.reset();
+        assert_eq!(timer.forfeiter(), None);
+        assert_eq!(timer.record_timeout(PlayerType::Victim), 1);
+    }
+
+    /// Players are given the turn time and a grace period before they're assumed to have left.
+    #[test]
+    fn deadlines() {
+        assert_eq!(TurnTimer::new(0).deadline(), None);
+        assert_eq!(TurnTimer::new(0).leave_deadline(), None);
+
+        let before = Instant::now();
+        let timer = TurnTimer::new(10);
+        let leave_deadline = timer.leave_deadline().unwrap();
+        let after = Instant::now();
+
+        let wait = Duration::from_secs(10) + LEAVE_GRACE_PERIOD;
+        assert!(leave_deadline >= before + wait && leave_deadline <= after + wait);
+        assert!(timer.deadline().unwrap() < leave_deadline);
+    }
+}
@@ -0,0 +1,573 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::achievements::*;
+use crate::console::*;
+use crate::game::difficulty::*;
+use crate::game::game_state::*;
+use crate::multiplayer::packets::*;
+use crate::settings::*;
+use crate::tr;
+use crate::util::*;
+
+/// File the profile is saved in.
+pub const PROFILE_FILE: &str = "profile.json";
+
+/// File the match history is exported to as JSON.
+pub const EXPORT_JSON_FILE: &str = "stats.json";
+
+/// File the match history is exported to as CSV.
+pub const EXPORT_CSV_FILE: &str = "stats.csv";
+
+/// Who a match was played against.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Opponent {
+    /// The AI, playing at a difficulty.
+    Ai(Difficulty),
+
+    /// Another player over the network.
+    Player,
+}
+
+/// A finished match.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MatchRecord {
+    /// When the match finished, in milliseconds since the Unix epoch.
+    pub finished_at: u64,
+
+    /// Player type we played as.
+    pub role: PlayerType,
+
+    /// Who we played against.
+    pub opponent: Opponent,
+
+    /// Flag indicating we won.
+    pub won: bool,
+
+    /// Number of rounds played.
+    pub rounds: usize,
+
+    /// Number of times the killer struck the victim.
+    pub wounds: usize,
+
+    /// Number of car parts the victim found.
+    pub parts_found: usize,
+
+    /// Section and sub-section of every move we made, in order.
+    pub moves: Vec<(usize, usize)>,
+
+    /// What happened in every round, in order.
+    #[serde(default)]
+    pub results: Vec<PlayResult>,
+}
+
+/// Everything remembered about the user's games, kept between runs.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Profile {
+    /// Every finished match, oldest first.
+    pub matches: Vec<MatchRecord>,
+
+    /// Identifiers of the achievements that have been unlocked.
+    pub achievements: Vec<String>,
+}
+
+impl MatchRecord {
+    /// Create a record of the finished match in `state`, played as `role` against `opponent`.
+    pub fn new(state: &GameState, role: PlayerType, opponent: Opponent, won: bool) -> MatchRecord {
+        MatchRecord {
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_millis() as u64)
+                .unwrap_or(0),
+            role,
+            opponent,
+            won,
+            rounds: state.history.len(),
+            wounds: state
+                .history
+                .iter()
+                .filter(|round| {
+                    matches!(
+                        round.result.result,
+                        RoundResult::Wounded | RoundResult::Caught
+                    )
+                })
+                .count(),
+            parts_found: state
+                .history
+                .iter()
+                .filter(|round| round.result.part_section_index.is_some())
+                .count(),
+            moves: state
+                .history
+                .iter()
+                .map(|round| match role {
+                    PlayerType::Victim => round.victim_move,
+                    PlayerType::Killer => round.killer_move,
+                })
+                .collect(),
+            results: state.history.iter().map(|round| round.result).collect(),
+        }
+    }
+}
+
+impl Profile {
+    /// Load the profile saved at `path`, or an empty one if there isn't one.
+    ///
+    /// Returns an error if there is a profile, but it couldn't be read.
+    pub fn load(path: &Path) -> std::io::Result<Profile> {
+        match std::fs::read(path) {
+            Ok(json) => Ok(serde_json::from_slice(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Profile::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Save the profile at `path`.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Get the number of matches won and played out of those `matching`.
+    pub fn record(&self, matching: impl Fn(&MatchRecord) -> bool) -> (usize, usize) {
+        let played: Vec<&MatchRecord> = self.matches.iter().filter(|m| matching(m)).collect();
+        (played.iter().filter(|m| m.won).count(), played.len())
+    }
+
+    /// Get the current streak and the longest winning streak.
+    ///
+    /// The current streak is positive for wins in a row and negative for losses in a row.
+    pub fn streaks(&self) -> (i64, usize) {
+        let mut current: i64 = 0;
+        let mut best = 0;
+        for m in &self.matches {
+            current = match (m.won, current) {
+                (true, c) if c > 0 => c + 1,
+                (true, _) => 1,
+                (false, c) if c < 0 => c - 1,
+                (false, _) => -1,
+            };
+            best = best.max(current.max(0) as usize);
+        }
+        (current, best)
+    }
+
+    /// Get how many times each section was searched, most searched first.
+    pub fn section_counts(&self) -> Vec<(usize, usize)> {
+        let mut counts = vec![0; MAP.len()];
+        for m in &self.matches {
+            for spot in &m.moves {
+                if let Some(count) = counts.get_mut(spot.0) {
+                    *count += 1;
+                }
+            }
+        }
+
+        let mut counts: Vec<(usize, usize)> = counts.into_iter().enumerate().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// Get how many times each spot was searched, most searched first.
+    pub fn spot_counts(&self) -> Vec<((usize, usize), usize)> {
+        let mut counts: Vec<((usize, usize), usize)> = Vec::new();
+        for spot in self.matches.iter().flat_map(|m| &m.moves) {
+            match counts.iter_mut().find(|(s, _)| s == spot) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((*spot, 1)),
+            }
+        }
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// Write the match history to `path` as JSON.
+    pub fn export_json(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(&self.matches)?)
+    }
+
+    /// Write the match history to `path` as CSV.
+    pub fn export_csv(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_csv())
+    }
+
+    /// Get the match history as CSV, with moves given by their letters.
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            String::from("finished_at,role,opponent,won,rounds,wounds,parts_found,moves\n");
+        for m in &self.matches {
+            let opponent = match m.opponent {
+                Opponent::Ai(difficulty) => format!("{:?} AI", difficulty),
+                Opponent::Player => String::from("Player"),
+            };
+            let moves: Vec<String> = m
+                .moves
+                .iter()
+                .filter_map(|(section, sub_section)| {
+                    let (_, letter, sub_sections) = MAP.get(*section)?;
+                    let (_, sub_letter) = sub_sections.get(*sub_section)?;
+                    Some(format!("{}{}", letter, sub_letter))
+                })
+                .collect();
+            csv.push_str(&format!(
+                "{},{:?},{},{},{},{},{},{}\n",
+                m.finished_at,
+                m.role,
+                opponent,
+                m.won,
+                m.rounds,
+                m.wounds,
+                m.parts_found,
+                moves.join(" ")
+            ));
+        }
+        csv
+    }
+}
+
+/// Get where the user's profile is kept by default.
+pub fn default_profile_path() -> PathBuf {
+    data_dir().join(PROFILE_FILE)
+}
+
+/// Get where a profile at `path` is moved to when it can't be read.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Load the profile of the user at `console`, or an empty one if they don't have one.
+///
+/// A profile that can't be read is left alone, and the user is told an empty one is used instead.
+pub fn user_profile(console: &mut Console) -> Profile {
+    let path = match console.profile_path() {
+        Some(path) => path.to_path_buf(),
+        None => return Profile::default(),
+    };
+
+    Profile::load(&path).unwrap_or_else(|_| {
+        writeln!(
+            console,
+            "{}",
+            tr!("stats.profile_unreadable", path = path.display())
+        );
+        Profile::default()
+    })
+}
+
+/// Remember the finished match in `state`, played as `role` against `opponent`.
+///
+/// Announces any achievements the match unlocked. A profile that can't be read is moved out of the
+/// way first, so the match history in it isn't written over.
+pub fn record_match(
+    console: &mut Console,
+    state: &GameState,
+    role: PlayerType,
+    opponent: Opponent,
+    won: bool,
+) {
+    let path = console.profile_path().map(Path::to_path_buf);
+    let mut profile = Profile::default();
+    let mut can_save = path.is_some();
+    if let Some(path) = &path {
+        match Profile::load(path) {
+            Ok(loaded) => profile = loaded,
+            Err(_) => {
+                let backup = backup_path(path);
+                if std::fs::rename(path, &backup).is_ok() {
+                    writeln!(
+                        console,
+                        "{}",
+                        tr!("stats.profile_backed_up", path = backup.display())
+                    );
+                } else {
+                    writeln!(
+                        console,
+                        "{}",
+                        tr!("stats.profile_unreadable", path = path.display())
+                    );
+                    can_save = false;
+                }
+            }
+        }
+    }
+
+    profile
+        .matches
+        .push(MatchRecord::new(state, role, opponent, won));
+    let unlocked = unlock_achievements(&mut profile);
+    if let Some(path) = path.filter(|_| can_save) {
+        if profile.save(&path).is_err() {
+            writeln!(console, "{}", tr!("stats.save_failed"));
+        }
+    }
+    announce_achievements(console, &unlocked);
+}
+
+/// Describe a number of wins out of a number of matches played.
+fn win_rate((won, played): (usize, usize)) -> String {
+    if played == 0 {
+        String::from(tr!("stats.no_games"))
+    } else {
+        tr!(
+            "stats.win_rate",
+            won = won,
+            played = played,
+            percent = 100 * won / played
+        )
+    }
+}
+
+/// Print the user's stats.
+pub fn print_stats(console: &mut Console, profile: &Profile) {
+    writeln!(console, "{}", tr!("stats.title"));
+    if profile.matches.is_empty() {
+        writeln!(console, "{}", tr!("stats.empty"));
+        return;
+    }
+
+    writeln!(
+        console,
+        "{}",
+        tr!("stats.overall", record = win_rate(profile.record(|_| true)))
+    );
+
+    // Win rates for each role against each kind of opponent
+    let opponents = [
+        (Opponent::Ai(Difficulty::Easy), tr!("stats.easy")),
+        (Opponent::Ai(Difficulty::Normal), tr!("stats.normal")),
+        (Opponent::Ai(Difficulty::Hard), tr!("stats.hard")),
+        (Opponent::Player, tr!("stats.players")),
+    ];
+    for (role, name) in [
+        (PlayerType::Victim, tr!("stats.as_victim")),
+        (PlayerType::Killer, tr!("stats.as_killer")),
+    ] {
+        writeln!(
+            console,
+            "\n{}: {}",
+            name,
+            win_rate(profile.record(|m| m.role == role))
+        );
+        for (opponent, opponent_name) in &opponents {
+            let record = profile.record(|m| m.role == role && m.opponent == *opponent);
+            if record.1 > 0 {
+                writeln!(console, "    {}: {}", opponent_name, win_rate(record));
+            }
+        }
+    }
+
+    // Streaks
+    let (current, best) = profile.streaks();
+    writeln!(console);
+    if current > 0 {
+        writeln!(console, "{}", tr!("stats.winning_streak", count = current));
+    } else {
+        writeln!(console, "{}", tr!("stats.losing_streak", count = -current));
+    }
+    writeln!(console, "{}", tr!("stats.best_streak", count = best));
+
+    // Averages
+    let games = profile.matches.len();
+    let rounds: usize = profile.matches.iter().map(|m| m.rounds).sum();
+    writeln!(
+        console,
+        "{}",
+        tr!(
+            "stats.average_rounds",
+            rounds = format!("{:.1}", rounds as f64 / games as f64)
+        )
+    );
+
+    // Where the user likes to go
+    let state = GameState::new();
+    let sections: Vec<String> = profile
+        .section_counts()
+        .iter()
+        .take(3)
+        .filter(|(_, count)| *count > 0)
+        .map(|(section, count)| format!("{} ({})", state.sections[*section].name, count))
+        .collect();
+    writeln!(
+        console,
+        "{}",
+        tr!("stats.favourite_sections", sections = sections.join(", "))
+    );
+    let spots: Vec<String> = profile
+        .spot_counts()
+        .iter()
+        .take(3)
+        .map(|((section, sub_section), count)| {
+            let section = &state.sections[*section];
+            tr!(
+                "stats.spot",
+                spot = section.sub_sections[*sub_section].name,
+                section = section.name,
+                count = count
+            )
+        })
+        .collect();
+    writeln!(
+        console,
+        "{}",
+        tr!("stats.favourite_spots", spots = spots.join(", "))
+    );
+}
+
+/// Show the user's stats, and let them export their match history.
+pub fn stats_menu(console: &mut Console) {
+    let profile = user_profile(console);
+    print_stats(console, &profile);
+
+    loop {
+        writeln!(console, "\n{}", tr!("stats.menu"));
+        let (path, res) = match pick_char(console, &['J', 'C', 'R'], tr!("error.not_option")) {
+            'J' => {
+                let path = data_dir().join(EXPORT_JSON_FILE);
+                let res = profile.export_json(&path);
+                (path, res)
+            }
+            'C' => {
+                let path = data_dir().join(EXPORT_CSV_FILE);
+                let res = profile.export_csv(&path);
+                (path, res)
+            }
+            'R' => return,
+            _ => panic!("Invalid option chosen!"),
+        };
+
+        match res {
+            Ok(()) => writeln!(console, "{}", tr!("stats.exported", path = path.display())),
+            Err(_) => writeln!(console, "{}", tr!("stats.export_failed")),
+        }
+    }
+}
+
+/// Testing for profiles.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Create a record of a match that was `won` or lost, searching `moves`.
+    fn finished(role: PlayerType, won: bool, moves: Vec<(usize, usize)>) -> MatchRecord {
+        MatchRecord {
+            finished_at: 0,
+            role,
+            opponent: Opponent::Ai(Difficulty::Normal),
+            won,
+            rounds: moves.len(),
+            wounds: 0,
+            parts_found: 0,
+            moves,
+            results: Vec::new(),
+        }
+    }
+
+    /// Win rates, streaks and favourite places are worked out from the match history.
+    #[test]
+    fn stats() {
+        let profile = Profile {
+            matches: vec![
+                finished(PlayerType::Victim, true, vec![(0, 0), (1, 2)]),
+                finished(PlayerType::Victim, true, vec![(1, 2)]),
+                finished(PlayerType::Killer, false, vec![(4, 1)]),
+                finished(PlayerType::Victim, true, vec![(1, 3)]),
+                finished(PlayerType::Killer, true, vec![(1, 2)]),
+            ],
+            achievements: Vec::new(),
+        };
+
+        assert_eq!(profile.record(|_| true), (4, 5));
+        assert_eq!(profile.record(|m| m.role == PlayerType::Killer), (1, 2));
+        assert_eq!(profile.streaks(), (2, 2));
+        assert_eq!(profile.section_counts()[0], (1, 4));
+        assert_eq!(profile.spot_counts()[0], ((1, 2), 3));
+
+        let (mut console, output) = Console::scripted(&[]);
+        print_stats(&mut console, &profile);
+        assert!(output.text().contains("Overall: won 4 of 5 (80%)"));
+
+        assert!(profile
+            .to_csv()
+            .contains(",Victim,Normal AI,true,2,0,0,CB LE\n"));
+    }
+
+    /// Finished games are added to the profile the console keeps, if it keeps one.
+    #[test]
+    fn recording_matches() {
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.play((0, 0), (0, 0));
+
+        let (mut console, _) = Console::scripted(&[]);
+        record_match(
+            &mut console,
+            &state,
+            PlayerType::Killer,
+            Opponent::Player,
+            true,
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("camp-misty-profile-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut console = Console::scripted(&[]).0.with_profile(&path);
+        record_match(
+            &mut console,
+            &state,
+            PlayerType::Killer,
+            Opponent::Player,
+            true,
+        );
+        record_match(
+            &mut console,
+            &state,
+            PlayerType::Victim,
+            Opponent::Player,
+            false,
+        );
+
+        let profile = Profile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(profile.record(|_| true), (1, 2));
+        assert_eq!(profile.matches[0].rounds, 1);
+        assert_eq!(Profile::load(&path).unwrap().matches.len(), 0);
+    }
+
+    /// A profile that can't be read is moved aside instead of having its history written over.
+    #[test]
+    fn broken_profile() {
+        let state = GameState::new();
+        let path = std::env::temp_dir().join(format!(
+            "camp-misty-broken-profile-{}.json",
+            std::process::id()
+        ));
+        let backup = backup_path(&path);
+        std::fs::write(&path, "{\"matches\": [").unwrap();
+        assert!(Profile::load(&path).is_err());
+
+        let (console, output) = Console::scripted(&[]);
+        let mut console = console.with_profile(&path);
+        record_match(
+            &mut console,
+            &state,
+            PlayerType::Killer,
+            Opponent::Player,
+            true,
+        );
+
+        let profile = Profile::load(&path).unwrap();
+        let old = std::fs::read_to_string(&backup).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&backup).unwrap();
+        assert_eq!(profile.matches.len(), 1);
+        assert_eq!(old, "{\"matches\": [");
+        assert!(output
+            .text()
+            .contains("couldn't be read, so it was moved to"));
+    }
+}
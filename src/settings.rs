@@ -0,0 +1,66 @@
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::theme::*;
+
+/// File the settings are saved in.
+pub const SETTINGS_FILE: &str = "settings.json";
+
+/// Get one of the user's folders for the game out of `dirs`, creating it if needed.
+///
+/// Falls back to the current folder if the user doesn't have one, or it can't be created.
+fn user_dir(dir: impl Fn(&ProjectDirs) -> &Path) -> PathBuf {
+    let dir = match ProjectDirs::from("", "", "camp-misty") {
+        Some(dirs) => dir(&dirs).to_path_buf(),
+        None => return PathBuf::new(),
+    };
+
+    match std::fs::create_dir_all(&dir) {
+        Ok(()) => dir,
+        Err(_) => PathBuf::new(),
+    }
+}
+
+/// Get the folder the user's settings are saved in, like `~/.config/camp-misty` on Linux.
+pub fn config_dir() -> PathBuf {
+    user_dir(ProjectDirs::config_dir)
+}
+
+/// Get the folder the user's profile, stats and recordings are saved in, like
+/// `~/.local/share/camp-misty` on Linux.
+pub fn data_dir() -> PathBuf {
+    user_dir(ProjectDirs::data_dir)
+}
+
+/// Choices the user made in the menus that are remembered between games.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Settings {
+    /// Code of the language the game is played in, or `None` to use the system's.
+    pub language: Option<String>,
+
+    /// Colours the text is shown in.
+    pub theme: Theme,
+
+    /// Whether screen reader mode is on.
+    pub accessible: bool,
+}
+
+impl Settings {
+    /// Load the saved settings, or the defaults if there aren't any.
+    pub fn load() -> Settings {
+        std::fs::read(config_dir().join(SETTINGS_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save the settings, so they are used next time.
+    pub fn save(&self) -> std::io::Result<()> {
+        std::fs::write(
+            config_dir().join(SETTINGS_FILE),
+            serde_json::to_vec_pretty(self)?,
+        )
+    }
+}
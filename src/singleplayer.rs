@@ -1,86 +1,218 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
+use crate::console::*;
+use crate::game::debrief::*;
+use crate::game::difficulty::*;
 use crate::game::game_state::*;
 use crate::game::killer_ai::*;
 use crate::game::killer_user::*;
 use crate::game::victim_ai::*;
 use crate::game::victim_user::*;
 use crate::multiplayer::packets::*;
+use crate::profile::*;
+use crate::tr;
+use crate::tui;
 use crate::util::*;
 
 /// Play the game by yourself.
-pub fn play_singleplayer() {
+pub fn play_singleplayer(console: &mut Console) {
     // Choose if you want to be the killer or the victim
-    println!("Would you like to be the (K)iller, the (V)ictim, or (R)andomly choose?");
-    let player_type = match pick_char(&['K', 'V', 'R'], "Sorry, that isn't a valid option.") {
-        'K' => PlayerType::Killer,
-        'V' => PlayerType::Victim,
-        'R' => {
-            if rand::thread_rng().gen_range(0, 2) == 0 {
-                PlayerType::Killer
-            } else {
-                PlayerType::Victim
-            }
-        }
+    writeln!(console, "{}", tr!("menu.ask_role"));
+    let player_type = match pick_char(console, &['K', 'V', 'R'], tr!("error.not_option")) {
+        'K' => Some(PlayerType::Killer),
+        'V' => Some(PlayerType::Victim),
+        'R' => None,
+        _ => panic!("Invalid option chosen!"),
+    };
+
+    // Choose how well the AI plays
+    writeln!(console, "{}", tr!("solo.ask_difficulty"));
+    let difficulty = match pick_char(console, &['E', 'N', 'H'], tr!("error.not_option")) {
+        'E' => Difficulty::Easy,
+        'N' => Difficulty::Normal,
+        'H' => Difficulty::Hard,
         _ => panic!("Invalid option chosen!"),
     };
 
+    play_solo(console, player_type, difficulty, None);
+
+    // Return to title screen
+    writeln!(console, "{}", tr!("menu.return_to_title"));
+    read_str(console);
+}
+
+/// Play a game against the AI.
+///
+/// `player_type` is who the user plays as, or `None` to choose randomly. Giving a `seed` makes
+/// the car parts and the AI's moves the same every time.
+pub fn play_solo(
+    console: &mut Console,
+    player_type: Option<PlayerType>,
+    difficulty: Difficulty,
+    seed: Option<u64>,
+) {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let player_type = player_type.unwrap_or_else(|| {
+        if rng.gen_range(0, 2) == 0 {
+            PlayerType::Killer
+        } else {
+            PlayerType::Victim
+        }
+    });
+
     // Create game state and generate random state
     let mut state = GameState::new();
-    state.gen_state();
+    state.gen_state_with(&mut rng);
 
-    // Play as either victim or killer
-    match player_type {
+    // The AI plays whoever the user isn't
+    let mut opponent: Box<tui::Opponent> = match player_type {
         PlayerType::Killer => {
-            // Create victim
-            let mut victim = VictimAI::new(&state);
-
-            // Play game until there is a winner
-            loop {
-                // Make moves
-                let killer_move = play_killer(&mut state);
-                let victim_move = victim.play(&mut state);
+            let mut victim = VictimAI::with_difficulty(&state, difficulty, rng);
+            Box::new(move |state| victim.play(state))
+        }
 
-                // Submit moves to the game state
-                let res = state.play(victim_move, killer_move);
+        PlayerType::Victim => {
+            let mut killer = KillerAI::with_difficulty(&state, difficulty, rng);
+            Box::new(move |state| killer.play(state))
+        }
+    };
 
-                // Break if someone won
-                if res.result == RoundResult::Caught {
-                    killer_win_message(player_type);
-                    break;
-                } else if res.result == RoundResult::AllPartsFound {
-                    victim_win_message(player_type);
-                    break;
-                }
+    // Use the terminal UI where we can, and plain text everywhere else
+    let mut tui_winner = None;
+    if tui::is_supported(console) {
+        match tui::play(&mut state, player_type, &mut opponent) {
+            Ok(Some(winner)) => tui_winner = Some(winner),
+            Ok(None) => {
+                writeln!(console, "{}", tr!("solo.left"));
+                return;
             }
+            Err(_) => writeln!(console, "{}", tr!("tui.failed")),
         }
+    }
 
-        PlayerType::Victim => {
-            // Create killer
-            let mut killer = KillerAI::new(&state);
+    // Play game until there is a winner
+    let winner = match tui_winner {
+        Some(winner) => winner,
+        None => loop {
+            // Make moves
+            let our_move = match player_type {
+                PlayerType::Killer => play_killer(console, &mut state),
+                PlayerType::Victim => play_victim(console, &state),
+            };
+            let their_move = opponent(&mut state);
+            let (victim_move, killer_move) = match player_type {
+                PlayerType::Killer => (their_move, our_move),
+                PlayerType::Victim => (our_move, their_move),
+            };
+
+            // Submit moves to the game state
+            let res = state.play(victim_move, killer_move);
+
+            // Break if someone won
+            if res.result == RoundResult::Caught {
+                break PlayerType::Killer;
+            } else if res.result == RoundResult::AllPartsFound {
+                break PlayerType::Victim;
+            }
+        },
+    };
+
+    match winner {
+        PlayerType::Killer => killer_win_message(console, player_type),
+        PlayerType::Victim => victim_win_message(console, player_type),
+    }
+    print_debrief(console, &state, Some(player_type));
+    record_match(
+        console,
+        &state,
+        player_type,
+        Opponent::Ai(difficulty),
+        winner == player_type,
+    );
+}
+
+/// Play `games` games of the AI against itself and print who won how often.
+///
+/// Both sides play at `difficulty`. Giving a `seed` makes the results the same every time.
+pub fn simulate(console: &mut Console, games: u32, difficulty: Difficulty, seed: Option<u64>) {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let mut victim_wins = 0u32;
+    let mut killer_wins = 0u32;
+    for _ in 0..games {
+        let mut state = GameState::new();
+        state.gen_state_with(&mut rng);
 
-            // Play game until there is a winner
-            loop {
-                // Make moves
-                let killer_move = killer.play(&state);
-                let victim_move = play_victim(&state);
+        // Each AI gets its own generator so they don't depend on each other's moves
+        let mut killer =
+            KillerAI::with_difficulty(&state, difficulty, StdRng::seed_from_u64(rng.gen()));
+        let mut victim =
+            VictimAI::with_difficulty(&state, difficulty, StdRng::seed_from_u64(rng.gen()));
 
-                // Submit moves to the game state
-                let res = state.play(victim_move, killer_move);
+        loop {
+            let killer_move = killer.play(&state);
+            let victim_move = victim.play(&mut state);
 
-                // Break if someone won
-                if res.result == RoundResult::Caught {
-                    killer_win_message(player_type);
+            match state.play(victim_move, killer_move).result {
+                RoundResult::Caught => {
+                    killer_wins += 1;
                     break;
-                } else if res.result == RoundResult::AllPartsFound {
-                    victim_win_message(player_type);
+                }
+                RoundResult::AllPartsFound => {
+                    victim_wins += 1;
                     break;
                 }
+                _ => {}
             }
         }
     }
 
-    // Return to title screen
-    println!("Enter anything to return to the title screen...");
-    read_str();
+    writeln!(console, "{}", tr!("simulate.games", games = games));
+    writeln!(
+        console,
+        "{}",
+        tr!("simulate.victim_wins", wins = victim_wins)
+    );
+    writeln!(
+        console,
+        "{}",
+        tr!("simulate.killer_wins", wins = killer_wins)
+    );
+    if games > 0 {
+        writeln!(
+            console,
+            "{}",
+            tr!(
+                "simulate.victim_win_rate",
+                percent = format!("{:.1}", 100.0 * f64::from(victim_wins) / f64::from(games))
+            )
+        );
+    }
+}
+
+/// Testing for playing against the AI.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Simulations with the same seed always have the same results.
+    #[test]
+    fn seeded_simulation() {
+        let simulated = |seed| {
+            let (mut console, output) = Console::scripted(&[]);
+            simulate(&mut console, 50, Difficulty::Normal, Some(seed));
+            output.text()
+        };
+
+        assert_eq!(simulated(7), simulated(7));
+        assert!(simulated(7).contains("50"));
+    }
 }
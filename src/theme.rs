@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::console::*;
+use crate::tr;
+
+/// Colours used for the text of the game.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Theme {
+    /// Easy on the eyes, with a different colour for every kind of event.
+    #[default]
+    Classic,
+
+    /// Everything in shades of red.
+    BloodRed,
+
+    /// Bold, bright colours that are easy to tell apart.
+    HighContrast,
+}
+
+/// What a piece of text is, which decides its colour.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Tone {
+    /// The title screen.
+    Title,
+
+    /// The name of a section or sub-section.
+    Section,
+
+    /// A car part being found.
+    Part,
+
+    /// A chase beginning.
+    Chase,
+
+    /// The victim being wounded or caught.
+    Wound,
+
+    /// The victim getting away, or the killer being trapped.
+    Escape,
+
+    /// Winning the game.
+    Win,
+
+    /// Losing the game.
+    Lose,
+}
+
+/// Index of the current theme in `Theme::ALL`.
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+impl Theme {
+    /// Every theme, in the order they are offered.
+    pub const ALL: [Theme; 3] = [Theme::Classic, Theme::BloodRed, Theme::HighContrast];
+
+    /// Name of the theme, in the current language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => tr!("theme.classic"),
+            Theme::BloodRed => tr!("theme.blood_red"),
+            Theme::HighContrast => tr!("theme.high_contrast"),
+        }
+    }
+
+    /// ANSI escape code parameters for text of the given `tone`.
+    fn code(self, tone: Tone) -> &'static str {
+        match (self, tone) {
+            (Theme::Classic, Tone::Title) => "1",
+            (Theme::Classic, Tone::Section) => "36",
+            (Theme::Classic, Tone::Part) => "32",
+            (Theme::Classic, Tone::Chase) => "33",
+            (Theme::Classic, Tone::Wound) => "31",
+            (Theme::Classic, Tone::Escape) => "35",
+            (Theme::Classic, Tone::Win) => "1;32",
+            (Theme::Classic, Tone::Lose) => "1;31",
+
+            (Theme::BloodRed, Tone::Title) => "1;31",
+            (Theme::BloodRed, Tone::Section) => "31",
+            (Theme::BloodRed, Tone::Part) => "37",
+            (Theme::BloodRed, Tone::Chase) => "1;91",
+            (Theme::BloodRed, Tone::Wound) => "1;97;41",
+            (Theme::BloodRed, Tone::Escape) => "90",
+            (Theme::BloodRed, Tone::Win) => "1;91",
+            (Theme::BloodRed, Tone::Lose) => "2;31",
+
+            (Theme::HighContrast, Tone::Title) => "1;97",
+            (Theme::HighContrast, Tone::Section) => "1;93",
+            (Theme::HighContrast, Tone::Part) => "1;92",
+            (Theme::HighContrast, Tone::Chase) => "1;30;103",
+            (Theme::HighContrast, Tone::Wound) => "1;97;41",
+            (Theme::HighContrast, Tone::Escape) => "1;96",
+            (Theme::HighContrast, Tone::Win) => "1;92",
+            (Theme::HighContrast, Tone::Lose) => "1;91",
+        }
+    }
+
+    /// Wrap `text` in the escape codes for `tone`.
+    pub fn style(self, tone: Tone, text: &str) -> String {
+        format!("\x1b[{}m{}\x1b[0m", self.code(tone), text)
+    }
+}
+
+/// Get the theme the game is using.
+pub fn current_theme() -> Theme {
+    Theme::ALL[CURRENT.load(Ordering::Relaxed)]
+}
+
+/// Switch to `theme`.
+pub fn set_theme(theme: Theme) {
+    let index = Theme::ALL.iter().position(|t| *t == theme).unwrap_or(0);
+    CURRENT.store(index, Ordering::Relaxed);
+}
+
+/// Returns 'true' if text written to `console` should be coloured.
+///
+/// Colour is left off when the output isn't a terminal, and whenever `NO_COLOR` is set, as
+/// described at https://no-color.org.
+pub fn colors_enabled(console: &Console) -> bool {
+    console.output_is_terminal()
+        && !console.is_accessible()
+        && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+        && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// Colour `text` as a `tone` in the current theme, if `console` can show colours.
+pub fn paint(console: &Console, tone: Tone, text: &str) -> String {
+    if colors_enabled(console) {
+        current_theme().style(tone, text)
+    } else {
+        String::from(text)
+    }
+}
+
+/// Testing for themes.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Themes wrap text in escape codes, but only for terminals.
+    #[test]
+    fn painting() {
+        assert_eq!(
+            Theme::Classic.style(Tone::Part, "Nice!"),
+            "\x1b[32mNice!\x1b[0m"
+        );
+
+        let (console, _) = Console::scripted(&[]);
+        assert_eq!(paint(&console, Tone::Wound, "Ouch!"), "Ouch!");
+    }
+}
@@ -0,0 +1,598 @@
+use ratatui::buffer::Buffer;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, Paragraph, Widget, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::multiplayer::packets::*;
+use crate::tr;
+
+/// Picks the other players move each round.
+pub type Opponent<'a> = dyn FnMut(&mut GameState) -> (usize, usize) + 'a;
+
+/// Flag indicating the user asked for plain text instead of the terminal UI.
+static DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Always use plain text instead of the terminal UI.
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Returns 'true' if the terminal UI can be used in place of `console`.
+///
+/// Dumb terminals, and consoles that aren't a terminal, get plain text instead.
+pub fn is_supported(console: &Console) -> bool {
+    !DISABLED.load(Ordering::Relaxed)
+        && !console.is_accessible()
+        && console.is_terminal()
+        && std::env::var("TERM").map_or(true, |term| term != "dumb")
+}
+
+/// Play a game against `opponent` in the terminal UI as a `player_type`.
+///
+/// `opponent` picks the other players move each round. Returns the winner, or `None` if the user
+/// left the game. Returns an error if the terminal UI couldn't be used, in which case the game
+/// can carry on in plain text from where it was left.
+pub fn play(
+    state: &mut GameState,
+    player_type: PlayerType,
+    opponent: &mut Opponent,
+) -> std::io::Result<Option<PlayerType>> {
+    let mut terminal = ratatui::try_init()?;
+    let res = run(&mut terminal, state, player_type, opponent);
+    ratatui::restore();
+    res
+}
+
+/// Draw the game and handle keys until the game is over.
+fn run(
+    terminal: &mut DefaultTerminal,
+    state: &mut GameState,
+    player_type: PlayerType,
+    opponent: &mut Opponent,
+) -> std::io::Result<Option<PlayerType>> {
+    let mut screen = GameScreen::new(state, player_type);
+    loop {
+        terminal.draw(|frame| screen.draw(frame, state))?;
+
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+
+        // Give the user a chance to see how the game ended
+        if screen.winner.is_some() {
+            return Ok(screen.winner);
+        }
+
+        match screen.handle_key(key, state) {
+            KeyAction::Nothing => {}
+            KeyAction::Quit => return Ok(None),
+            KeyAction::Move(our_move) => {
+                let their_move = opponent(state);
+                let (victim_move, killer_move) = match player_type {
+                    PlayerType::Victim => (our_move, their_move),
+                    PlayerType::Killer => (their_move, our_move),
+                };
+
+                let res = state.play(victim_move, killer_move);
+                screen.record_round(state, our_move, res);
+            }
+        }
+    }
+}
+
+/// What the user wants to do after pressing a key.
+#[derive(Debug, PartialEq)]
+enum KeyAction {
+    /// Nothing yet.
+    Nothing,
+
+    /// Make a move.
+    Move((usize, usize)),
+
+    /// Leave the game.
+    Quit,
+}
+
+/// Everything the terminal UI shows besides the game state itself.
+struct GameScreen {
+    /// Player type of the user.
+    player_type: PlayerType,
+
+    /// Section and sub-section the cursor is on.
+    cursor: (usize, usize),
+
+    /// Flag indicating the user picked a section by its letter and is picking a spot in it.
+    picking_spot: bool,
+
+    /// Number of rounds played.
+    round: u32,
+
+    /// Number of car parts found so far.
+    parts_found: usize,
+
+    /// Sections the victim found a car part in, which both players know about.
+    part_sections: Vec<usize>,
+
+    /// Spots the user has searched.
+    searched: Vec<(usize, usize)>,
+
+    /// Messages describing what happened, oldest first.
+    log: Vec<String>,
+
+    /// Player type of whoever won, once the game is over.
+    winner: Option<PlayerType>,
+}
+
+impl GameScreen {
+    /// Create the screen for a new game in `state`.
+    fn new(state: &GameState, player_type: PlayerType) -> GameScreen {
+        let intro = match player_type {
+            PlayerType::Victim => tr!("tui.intro.victim", count = state.part_count),
+            PlayerType::Killer => String::from(tr!("tui.intro.killer")),
+        };
+
+        GameScreen {
+            player_type,
+            cursor: (0, 0),
+            picking_spot: false,
+            round: 0,
+            parts_found: 0,
+            part_sections: Vec::new(),
+            searched: Vec::new(),
+            log: vec![intro],
+            winner: None,
+        }
+    }
+
+    /// Section the next move has to be in because of a chase, if there is one.
+    fn chase_section(state: &GameState) -> Option<usize> {
+        match state.last_result.result {
+            RoundResult::ChaseBegins(section) => Some(section),
+            _ => None,
+        }
+    }
+
+    /// Move the cursor to the section at `index`, keeping it on a spot that exists.
+    fn select_section(&mut self, state: &GameState, index: usize) {
+        let spots = state.sections[index].sub_sections.len();
+        self.cursor = (index, self.cursor.1.min(spots - 1));
+    }
+
+    /// Deal with a key the user pressed.
+    fn handle_key(&mut self, key: KeyEvent, state: &GameState) -> KeyAction {
+        let section_count = state.sections.len();
+        let spot_count = state.sections[self.cursor.0].sub_sections.len();
+        let chase = GameScreen::chase_section(state);
+
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => KeyAction::Quit,
+
+            KeyCode::Esc => {
+                if self.picking_spot {
+                    self.picking_spot = false;
+                    KeyAction::Nothing
+                } else {
+                    KeyAction::Quit
+                }
+            }
+
+            // Nobody can leave the section during a chase
+            KeyCode::Left | KeyCode::Right if chase.is_none() => {
+                let index = if key.code == KeyCode::Left {
+                    (self.cursor.0 + section_count - 1) % section_count
+                } else {
+                    (self.cursor.0 + 1) % section_count
+                };
+                self.select_section(state, index);
+                self.picking_spot = false;
+                KeyAction::Nothing
+            }
+
+            KeyCode::Up => {
+                self.cursor.1 = (self.cursor.1 + spot_count - 1) % spot_count;
+                KeyAction::Nothing
+            }
+
+            KeyCode::Down => {
+                self.cursor.1 = (self.cursor.1 + 1) % spot_count;
+                KeyAction::Nothing
+            }
+
+            KeyCode::Enter if state.is_valid_move(self.cursor) => KeyAction::Move(self.cursor),
+
+            KeyCode::Char(c) => {
+                let letter = c.to_ascii_uppercase();
+
+                // Spot letters pick a spot in the chosen section and make the move straight away
+                if self.picking_spot || chase.is_some() {
+                    let spot = state.sections[self.cursor.0]
+                        .sub_sections
+                        .iter()
+                        .position(|sub_section| sub_section.letter == letter);
+                    if let Some(spot) = spot {
+                        self.cursor.1 = spot;
+                        return KeyAction::Move(self.cursor);
+                    }
+                } else if let Some(index) = state.get_section_by_letter(letter) {
+                    self.select_section(state, index);
+                    self.picking_spot = true;
+                }
+
+                KeyAction::Nothing
+            }
+
+            _ => KeyAction::Nothing,
+        }
+    }
+
+    /// Update the screen after a round where the user made `our_move` and the result was `res`.
+    fn record_round(&mut self, state: &GameState, our_move: (usize, usize), res: PlayResult) {
+        self.round += 1;
+        self.picking_spot = false;
+        if !self.searched.contains(&our_move) {
+            self.searched.push(our_move);
+        }
+
+        let section = &state.sections[our_move.0];
+        self.log.push(tr!(
+            "tui.log.searched",
+            round = self.round,
+            spot = section.sub_sections[our_move.1].name,
+            location = section.name,
+        ));
+
+        if let Some(part_section) = res.part_section_index {
+            self.parts_found += 1;
+            self.part_sections.push(part_section);
+            self.log.push(match self.player_type {
+                PlayerType::Victim => tr!(
+                    "tui.log.part.victim",
+                    found = self.parts_found,
+                    total = self.parts_found + state.part_count,
+                ),
+                PlayerType::Killer => tr!(
+                    "tui.log.part.killer",
+                    location = state.sections[part_section].name,
+                ),
+            });
+        }
+
+        let victim = self.player_type == PlayerType::Victim;
+        let message = match res.result {
+            RoundResult::Nothing if victim => String::from(tr!("tui.log.nothing.victim")),
+            RoundResult::Nothing => String::from(tr!("tui.log.nothing.killer")),
+            RoundResult::ChaseBegins(section) if victim => tr!(
+                "tui.log.chase.victim",
+                location = state.sections[section].name,
+            ),
+            RoundResult::ChaseBegins(section) => tr!(
+                "tui.log.chase.killer",
+                location = state.sections[section].name,
+            ),
+            RoundResult::Evaded if victim => String::from(tr!("tui.log.evaded.victim")),
+            RoundResult::Evaded => String::from(tr!("tui.log.evaded.killer")),
+            RoundResult::TrapTriggered if victim => String::from(tr!("tui.log.trap.victim")),
+            RoundResult::TrapTriggered => String::from(tr!("tui.log.trap.killer")),
+            RoundResult::Wounded if victim => String::from(tr!("tui.log.wounded.victim")),
+            RoundResult::Wounded => String::from(tr!("tui.log.wounded.killer")),
+            RoundResult::Caught => {
+                self.winner = Some(PlayerType::Killer);
+                String::from(tr!("tui.log.caught"))
+            }
+            RoundResult::AllPartsFound => {
+                self.winner = Some(PlayerType::Victim);
+                String::from(tr!("tui.log.escaped"))
+            }
+        };
+        self.log.push(message);
+
+        // The cursor jumps to the chase, since the move has to be made there
+        if let Some(section) = GameScreen::chase_section(state) {
+            self.select_section(state, section);
+        }
+    }
+
+    /// Draw the whole screen.
+    fn draw(&self, frame: &mut Frame, state: &GameState) {
+        let [map_area, log_area, status_area, input_area] = Layout::vertical([
+            Constraint::Length(8),
+            Constraint::Min(3),
+            Constraint::Length(1),
+            Constraint::Length(4),
+        ])
+        .areas(frame.area());
+
+        // Map of Camp Misty, one column per section
+        let chase = GameScreen::chase_section(state);
+        render_map(frame.buffer_mut(), map_area, state, |i| {
+            let border_style = if chase == Some(i) {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else if self.cursor.0 == i {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+
+            let mut lines: Vec<Line> = state.sections[i]
+                .sub_sections
+                .iter()
+                .enumerate()
+                .map(|(j, sub_section)| {
+                    let mut style = Style::default();
+                    if self.searched.contains(&(i, j)) {
+                        style = style.fg(Color::DarkGray);
+                    }
+                    if self.cursor == (i, j) && self.winner.is_none() {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    Line::from(Span::styled(sub_section.name.clone(), style))
+                })
+                .collect();
+
+            if self.part_sections.contains(&i) {
+                lines.push(Line::from(Span::styled(
+                    tr!("tui.part_found"),
+                    Style::default().fg(Color::Green),
+                )));
+            }
+
+            (border_style, lines)
+        });
+
+        // Only the newest messages fit
+        let visible = log_area.height.saturating_sub(2) as usize;
+        let log: Vec<Line> = self
+            .log
+            .iter()
+            .skip(self.log.len().saturating_sub(visible))
+            .map(|message| Line::from(message.as_str()))
+            .collect();
+        let log_block = Block::default().borders(Borders::ALL).title(tr!("tui.log"));
+        frame.render_widget(Paragraph::new(log).block(log_block), log_area);
+
+        // Status bar
+        let role = match self.player_type {
+            PlayerType::Killer => tr!("tui.role.killer"),
+            PlayerType::Victim => tr!("tui.role.victim"),
+        };
+        let health = if state.victim_is_wounded {
+            tr!("tui.health.wounded")
+        } else {
+            tr!("tui.health.unhurt")
+        };
+        let status = format!(
+            " {}",
+            tr!(
+                "tui.status",
+                round = self.round + 1,
+                role = role,
+                found = self.parts_found,
+                total = self.parts_found + state.part_count,
+                health = health,
+            )
+        );
+        frame.render_widget(
+            Paragraph::new(status).style(Style::default().add_modifier(Modifier::REVERSED)),
+            status_area,
+        );
+
+        // What the user can do next
+        let hint = match self.winner {
+            Some(winner) if winner == self.player_type => String::from(tr!("tui.hint.win")),
+            Some(_) => String::from(tr!("tui.hint.lose")),
+            None if chase.is_some() || self.picking_spot => tr!(
+                "tui.hint.spot",
+                location = state.sections[self.cursor.0].name,
+                escape = if self.picking_spot {
+                    tr!("tui.hint.back")
+                } else {
+                    tr!("tui.hint.leave")
+                },
+            ),
+            None => String::from(tr!("tui.hint.location")),
+        };
+        let input_block = Block::default()
+            .borders(Borders::ALL)
+            .title(tr!("tui.your_move"));
+        frame.render_widget(
+            Paragraph::new(hint)
+                .block(input_block)
+                .wrap(Wrap { trim: true }),
+            input_area,
+        );
+    }
+}
+
+/// Draw the map of Camp Misty into `area`, one column per section.
+///
+/// `section` gives the border style and lines to show for each section.
+fn render_map(
+    buf: &mut Buffer,
+    area: Rect,
+    state: &GameState,
+    section: impl Fn(usize) -> (Style, Vec<Line<'static>>),
+) {
+    let columns = Layout::horizontal(vec![
+        Constraint::Ratio(1, state.sections.len() as u32);
+        state.sections.len()
+    ])
+    .split(area);
+
+    for (i, info) in state.sections.iter().enumerate() {
+        let (border_style, lines) = section(i);
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .title(info.name.clone());
+        Paragraph::new(lines).block(block).render(columns[i], buf);
+    }
+}
+
+/// Draw the map of Camp Misty as plain text, for printing outside the terminal UI.
+///
+/// `mark` picks a character to show in front of a spot, if it should stand out.
+pub fn map_text(state: &GameState, mark: impl Fn((usize, usize)) -> Option<char>) -> Vec<String> {
+    let spot_text = |spot: (usize, usize)| {
+        let name = &state.sections[spot.0].sub_sections[spot.1].name;
+        format!("{} {}", mark(spot).unwrap_or(' '), name)
+    };
+
+    // Every column is made wide enough for the longest name on the map
+    let column_width = state
+        .sections
+        .iter()
+        .enumerate()
+        .flat_map(|(i, section)| {
+            std::iter::once(section.name.chars().count()).chain(
+                (0..section.sub_sections.len()).map(move |j| spot_text((i, j)).chars().count()),
+            )
+        })
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let height = state
+        .sections
+        .iter()
+        .map(|section| section.sub_sections.len())
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let area = Rect::new(
+        0,
+        0,
+        (column_width * state.sections.len()) as u16,
+        height as u16,
+    );
+
+    let mut buf = Buffer::empty(area);
+    render_map(&mut buf, area, state, |i| {
+        let lines = (0..state.sections[i].sub_sections.len())
+            .map(|j| Line::from(spot_text((i, j))))
+            .collect();
+        (Style::default(), lines)
+    });
+
+    (area.top()..area.bottom())
+        .map(|y| {
+            let row: String = (area.left()..area.right())
+                .map(|x| buf[(x, y)].symbol())
+                .collect();
+            String::from(row.trim_end())
+        })
+        .collect()
+}
+
+/// Testing for the terminal UI.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    /// Keys move the cursor around the map and make moves, and chases keep the cursor in the
+    /// chase section.
+    #[test]
+    fn keys() {
+        let mut state = GameState::new();
+        state.gen_state();
+        let mut screen = GameScreen::new(&state, PlayerType::Victim);
+        let press = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Left), &state),
+            KeyAction::Nothing
+        );
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Up), &state),
+            KeyAction::Nothing
+        );
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Enter), &state),
+            KeyAction::Move((4, 4))
+        );
+
+        // Letters pick a location, then a spot in it
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Char('l')), &state),
+            KeyAction::Nothing
+        );
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Char('b')), &state),
+            KeyAction::Move((1, 1))
+        );
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Char('c')), &state),
+            KeyAction::Nothing
+        );
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Esc), &state),
+            KeyAction::Nothing
+        );
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Esc), &state),
+            KeyAction::Quit
+        );
+
+        // Chases lock the location
+        let res = state.play((2, 0), (2, 1));
+        screen.record_round(&state, (2, 0), res);
+        assert_eq!(screen.cursor.0, 2);
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Right), &state),
+            KeyAction::Nothing
+        );
+        assert_eq!(screen.cursor.0, 2);
+        assert_eq!(
+            screen.handle_key(press(KeyCode::Char('k')), &state),
+            KeyAction::Move((2, 3))
+        );
+    }
+
+    /// The map, log, and status bar all make it onto the screen.
+    #[test]
+    fn draw() {
+        let state = GameState::new();
+        let mut screen = GameScreen::new(&state, PlayerType::Killer);
+        screen.log.push(String::from("Something happened."));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        terminal.draw(|frame| screen.draw(frame, &state)).unwrap();
+
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for section in &state.sections {
+            assert!(text.contains(section.name.as_str()));
+        }
+        assert!(text.contains("Something happened."));
+        assert!(text.contains("Round 1 | You are the killer"));
+    }
+
+    /// The plain text map has every section, with the marked spots standing out.
+    #[test]
+    fn plain_map() {
+        let state = GameState::new();
+        let map = map_text(&state, |spot| (spot == (1, 2)).then_some('*'));
+        assert_eq!(map.len(), 7);
+
+        let text = map.join("\n");
+        for section in &state.sections {
+            assert!(text.contains(section.name.as_str()));
+        }
+        assert!(text.contains("* (E)ast shore"));
+        assert!(text.contains("  (W)est shore"));
+    }
+}
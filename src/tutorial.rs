@@ -0,0 +1,326 @@
+use crate::console::*;
+use crate::game::game_state::*;
+use crate::game::killer_user::*;
+use crate::game::victim_user::*;
+use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
+use crate::util::*;
+
+/// Number of car parts hidden in the tutorial, so it doesn't take too long.
+const TUTORIAL_PARTS: usize = 2;
+
+/// Quiz questions, along with the letter of the right answer.
+const QUIZ: [(&str, char); 4] = [
+    ("tutorial.quiz.parts", 'B'),
+    ("tutorial.quiz.chase", 'C'),
+    ("tutorial.quiz.hide", 'B'),
+    ("tutorial.quiz.wounded", 'A'),
+];
+
+/// A step of the tutorial, each of which makes something happen.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Step {
+    /// Nothing happens.
+    Search,
+
+    /// The victim finds a car part.
+    FindPart,
+
+    /// A chase begins.
+    Chase,
+
+    /// The victim evades the killer.
+    Evade,
+
+    /// The killer wounds the victim.
+    Wound,
+
+    /// Whoever the user is wins.
+    Win,
+}
+
+impl Step {
+    /// Every step, in the order they are played.
+    const ALL: [Step; 6] = [
+        Step::Search,
+        Step::FindPart,
+        Step::Chase,
+        Step::Evade,
+        Step::Wound,
+        Step::Win,
+    ];
+
+    /// Key of the message telling a `player_type` player what to do in the step.
+    fn intro(self, player_type: PlayerType) -> &'static str {
+        match (player_type, self) {
+            (PlayerType::Victim, Step::Search) => "tutorial.victim.search",
+            (PlayerType::Victim, Step::FindPart) => "tutorial.victim.find_part",
+            (PlayerType::Victim, Step::Chase) => "tutorial.victim.chase",
+            (PlayerType::Victim, Step::Evade) => "tutorial.victim.evade",
+            (PlayerType::Victim, Step::Wound) => "tutorial.victim.wound",
+            (PlayerType::Victim, Step::Win) => "tutorial.victim.win",
+            (PlayerType::Killer, Step::Search) => "tutorial.killer.search",
+            (PlayerType::Killer, Step::FindPart) => "tutorial.killer.find_part",
+            (PlayerType::Killer, Step::Chase) => "tutorial.killer.chase",
+            (PlayerType::Killer, Step::Evade) => "tutorial.killer.evade",
+            (PlayerType::Killer, Step::Wound) => "tutorial.killer.wound",
+            (PlayerType::Killer, Step::Win) => "tutorial.killer.win",
+        }
+    }
+
+    /// Key of the message explaining to a `player_type` player what happened in the step.
+    fn outro(self, player_type: PlayerType) -> &'static str {
+        match (player_type, self) {
+            (PlayerType::Victim, Step::Search) => "tutorial.victim.search.done",
+            (PlayerType::Victim, Step::FindPart) => "tutorial.victim.find_part.done",
+            (PlayerType::Victim, Step::Chase) => "tutorial.victim.chase.done",
+            (PlayerType::Victim, Step::Evade) => "tutorial.victim.evade.done",
+            (PlayerType::Victim, Step::Wound) => "tutorial.victim.wound.done",
+            (PlayerType::Victim, Step::Win) => "tutorial.victim.win.done",
+            (PlayerType::Killer, Step::Search) => "tutorial.killer.search.done",
+            (PlayerType::Killer, Step::FindPart) => "tutorial.killer.find_part.done",
+            (PlayerType::Killer, Step::Chase) => "tutorial.killer.chase.done",
+            (PlayerType::Killer, Step::Evade) => "tutorial.killer.evade.done",
+            (PlayerType::Killer, Step::Wound) => "tutorial.killer.wound.done",
+            (PlayerType::Killer, Step::Win) => "tutorial.killer.win.done",
+        }
+    }
+}
+
+/// Get a section other than `section`.
+fn other_section(state: &GameState, section: usize) -> usize {
+    (section + 1) % state.sections.len()
+}
+
+/// Get a sub-section of `section` other than `sub_section`.
+fn other_spot(state: &GameState, section: usize, sub_section: usize) -> usize {
+    (sub_section + 1) % state.sections[section].sub_sections.len()
+}
+
+/// Make sure there's no car part in `spot`, by moving it to another spot in the same section.
+fn clear_part(state: &mut GameState, spot: (usize, usize)) {
+    if state.sections[spot.0].sub_sections[spot.1].part {
+        let other = other_spot(state, spot.0, spot.1);
+        state.sections[spot.0].sub_sections[spot.1].part = false;
+        state.sections[spot.0].sub_sections[other].part = true;
+    }
+}
+
+/// Make sure there's a car part in `spot`, by moving one there.
+///
+/// A part in the same section is moved if there is one, so no section ever has two.
+fn place_part(state: &mut GameState, spot: (usize, usize)) {
+    if state.sections[spot.0].sub_sections[spot.1].part {
+        return;
+    }
+
+    let same_section = (0..state.sections[spot.0].sub_sections.len())
+        .find(|j| state.sections[spot.0].sub_sections[*j].part)
+        .map(|j| (spot.0, j));
+    let from = same_section.or_else(|| {
+        state.sections.iter().enumerate().find_map(|(i, section)| {
+            section
+                .sub_sections
+                .iter()
+                .position(|sub_section| sub_section.part)
+                .map(|j| (i, j))
+        })
+    });
+
+    if let Some(from) = from {
+        state.sections[from.0].sub_sections[from.1].part = false;
+        state.sections[spot.0].sub_sections[spot.1].part = true;
+    }
+}
+
+/// Pick the scripted opponent's move for `step`, given `our_move`, moving car parts around so
+/// the step happens.
+///
+/// Returns whether the step happens, along with the opponent's move. If the step can't happen
+/// after `our_move`, the opponent's move makes sure nothing happens instead.
+fn scripted_move(
+    state: &mut GameState,
+    player_type: PlayerType,
+    step: Step,
+    our_move: (usize, usize),
+) -> (bool, (usize, usize)) {
+    let elsewhere = (other_section(state, our_move.0), 0);
+    let same_section = (our_move.0, other_spot(state, our_move.0, our_move.1));
+
+    match (player_type, step) {
+        // The user is the victim, so the killer goes wherever makes the step happen
+        (PlayerType::Victim, Step::FindPart) => {
+            place_part(state, our_move);
+            (true, elsewhere)
+        }
+        (PlayerType::Victim, Step::Win) => {
+            // Parts are never found twice in the same section
+            let cleared = state
+                .history
+                .iter()
+                .any(|round| round.result.part_section_index == Some(our_move.0));
+            if cleared {
+                (false, elsewhere)
+            } else {
+                place_part(state, our_move);
+                (true, elsewhere)
+            }
+        }
+        (PlayerType::Victim, _) => {
+            clear_part(state, our_move);
+            let killer_move = match step {
+                Step::Chase | Step::Evade => same_section,
+                Step::Wound => our_move,
+                _ => elsewhere,
+            };
+            (true, killer_move)
+        }
+
+        // The user is the killer, so the victim goes wherever makes the step happen
+        (PlayerType::Killer, Step::FindPart) => {
+            place_part(state, elsewhere);
+            (true, elsewhere)
+        }
+        (PlayerType::Killer, _) => {
+            let victim_move = match step {
+                Step::Chase | Step::Evade => same_section,
+                Step::Wound | Step::Win => our_move,
+                _ => elsewhere,
+            };
+            clear_part(state, victim_move);
+            (true, victim_move)
+        }
+    }
+}
+
+/// Play a tutorial game as `player_type` against a scripted opponent, then give a quiz.
+///
+/// Returns the number of quiz questions answered correctly.
+pub fn run_tutorial(console: &mut Console, player_type: PlayerType) -> usize {
+    let mut state = GameState::new();
+    for section in 0..TUTORIAL_PARTS {
+        state.hide_part(section, 0);
+    }
+
+    writeln!(console, "{}\n", tr!("tutorial.welcome"));
+
+    let mut steps = Step::ALL.iter().peekable();
+    while let Some(step) = steps.peek() {
+        writeln!(
+            console,
+            "{}",
+            paint(console, Tone::Title, tr!(step.intro(player_type)))
+        );
+
+        let our_move = match player_type {
+            PlayerType::Killer => play_killer(console, &mut state),
+            PlayerType::Victim => play_victim(console, &state),
+        };
+        let (happened, their_move) = scripted_move(&mut state, player_type, **step, our_move);
+        let (victim_move, killer_move) = match player_type {
+            PlayerType::Killer => (their_move, our_move),
+            PlayerType::Victim => (our_move, their_move),
+        };
+        state.play(victim_move, killer_move);
+
+        // Steps that didn't happen are tried again
+        if !happened {
+            writeln!(console, "{}\n", tr!("tutorial.victim.win.cleared"));
+            continue;
+        }
+
+        writeln!(
+            console,
+            "\n{}\n",
+            paint(console, Tone::Title, tr!(step.outro(player_type)))
+        );
+        steps.next();
+    }
+
+    match player_type {
+        PlayerType::Killer => killer_win_message(console, player_type),
+        PlayerType::Victim => victim_win_message(console, player_type),
+    }
+
+    // Quiz
+    writeln!(console, "\n{}", tr!("tutorial.quiz"));
+    let mut right = 0;
+    for (question, answer) in QUIZ {
+        writeln!(console, "\n{}", tr!(question));
+        if pick_char(console, &['A', 'B', 'C'], tr!("error.not_option")) == answer {
+            right += 1;
+            writeln!(
+                console,
+                "{}",
+                paint(console, Tone::Part, tr!("tutorial.quiz.right"))
+            );
+        } else {
+            let explanation = match question {
+                "tutorial.quiz.parts" => tr!("tutorial.quiz.parts.answer"),
+                "tutorial.quiz.chase" => tr!("tutorial.quiz.chase.answer"),
+                "tutorial.quiz.hide" => tr!("tutorial.quiz.hide.answer"),
+                _ => tr!("tutorial.quiz.wounded.answer"),
+            };
+            writeln!(
+                console,
+                "{}",
+                paint(
+                    console,
+                    Tone::Wound,
+                    &tr!("tutorial.quiz.wrong", explanation = explanation)
+                )
+            );
+        }
+    }
+    writeln!(
+        console,
+        "\n{}",
+        tr!("tutorial.quiz.score", right = right, total = QUIZ.len())
+    );
+
+    right
+}
+
+/// Let the user pick a role and play the tutorial.
+pub fn play_tutorial(console: &mut Console) {
+    writeln!(console, "{}", tr!("tutorial.ask_role"));
+    let player_type = match pick_char(console, &['K', 'V'], tr!("error.not_option")) {
+        'K' => PlayerType::Killer,
+        'V' => PlayerType::Victim,
+        _ => panic!("Invalid option chosen!"),
+    };
+
+    run_tutorial(console, player_type);
+
+    writeln!(console, "{}", tr!("menu.return_to_main"));
+    read_str(console);
+}
+
+/// Testing for the tutorial.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The tutorial can be played through as either role, whatever moves are made.
+    #[test]
+    fn tutorial() {
+        // The victim tries the cleared lake at the end before going elsewhere
+        let (mut console, output) = Console::scripted(&[
+            "C", "B", "L", "D", "A", "D", "B", "B", "S", "L", "D", "C", "K", "B", "C", "B", "C",
+        ]);
+        assert_eq!(run_tutorial(&mut console, PlayerType::Victim), 3);
+        let text = output.text();
+        assert!(text.contains("You already found the part in there"));
+        assert!(text.contains("Yes!!! You found all of the car parts"));
+        assert!(text.contains("You got 3 of 4 questions right."));
+
+        let (mut console, output) = Console::scripted(&[
+            "C", "B", "C", "K", "A", "D", "B", "B", "S", "C", "T", "B", "C", "B", "A",
+        ]);
+        assert_eq!(run_tutorial(&mut console, PlayerType::Killer), 4);
+        let text = output.text();
+        assert!(text.contains("Oh no! The victim found a car part in the (L)ake Misty!"));
+        assert!(text.contains("Muahahahaha!!!"));
+    }
+}
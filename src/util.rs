@@ -1,31 +1,152 @@
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+use crate::console::*;
 use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
+
+/// How long to wait for input before checking on other things, like deadlines.
+pub const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Blank lines printed to push old text out of sight when the screen can't be cleared.
+pub const CLEAR_SCREEN_LINES: usize = 100;
+
+/// Seconds left on a deadline at which the user is reminded of the time.
+const TIME_REMINDERS: [u64; 3] = [30, 10, 5];
+
+/// Print the input prompt.
+pub fn print_prompt(console: &mut Console) {
+    write!(console, "> ");
+}
+
+/// Clear the screen, so the next person at the keyboard can't see what was on it.
+///
+/// The scrollback is cleared too, so nobody can scroll up to see it either. Output that isn't a
+/// terminal can't be cleared, so what was on it is pushed out of sight with blank lines instead.
+pub fn clear_screen(console: &mut Console) {
+    if console.output_is_terminal() {
+        write!(console, "\x1b[2J\x1b[3J\x1b[H");
+    } else {
+        write!(console, "{}", "\n".repeat(CLEAR_SCREEN_LINES));
+    }
+}
 
 /// Helper function to read user input.
-pub fn read_str() -> String {
-    print!("> ");
-    std::io::Write::flush(&mut std::io::stdout()).expect("Flush failed!");
+pub fn read_str(console: &mut Console) -> String {
+    print_prompt(console);
 
-    // Get port as string
-    let mut input = String::new();
-    std::io::stdin()
-        .read_line(&mut input)
-        .expect("Unable to read user input.");
+    // Input being closed is treated like an empty line
+    console.input().read_line(None).unwrap_or_default()
+}
 
-    String::from(input.trim())
+/// Helper function to read user input before a `deadline`.
+///
+/// The time remaining is shown in the prompt, and the user is reminded as the deadline gets
+/// close. Returns `None` if the deadline passes first. A `deadline` of `None` means there is no
+/// time limit.
+pub fn read_str_before(console: &mut Console, deadline: Option<Instant>) -> Option<String> {
+    let deadline = match deadline {
+        Some(deadline) => deadline,
+        None => return Some(read_str(console)),
+    };
+
+    // Show how long is left
+    let seconds_left = |now: Instant| deadline.saturating_duration_since(now).as_secs() + 1;
+    let mut last_reminder = seconds_left(Instant::now());
+    write!(console, "{} ", tr!("time.left", seconds = last_reminder));
+    print_prompt(console);
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            writeln!(console);
+            return None;
+        }
+
+        // Remind the user when time is running out
+        let left = seconds_left(now);
+        if left < last_reminder && TIME_REMINDERS.contains(&left) {
+            write!(console, "\n{} ", tr!("time.running_out", seconds = left));
+            print_prompt(console);
+            last_reminder = left;
+        }
+
+        let timeout = INPUT_POLL_INTERVAL.min(deadline - now);
+        match console.input().read_line(Some(timeout)) {
+            Ok(line) => return Some(line),
+            Err(RecvTimeoutError::Timeout) => {}
+
+            // Nothing will ever be entered once the input is closed
+            Err(RecvTimeoutError::Disconnected) => std::thread::sleep(timeout),
+        }
+    }
+}
+
+/// Get text sent by someone else ready to be printed, cut to `max_chars` characters.
+///
+/// Control characters are taken out, so escape sequences can't be sent to the user's terminal.
+pub fn printable(text: &str, max_chars: usize) -> String {
+    text.chars()
+        .filter(|c| !c.is_control())
+        .take(max_chars)
+        .collect()
+}
+
+/// Helper function to read a line the user typed while waiting on something else, without
+/// blocking for longer than `timeout`.
+///
+/// No prompt is printed. Returns `None` if the user didn't enter anything in time.
+pub fn try_read_str(console: &mut Console, timeout: Duration) -> Option<String> {
+    match console.input().read_while_waiting(timeout) {
+        Ok(line) => Some(line),
+        Err(RecvTimeoutError::Timeout) => None,
+
+        // Nothing will ever be entered once the input is closed
+        Err(RecvTimeoutError::Disconnected) => {
+            std::thread::sleep(timeout);
+            None
+        }
+    }
 }
 
 /// Helper function to have the user pick a character from a list of valid choices.
 ///
-/// The first argument is an array of valid uppercase characters to choose from.
+/// `valid_chars` is an array of valid uppercase characters to choose from.
 ///
-/// The second argument is the message to prompt the user with when an invalid character is chosen.
+/// `err_msg` is the message to prompt the user with when an invalid character is chosen.
+///
+/// A choice can also be picked by its number, counting from 1 in the order of `valid_chars`.
 ///
 /// The function returns the chosen character.
-pub fn pick_char(valid_chars: &[char], err_msg: &str) -> char {
+pub fn pick_char(console: &mut Console, valid_chars: &[char], err_msg: &str) -> char {
+    pick_char_before(console, valid_chars, err_msg, None)
+        .expect("Input without a deadline timed out!")
+}
+
+/// Helper function to have the user pick a character from a list of valid choices before a
+/// `deadline`.
+///
+/// Works like `pick_char`, but returns `None` if the deadline passes first. A `deadline` of
+/// `None` means there is no time limit.
+pub fn pick_char_before(
+    console: &mut Console,
+    valid_chars: &[char],
+    err_msg: &str,
+    deadline: Option<Instant>,
+) -> Option<char> {
     // Loop to constantly as for input
     loop {
-        // Read input
-        let input = read_str();
+        // Read input, ignoring any spaces around it
+        let input = read_str_before(console, deadline)?;
+        let input = input.trim();
+
+        // Numbers count through the choices
+        if let Ok(number) = input.parse::<usize>() {
+            if let Some(c) = number.checked_sub(1).and_then(|i| valid_chars.get(i)) {
+                return Some(*c);
+            }
+        }
 
         // Must be a single character
         if input.len() == 1 {
@@ -37,46 +158,57 @@ pub fn pick_char(valid_chars: &[char], err_msg: &str) -> char {
             for c in valid_chars {
                 // If a valid char was entered, return it
                 if upper == *c {
-                    return *c;
+                    return Some(*c);
                 }
             }
         }
 
         // Unable to read input
-        println!("{}", err_msg);
+        writeln!(console, "{}", err_msg);
     }
 }
 
 /// Prints a message when the vitim wins.
 ///
-/// The only arguments is the type of player "we" are.
-pub fn victim_win_message(player_type: PlayerType) {
+/// `player_type` is the type of player "we" are.
+pub fn victim_win_message(console: &mut Console, player_type: PlayerType) {
     if player_type == PlayerType::Victim {
-        println!("Yes!!! You found all of the car parts and are able to escape Camp Misty!");
-        print_win();
+        writeln!(console, "{}", tr!("win.victim"));
+        print_win(console);
     } else {
-        println!("No!!! The victim found all the car parts and escaped Camp Misty!");
-        print_lose();
+        writeln!(console, "{}", tr!("lose.victim"));
+        print_lose(console);
     }
 }
 
 /// Prints a message when the killer wins.
 ///
-/// The only arguments is the type of player "we" are.
-pub fn killer_win_message(player_type: PlayerType) {
+/// `player_type` is the type of player "we" are.
+pub fn killer_win_message(console: &mut Console, player_type: PlayerType) {
     if player_type == PlayerType::Victim {
-        println!("Noooo!!! The killer slices your back and you fall dead...");
-        print_lose();
+        writeln!(console, "{}", tr!("lose.killer"));
+        print_lose(console);
     } else {
-        println!("Muahahahaha!!! You slice the victim across their back, and they fall dead...");
-        print_win();
+        writeln!(console, "{}", tr!("win.killer"));
+        print_win(console);
     }
 }
 
 /// Print the title screen
-pub fn print_title_screen() {
-    println!(
-        " 
+pub fn print_title_screen(console: &mut Console) {
+    // Screen readers would read out every block of the art
+    if console.is_accessible() {
+        writeln!(console, "\n{}\n", tr!("title.plain"));
+        return;
+    }
+
+    writeln!(
+        console,
+        "{}",
+        paint(
+            console,
+            Tone::Title,
+            " 
             Welcome To...\n\n\
     ▄████▄  ▄▄▄      ███▄ ▄███▓██▓███    \n\
     ▒██▀ ▀█ ▒████▄   ▓██▒▀█▀ ██▓██░  ██▒ \n\
@@ -96,26 +228,46 @@ pub fn print_title_screen() {
     ░      ░   ▒ ░  ░  ░   ░      ▒ ▒ ░░   \n\
             ░   ░       ░          ░ ░     \n\
                                     ░ ░ "
+        )
     );
 }
 
 // Print win message
-pub fn print_win() {
-    println!(
-        "\n
+pub fn print_win(console: &mut Console) {
+    if console.is_accessible() {
+        return;
+    }
+
+    writeln!(
+        console,
+        "{}",
+        paint(
+            console,
+            Tone::Win,
+            "\n
 ▀▄    ▄ ████▄   ▄        ▄ ▄   ▄█    ▄  
   █  █  █   █    █      █   █  ██     █ 
    ▀█   █   █ █   █    █ ▄   █ ██ ██   █
    █    ▀████ █   █    █  █  █ ▐█ █ █  █
  ▄▀           █▄ ▄█     █ █ █   ▐ █  █ █
                ▀▀▀       ▀ ▀      █   ██"
+        )
     );
 }
 
 // Print lose message
-pub fn print_lose() {
-    println!(
-        "\n
+pub fn print_lose(console: &mut Console) {
+    if console.is_accessible() {
+        return;
+    }
+
+    writeln!(
+        console,
+        "{}",
+        paint(
+            console,
+            Tone::Lose,
+            "\n
 ▓██   ██▓ ▒█████   █    ██     ██▓     ▒█████    ██████ ▓█████ 
  ▒██  ██▒▒██▒  ██▒ ██  ▓██▒   ▓██▒    ▒██▒  ██▒▒██    ▒ ▓█   ▀ 
   ▒██ ██░▒██░  ██▒▓██  ▒██░   ▒██░    ▒██░  ██▒░ ▓██▄   ▒███   
@@ -126,5 +278,6 @@ pub fn print_lose() {
  ▒ ▒ ░░  ░ ░ ░ ▒   ░░░ ░ ░      ░ ░   ░ ░ ░ ▒  ░  ░  ░     ░   
  ░ ░         ░ ░     ░            ░  ░    ░ ░        ░     ░  ░
  ░ ░                                                           "
+        )
     )
 }
@@ -0,0 +1,100 @@
+use crate::console::*;
+use crate::game::debrief::*;
+use crate::game::game_state::*;
+use crate::game::killer_user::*;
+use crate::game::victim_user::*;
+use crate::multiplayer::packets::*;
+use crate::theme::*;
+use crate::tr;
+use crate::util::*;
+
+/// Clear the screen and wait for the `player_type` player to take the keyboard.
+fn hand_over(console: &mut Console, player_type: PlayerType) {
+    clear_screen(console);
+    let message = match player_type {
+        PlayerType::Killer => tr!("versus.handoff.killer"),
+        PlayerType::Victim => tr!("versus.handoff.victim"),
+    };
+    writeln!(console, "{}", paint(console, Tone::Title, message));
+    read_str(console);
+    clear_screen(console);
+}
+
+/// Play the game in `state` with both players taking turns at the same keyboard, returning who
+/// won.
+///
+/// The screen is cleared between turns, so neither player sees the other's moves or the messages
+/// meant for them.
+pub fn play_local(console: &mut Console, state: &mut GameState) -> PlayerType {
+    let winner = loop {
+        hand_over(console, PlayerType::Victim);
+        let victim_move = play_victim(console, state);
+
+        hand_over(console, PlayerType::Killer);
+        let killer_move = play_killer(console, state);
+
+        match state.play(victim_move, killer_move).result {
+            RoundResult::Caught => break PlayerType::Killer,
+            RoundResult::AllPartsFound => break PlayerType::Victim,
+            _ => {}
+        }
+    };
+
+    // Both players can look now
+    clear_screen(console);
+    let (tone, message) = match winner {
+        PlayerType::Killer => (Tone::Lose, tr!("spectate.caught")),
+        PlayerType::Victim => (Tone::Win, tr!("spectate.all_parts_found")),
+    };
+    writeln!(console, "{}", paint(console, tone, message));
+    print_debrief(console, state, None);
+
+    winner
+}
+
+/// Play a game against someone else at the same keyboard.
+pub fn play_versus(console: &mut Console) {
+    let mut state = GameState::new();
+    state.gen_state();
+
+    play_local(console, &mut state);
+
+    // Return to title screen
+    writeln!(console, "{}", tr!("menu.return_to_title"));
+    read_str(console);
+}
+
+/// Testing for local games.
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Both players take turns, handing over the keyboard each time.
+    #[test]
+    fn local_versus() {
+        let mut state = GameState::new();
+        state.hide_part(0, 0);
+        state.hide_part(1, 0);
+
+        let (mut console, output) = Console::scripted(&[
+            "", "C B", "", "L D", "", "A B", "", "A B", "", "C K", "", "C K",
+        ]);
+        assert_eq!(play_local(&mut console, &mut state), PlayerType::Killer);
+
+        let text = output.text();
+        assert_eq!(text.matches("Pass the keyboard to the Killer").count(), 3);
+
+        // The screen can't be cleared when it isn't a terminal, so old turns are scrolled away
+        assert!(!text.contains('\x1b'));
+        assert!(text.contains(&"\n".repeat(CLEAR_SCREEN_LINES)));
+        assert!(text.contains("The killer caught the victim! The killer wins!"));
+
+        // Each player only hears about the part in their own turn
+        assert_eq!(text.matches("Nice! You found a car part!").count(), 1);
+        assert_eq!(
+            text.matches("The victim found a car part in the (C)abin")
+                .count(),
+            1
+        );
+    }
+}